@@ -0,0 +1,300 @@
+//! Command implementations: fetch what a command needs over RPC, build its
+//! instructions with the client crate, then either send them or simulate them
+//! for `--dry-run`. The builders take everything they need as arguments, so
+//! they also run without a cluster.
+
+use std::error::Error;
+
+use anchor_lang::AccountDeserialize;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFee;
+use serde_json::{json, Value};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+use prediction_market::{instruction, Market, MarketKind, MarketType, ResolverConfig, ShareOutcome, WinningOutcome};
+use prediction_market_client::{quote, rpc, MarketInstructions};
+
+use crate::{output, Cli, Command, CreateArgs, OutcomeArg, ResolveOutcome, TradeArgs};
+
+pub type CliResult<T> = Result<T, Box<dyn Error>>;
+
+pub fn run(cli: Cli) -> CliResult<Value> {
+    let keypair_path = match cli.keypair {
+        Some(path) => path,
+        None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+    };
+    let ctx = Context {
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        payer: read_keypair_file(&keypair_path).map_err(|err| format!("cannot read keypair {keypair_path}: {err}"))?,
+        dry_run: cli.dry_run,
+    };
+
+    match cli.command {
+        Command::Create(args) => create(&ctx, args),
+        Command::Buy(args) => buy(&ctx, args),
+        Command::Sell(args) => sell(&ctx, args),
+        Command::Resolve { market, outcome } => {
+            let (state, instructions) = ctx.load(&market)?;
+            let resolve = resolve_instruction(&ctx.signer(), &state, &instructions, outcome)?;
+            ctx.execute(vec![resolve], &market, Value::Null)
+        },
+        Command::EmergencyResolve { market, outcome } => {
+            let (state, instructions) = ctx.load(&market)?;
+            let resolve = instructions.emergency_resolve_market(&ctx.signer(), winning_outcome(&state, outcome)?);
+            ctx.execute(vec![resolve], &market, Value::Null)
+        },
+        Command::Redeem { market } => redeem(&ctx, &market),
+        Command::Show { market } => Ok(output::market(&market, &rpc::fetch_market(&ctx.rpc, &market)?)),
+        Command::List { creator } => {
+            let markets = rpc::list_markets(&ctx.rpc, creator.as_ref())?;
+            Ok(markets.iter().map(|(address, market)| output::market_summary(address, market)).collect())
+        },
+    }
+}
+
+struct Context {
+    rpc: RpcClient,
+    payer: Keypair,
+    dry_run: bool,
+}
+
+impl Context {
+    fn signer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Fetches a market and the builders for it.
+    fn load(&self, address: &Pubkey) -> CliResult<(Market, MarketInstructions)> {
+        let market = rpc::fetch_market(&self.rpc, address)?;
+        let token_program = rpc::token_program(&self.rpc, &market.collateral_mint)?;
+        let instructions = MarketInstructions::for_market(&market, token_program);
+        Ok((market, instructions))
+    }
+
+    /// Sends the instructions, or simulates them under `--dry-run`, and reports
+    /// `market` as it stands afterwards.
+    fn execute(&self, instructions: Vec<Instruction>, market: &Pubkey, quote: Value) -> CliResult<Value> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(&instructions, Some(&self.signer()), &[&self.payer], blockhash);
+
+        if !self.dry_run {
+            let signature = self.rpc.send_and_confirm_transaction(&tx)?;
+            let state = rpc::fetch_market(&self.rpc, market)?;
+            return Ok(json!({
+                "signature": signature.to_string(),
+                "quote": quote,
+                "market": output::market(market, &state),
+            }));
+        }
+
+        let config = RpcSimulateTransactionConfig {
+            replace_recent_blockhash: true,
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: vec![market.to_string()],
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let simulation = self.rpc.simulate_transaction_with_config(&tx, config)?.value;
+        let state = simulation
+            .accounts
+            .and_then(|accounts| accounts.into_iter().next().flatten())
+            .and_then(|account| account.data.decode())
+            .and_then(|data| Market::try_deserialize(&mut data.as_slice()).ok());
+        Ok(json!({
+            "dry_run": true,
+            "error": simulation.err.map(|err| err.to_string()),
+            "quote": quote,
+            "market": state.map_or(Value::Null, |state| output::market(market, &state)),
+            "logs": simulation.logs,
+        }))
+    }
+}
+
+fn create(ctx: &Context, args: CreateArgs) -> CliResult<Value> {
+    let token_program = rpc::token_program(&ctx.rpc, &args.collateral_mint)?;
+    let (instructions, create) = create_instruction(&ctx.signer(), token_program, args);
+    ctx.execute(vec![create], &instructions.market(), Value::Null)
+}
+
+/// The `create_market` instruction for `args`, and the builders for the market it creates.
+pub fn create_instruction(signer: &Pubkey, token_program: Pubkey, args: CreateArgs) -> (MarketInstructions, Instruction) {
+    let instructions = MarketInstructions::new(signer, args.market_id, args.collateral_mint, token_program);
+    let (market_type, resolution_source) = match args.oracle_feed {
+        Some(feed) => (MarketType::Oracle, feed),
+        None => (MarketType::Manual, *signer),
+    };
+    let create = instructions.create_market(instruction::CreateMarket {
+        market_id: args.market_id,
+        question: args.question,
+        end_timestamp: args.end_timestamp,
+        trading_close_timestamp: args.trading_close_timestamp,
+        market_type,
+        resolution_source,
+        resolver: args.resolver.map(ResolverConfig::Single),
+        initial_liquidity: args.initial_liquidity,
+        liquidity_parameter: args.liquidity_parameter,
+        fee_bps: args.fee_bps,
+        oracle_threashold: args.threshold,
+        oracle_threshold_expo: args.threshold_expo,
+        oracle_comparison: args.comparison.into(),
+        oracle_max_age: args.oracle_max_age,
+        oracle_max_conf_bps: args.oracle_max_conf_bps,
+    });
+    (instructions, create)
+}
+
+fn buy(ctx: &Context, args: TradeArgs) -> CliResult<Value> {
+    let (market, instructions) = ctx.load(&args.market)?;
+    let (ixs, quote) = buy_instructions(&ctx.signer(), &market, &instructions, &args)?;
+    ctx.execute(ixs, &args.market, quote)
+}
+
+/// Creates the signer's share accounts if needed and buys at the quoted price
+/// plus slippage, or at `--limit`. Also returns the quote as JSON.
+pub fn buy_instructions(user: &Pubkey, market: &Market, instructions: &MarketInstructions, args: &TradeArgs) -> CliResult<(Vec<Instruction>, Value)> {
+    let index = args.outcome.index();
+    let quote = quote::quote_buy(market, index as usize, args.shares)?;
+    let max_cost = match args.limit {
+        Some(limit) => limit,
+        None => quote.max_cost(args.slippage_bps)?,
+    };
+
+    let (mut ixs, trade) = match market.market_kind {
+        MarketKind::Categorical => (
+            create_token_accounts(user, instructions, &[instructions.addresses.outcome_mint(index)]),
+            instructions.buy_outcome_shares(user, index, max_cost, args.shares),
+        ),
+        MarketKind::Binary | MarketKind::Scalar => (
+            create_token_accounts(user, instructions, &[instructions.addresses.yes_mint, instructions.addresses.no_mint]),
+            instructions.buy_shares(user, share_outcome(args.outcome)?, max_cost, args.shares),
+        ),
+    };
+    ixs.push(trade);
+    Ok((ixs, output::buy_quote(&quote, max_cost)))
+}
+
+fn sell(ctx: &Context, args: TradeArgs) -> CliResult<Value> {
+    let (market, instructions) = ctx.load(&args.market)?;
+    let transfer_fee = rpc::transfer_fee(&ctx.rpc, &market.collateral_mint)?;
+    let (trade, quote) = sell_instruction(&ctx.signer(), &market, &instructions, &args, transfer_fee.as_ref())?;
+    ctx.execute(vec![trade], &args.market, quote)
+}
+
+/// Sells at the quoted payout minus slippage, or at `--limit`, net of the
+/// collateral mint's `transfer_fee`. Also returns the quote as JSON.
+pub fn sell_instruction(
+    user: &Pubkey,
+    market: &Market,
+    instructions: &MarketInstructions,
+    args: &TradeArgs,
+    transfer_fee: Option<&TransferFee>,
+) -> CliResult<(Instruction, Value)> {
+    let index = args.outcome.index();
+    let quote = quote::quote_sell(market, index as usize, args.shares, transfer_fee)?;
+    let min_payout = match args.limit {
+        Some(limit) => limit,
+        None => quote.min_payout(args.slippage_bps)?,
+    };
+
+    let trade = match market.market_kind {
+        MarketKind::Categorical => instructions.sell_outcome_shares(user, index, args.shares, min_payout),
+        MarketKind::Binary | MarketKind::Scalar => instructions.sell_shares(user, share_outcome(args.outcome)?, args.shares, min_payout),
+    };
+    Ok((trade, output::sell_quote(&quote, min_payout)))
+}
+
+/// Resolves from the oracle when `outcome` is omitted; manual markets need one.
+pub fn resolve_instruction(resolver: &Pubkey, market: &Market, instructions: &MarketInstructions, outcome: Option<ResolveOutcome>) -> CliResult<Instruction> {
+    if market.market_type == MarketType::Manual && outcome.is_none() {
+        return Err("manual markets need --outcome".into());
+    }
+    let outcome = outcome.map(|outcome| winning_outcome(market, outcome)).transpose()?;
+    Ok(instructions.resolve_market(resolver, market.resolution_source, outcome))
+}
+
+/// Maps `outcome` onto `market`: two-outcome markets take `yes`/`no` or index
+/// 0/1, categorical markets an index below their outcome count.
+pub fn winning_outcome(market: &Market, outcome: ResolveOutcome) -> CliResult<WinningOutcome> {
+    match (&market.market_kind, outcome) {
+        (_, ResolveOutcome::Canceled) => Ok(WinningOutcome::Canceled),
+        (MarketKind::Categorical, ResolveOutcome::Outcome(index)) if (index as usize) < market.outcome_mints.len() => Ok(WinningOutcome::Outcome(index)),
+        (MarketKind::Categorical, ResolveOutcome::Outcome(index)) => Err(format!("market has no outcome {index}").into()),
+        (MarketKind::Categorical, _) => Err("categorical markets resolve to an outcome index".into()),
+        (_, ResolveOutcome::Yes | ResolveOutcome::Outcome(0)) => Ok(WinningOutcome::Yes),
+        (_, ResolveOutcome::No | ResolveOutcome::Outcome(1)) => Ok(WinningOutcome::No),
+        (_, ResolveOutcome::Outcome(index)) => Err(format!("two-outcome markets have no outcome {index}").into()),
+    }
+}
+
+fn redeem(ctx: &Context, address: &Pubkey) -> CliResult<Value> {
+    let (market, instructions) = ctx.load(address)?;
+    let user = ctx.signer();
+    let balance = |mint: &Pubkey| {
+        ctx.rpc
+            .get_token_account_balance(&instructions.token_account(&user, mint))
+            .ok()
+            .and_then(|balance| balance.amount.parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    let (ixs, redeemed) = redeem_instructions(&user, &market, &instructions, balance)?;
+    ctx.execute(ixs, address, redeemed)
+}
+
+/// Redeems every token the signer holds, per `balance`, of each mint the
+/// resolution pays out on. Also returns what is redeemed as JSON.
+pub fn redeem_instructions(
+    user: &Pubkey,
+    market: &Market,
+    instructions: &MarketInstructions,
+    balance: impl Fn(&Pubkey) -> u64,
+) -> CliResult<(Vec<Instruction>, Value)> {
+    let addresses = &instructions.addresses;
+    let mints = match market.winning_outcome {
+        WinningOutcome::Undecided => return Err("market is not resolved".into()),
+        WinningOutcome::Yes => vec![addresses.yes_mint],
+        WinningOutcome::No => vec![addresses.no_mint],
+        WinningOutcome::Outcome(index) => vec![addresses.outcome_mint(index)],
+        WinningOutcome::Scalar => vec![addresses.yes_mint, addresses.no_mint],
+        WinningOutcome::Canceled => match market.market_kind {
+            MarketKind::Categorical => (0..market.outcome_mints.len() as u8).map(|index| addresses.outcome_mint(index)).collect(),
+            MarketKind::Binary | MarketKind::Scalar => vec![addresses.yes_mint, addresses.no_mint],
+        },
+    };
+
+    let mut ixs = create_token_accounts(user, instructions, &[instructions.collateral_mint]);
+    let mut redeemed = Vec::new();
+    for mint in mints {
+        let amount = balance(&mint);
+        if amount > 0 {
+            ixs.push(instructions.redeem_winnings(user, mint, amount));
+            redeemed.push(json!({ "mint": mint.to_string(), "amount": amount }));
+        }
+    }
+    if redeemed.is_empty() {
+        return Err("nothing to redeem".into());
+    }
+    Ok((ixs, json!({ "redeemed": redeemed })))
+}
+
+fn create_token_accounts(owner: &Pubkey, instructions: &MarketInstructions, mints: &[Pubkey]) -> Vec<Instruction> {
+    mints
+        .iter()
+        .map(|mint| create_associated_token_account_idempotent(owner, owner, mint, &instructions.token_program))
+        .collect()
+}
+
+fn share_outcome(outcome: OutcomeArg) -> CliResult<ShareOutcome> {
+    match outcome {
+        OutcomeArg::Yes | OutcomeArg::Index(0) => Ok(ShareOutcome::Yes),
+        OutcomeArg::No | OutcomeArg::Index(1) => Ok(ShareOutcome::No),
+        OutcomeArg::Index(index) => Err(format!("two-outcome markets have no outcome {index}").into()),
+    }
+}
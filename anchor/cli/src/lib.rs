@@ -0,0 +1,189 @@
+//! Argument definitions and command implementations of the `prediction-market`
+//! CLI, kept out of `main` so the tests can drive them.
+
+pub mod commands;
+pub mod output;
+
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use solana_sdk::pubkey::Pubkey;
+
+use prediction_market::OracleComparison;
+
+#[derive(Parser)]
+#[command(name = "prediction-market", version, about = "Operate prediction_market markets")]
+pub struct Cli {
+    /// JSON-RPC endpoint of the cluster.
+    #[arg(long, short = 'u', global = true, default_value = "http://127.0.0.1:8899")]
+    pub url: String,
+    /// Keypair file that pays for and signs every transaction. Defaults to the
+    /// Solana CLI's `~/.config/solana/id.json`.
+    #[arg(long, short = 'k', global = true)]
+    pub keypair: Option<String>,
+    /// Simulate instead of sending, and print the quote and the market as it
+    /// would be afterwards.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a binary market funded and owned by the signer.
+    Create(CreateArgs),
+    /// Buy shares of an outcome from the AMM.
+    Buy(TradeArgs),
+    /// Sell shares of an outcome to the AMM.
+    Sell(TradeArgs),
+    /// Resolve a market: from its oracle, or to `--outcome` for manual markets.
+    Resolve {
+        market: Pubkey,
+        #[arg(long)]
+        outcome: Option<ResolveOutcome>,
+    },
+    /// Resolve a market the oracle failed to resolve, once the emergency period is over.
+    EmergencyResolve { market: Pubkey, outcome: ResolveOutcome },
+    /// Redeem the signer's entire redeemable position in a resolved market.
+    Redeem { market: Pubkey },
+    /// Print a market.
+    Show { market: Pubkey },
+    /// Print every market, optionally only those of one creator.
+    List {
+        #[arg(long)]
+        creator: Option<Pubkey>,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct CreateArgs {
+    /// Creator-chosen id; the market address is derived from the signer and this id.
+    #[arg(long)]
+    pub market_id: u64,
+    #[arg(long)]
+    pub question: String,
+    #[arg(long)]
+    pub collateral_mint: Pubkey,
+    /// Unix timestamp the market ends at.
+    #[arg(long)]
+    pub end_timestamp: i64,
+    /// Unix timestamp trading closes at; defaults to `--end-timestamp`.
+    #[arg(long)]
+    pub trading_close_timestamp: Option<i64>,
+    /// Collateral the creator deposits into each side of the pool.
+    #[arg(long)]
+    pub initial_liquidity: u64,
+    /// LMSR depth `b`; larger is deeper and costs the creator more to fund.
+    #[arg(long)]
+    pub liquidity_parameter: u64,
+    /// Trading fee; defaults to the protocol default.
+    #[arg(long)]
+    pub fee_bps: Option<u16>,
+    /// Key allowed to resolve a manual market; defaults to the signer.
+    #[arg(long, conflicts_with = "oracle_feed")]
+    pub resolver: Option<Pubkey>,
+    /// Pyth price account that resolves the market; omit for a manual market.
+    #[arg(long, requires = "threshold")]
+    pub oracle_feed: Option<Pubkey>,
+    #[arg(long, requires = "oracle_feed")]
+    pub threshold: Option<i64>,
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub threshold_expo: i32,
+    #[arg(long, value_enum, default_value_t = Comparison::EqualOrAbove)]
+    pub comparison: Comparison,
+    /// Oldest oracle price, in seconds, accepted at resolution.
+    #[arg(long, default_value_t = 60)]
+    pub oracle_max_age: u64,
+    /// Widest oracle confidence interval, relative to the threshold, accepted at resolution.
+    #[arg(long, default_value_t = 100)]
+    pub oracle_max_conf_bps: u16,
+}
+
+#[derive(clap::Args)]
+pub struct TradeArgs {
+    pub market: Pubkey,
+    /// `yes`, `no`, or an outcome index of a categorical market.
+    pub outcome: OutcomeArg,
+    pub shares: u64,
+    /// Slippage allowed against the local quote when no explicit limit is given.
+    #[arg(long, default_value_t = 100)]
+    pub slippage_bps: u16,
+    /// Explicit `max_cost` (buy) or `min_payout` (sell), overriding `--slippage-bps`.
+    #[arg(long)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutcomeArg {
+    Yes,
+    No,
+    Index(u8),
+}
+
+impl OutcomeArg {
+    pub fn index(self) -> u8 {
+        match self {
+            OutcomeArg::Yes => 0,
+            OutcomeArg::No => 1,
+            OutcomeArg::Index(index) => index,
+        }
+    }
+}
+
+impl FromStr for OutcomeArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "yes" => Ok(OutcomeArg::Yes),
+            "no" => Ok(OutcomeArg::No),
+            index => index
+                .parse()
+                .map(OutcomeArg::Index)
+                .map_err(|_| format!("expected `yes`, `no` or an outcome index, got `{value}`")),
+        }
+    }
+}
+
+/// `yes`, `no`, `canceled`, or the winning outcome index of a categorical market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolveOutcome {
+    Yes,
+    No,
+    Canceled,
+    Outcome(u8),
+}
+
+impl FromStr for ResolveOutcome {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "yes" => Ok(ResolveOutcome::Yes),
+            "no" => Ok(ResolveOutcome::No),
+            "canceled" => Ok(ResolveOutcome::Canceled),
+            index => index
+                .parse()
+                .map(ResolveOutcome::Outcome)
+                .map_err(|_| format!("expected `yes`, `no`, `canceled` or an outcome index, got `{value}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Comparison {
+    Above,
+    Below,
+    EqualOrAbove,
+}
+
+impl From<Comparison> for OracleComparison {
+    fn from(comparison: Comparison) -> Self {
+        match comparison {
+            Comparison::Above => OracleComparison::Above,
+            Comparison::Below => OracleComparison::Below,
+            Comparison::EqualOrAbove => OracleComparison::EqualOrAbove,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! `prediction-market`: create, trade, resolve and inspect markets from the
+//! command line. Every command prints one JSON document on stdout.
+
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use prediction_market_cli::{commands, Cli};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match commands::run(cli) {
+        Ok(output) => {
+            println!("{output:#}");
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("{:#}", serde_json::json!({ "error": err.to_string() }));
+            ExitCode::FAILURE
+        },
+    }
+}
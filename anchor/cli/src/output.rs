@@ -0,0 +1,108 @@
+//! JSON rendering of program accounts and quotes. The program's types carry no
+//! serde impls, so the fields an operator cares about are mapped here by hand.
+
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+use prediction_market::{Market, MarketKind, MarketType, ResolverConfig, WinningOutcome};
+use prediction_market_client::quote::{self, BuyQuote, SellQuote};
+
+pub fn market(address: &Pubkey, market: &Market) -> Value {
+    let prices: Vec<Value> = (0..market.shares().len())
+        .map(|index| quote::price(market, index).map_or(Value::Null, Value::from))
+        .collect();
+    json!({
+        "address": address.to_string(),
+        "creator": market.creator.to_string(),
+        "market_id": market.market_id,
+        "question": market.question,
+        "kind": market_kind(&market.market_kind),
+        "type": market_type(&market.market_type),
+        "collateral_mint": market.collateral_mint.to_string(),
+        "resolution_source": market.resolution_source.to_string(),
+        "resolver": resolver(&market.resolver),
+        "end_timestamp": market.end_timestamp,
+        "trading_close_timestamp": market.trading_close_timestamp,
+        "paused": market.paused,
+        "resolved": market.resolved,
+        "winning_outcome": winning_outcome(&market.winning_outcome),
+        "shares_outstanding": market.shares(),
+        "prices": prices,
+        "total_liquidity": market.total_liquidity,
+        "liquidity_parameter": market.liquidity_parameter,
+        "fee_bps": market.fee_bps,
+        "creator_fees_accrued": market.creator_fees_accrued,
+        "protocol_fees_accrued": market.protocol_fees_accrued,
+    })
+}
+
+/// One line of `list`: enough to pick a market, `show` has the rest.
+pub fn market_summary(address: &Pubkey, market: &Market) -> Value {
+    json!({
+        "address": address.to_string(),
+        "creator": market.creator.to_string(),
+        "market_id": market.market_id,
+        "question": market.question,
+        "kind": market_kind(&market.market_kind),
+        "end_timestamp": market.end_timestamp,
+        "resolved": market.resolved,
+        "winning_outcome": winning_outcome(&market.winning_outcome),
+    })
+}
+
+pub fn buy_quote(quote: &BuyQuote, max_cost: u64) -> Value {
+    json!({
+        "cost": quote.cost,
+        "creator_fee": quote.creator_fee,
+        "protocol_fee": quote.protocol_fee,
+        "total_cost": quote.total_cost,
+        "max_cost": max_cost,
+    })
+}
+
+pub fn sell_quote(quote: &SellQuote, min_payout: u64) -> Value {
+    json!({
+        "payout": quote.payout,
+        "creator_fee": quote.creator_fee,
+        "protocol_fee": quote.protocol_fee,
+        "fee_transfer_cost": quote.fee_transfer_cost,
+        "net_payout": quote.net_payout,
+        "min_payout": min_payout,
+    })
+}
+
+pub fn winning_outcome(outcome: &WinningOutcome) -> Value {
+    match outcome {
+        WinningOutcome::Undecided => json!("undecided"),
+        WinningOutcome::Yes => json!("yes"),
+        WinningOutcome::No => json!("no"),
+        WinningOutcome::Canceled => json!("canceled"),
+        WinningOutcome::Outcome(index) => json!(index),
+        WinningOutcome::Scalar => json!("scalar"),
+    }
+}
+
+fn market_kind(kind: &MarketKind) -> &'static str {
+    match kind {
+        MarketKind::Binary => "binary",
+        MarketKind::Categorical => "categorical",
+        MarketKind::Scalar => "scalar",
+    }
+}
+
+fn market_type(market_type: &MarketType) -> &'static str {
+    match market_type {
+        MarketType::Oracle => "oracle",
+        MarketType::Manual => "manual",
+    }
+}
+
+fn resolver(resolver: &ResolverConfig) -> Value {
+    match resolver {
+        ResolverConfig::Single(key) => json!(key.to_string()),
+        ResolverConfig::Committee { members, threshold } => json!({
+            "members": members.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            "threshold": threshold,
+        }),
+    }
+}
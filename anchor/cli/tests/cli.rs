@@ -0,0 +1,228 @@
+//! Parses command lines the way `main` does and runs the instructions each
+//! command builds against the program in a `solana-program-test` bank.
+
+use std::collections::HashMap;
+
+use anchor_spl::token::spl_token;
+use clap::Parser;
+use prediction_market::{instruction, MarketType, ShareOutcome, WinningOutcome};
+use prediction_market_cli::commands::{self, CliResult};
+use prediction_market_cli::{output, Cli, Command, CreateArgs, OutcomeArg, ResolveOutcome, TradeArgs};
+use prediction_market_client::MarketInstructions;
+use prediction_market_test_utils::*;
+use solana_sdk::pubkey::Pubkey;
+
+fn parse(args: &[&str]) -> CliResult<Command> {
+    let argv = ["prediction-market"].iter().chain(args);
+    Ok(Cli::try_parse_from(argv)?.command)
+}
+
+fn create_args(args: &[&str]) -> CreateArgs {
+    match parse(&[&["create"], args].concat()).unwrap() {
+        Command::Create(args) => args,
+        _ => unreachable!(),
+    }
+}
+
+fn trade_args(args: &[&str]) -> TradeArgs {
+    match parse(args).unwrap() {
+        Command::Buy(args) | Command::Sell(args) => args,
+        _ => unreachable!(),
+    }
+}
+
+/// Creates a manual market through `create`, returning its funded creator.
+async fn setup() -> (TestEnv, User, MarketInstructions) {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(100_000_000).await;
+    let end = (env.now().await + DAY).to_string();
+    let mint = env.collateral_mint.to_string();
+    let args = create_args(&[
+        "--market-id", "3", "--question", "Will it rain tomorrow?", "--collateral-mint", &mint, "--end-timestamp", &end,
+        "--initial-liquidity", "1000000", "--liquidity-parameter", "1000000",
+    ]);
+    let (instructions, create) = commands::create_instruction(&creator.pubkey(), spl_token::ID, args);
+    env.send(vec![create], &[&creator.keypair]).await.unwrap();
+    (env, creator, instructions)
+}
+
+#[test]
+fn arguments_parse_into_commands() {
+    let market = Pubkey::new_unique();
+    let feed = Pubkey::new_unique().to_string();
+
+    let args = create_args(&[
+        "--market-id", "1", "--question", "BTC above 100k?", "--collateral-mint", &market.to_string(), "--end-timestamp", "1700000000",
+        "--initial-liquidity", "5", "--liquidity-parameter", "7", "--oracle-feed", &feed, "--threshold", "100000", "--threshold-expo", "-8",
+        "--comparison", "below",
+    ]);
+    assert_eq!(args.oracle_feed.unwrap().to_string(), feed);
+    assert_eq!((args.threshold, args.threshold_expo), (Some(100_000), -8));
+    assert_eq!((args.oracle_max_age, args.oracle_max_conf_bps, args.fee_bps, args.trading_close_timestamp), (60, 100, None, None));
+    let base = ["create", "--market-id", "1", "--question", "q", "--collateral-mint", &feed, "--end-timestamp", "1", "--initial-liquidity", "1", "--liquidity-parameter", "1"];
+    assert!(parse(&[&base[..], &["--threshold", "5"]].concat()).is_err());
+    assert!(parse(&[&base[..], &["--oracle-feed", &feed, "--threshold", "5", "--resolver", &feed]].concat()).is_err());
+
+    let buy = trade_args(&["buy", &market.to_string(), "yes", "1000"]);
+    assert_eq!((buy.market, buy.outcome, buy.shares, buy.slippage_bps, buy.limit), (market, OutcomeArg::Yes, 1_000, 100, None));
+    let sell = trade_args(&["sell", &market.to_string(), "2", "500", "--limit", "400"]);
+    assert_eq!((sell.outcome, sell.limit), (OutcomeArg::Index(2), Some(400)));
+    assert!(parse(&["buy", &market.to_string(), "maybe", "1"]).is_err());
+
+    let resolves = [
+        (vec![], None),
+        (vec!["--outcome", "No"], Some(ResolveOutcome::No)),
+        (vec!["--outcome", "canceled"], Some(ResolveOutcome::Canceled)),
+        (vec!["--outcome", "4"], Some(ResolveOutcome::Outcome(4))),
+    ];
+    for (flags, expected) in resolves {
+        match parse(&[&["resolve", &market.to_string()], &flags[..]].concat()).unwrap() {
+            Command::Resolve { market: parsed, outcome } => assert_eq!((parsed, outcome), (market, expected)),
+            _ => unreachable!(),
+        }
+    }
+    assert!(parse(&["resolve", &market.to_string(), "--outcome", "maybe"]).is_err());
+    assert!(matches!(parse(&["emergency-resolve", &market.to_string(), "yes"]).unwrap(), Command::EmergencyResolve { outcome: ResolveOutcome::Yes, .. }));
+    assert!(parse(&["emergency-resolve", &market.to_string()]).is_err());
+
+    assert!(matches!(parse(&["redeem", &market.to_string()]).unwrap(), Command::Redeem { market: parsed } if parsed == market));
+    assert!(matches!(parse(&["show", &market.to_string()]).unwrap(), Command::Show { market: parsed } if parsed == market));
+    assert!(matches!(parse(&["list"]).unwrap(), Command::List { creator: None }));
+    assert!(matches!(parse(&["list", "--creator", &market.to_string()]).unwrap(), Command::List { creator: Some(parsed) } if parsed == market));
+
+    let cli = Cli::try_parse_from(["prediction-market", "show", &market.to_string(), "--dry-run", "-u", "http://localhost:1"]).unwrap();
+    assert!(cli.dry_run);
+    assert_eq!(cli.url, "http://localhost:1");
+}
+
+#[tokio::test]
+async fn create_builds_the_market_it_describes() {
+    let (mut env, creator, instructions) = setup().await;
+    let market = env.market(&instructions.market()).await;
+    assert_eq!(instructions.market(), market_pda(&creator.pubkey(), 3));
+    assert_eq!(market.question, "Will it rain tomorrow?");
+    assert!(market.market_type == MarketType::Manual);
+    assert_eq!(market.resolution_source, creator.pubkey());
+
+    let shown = output::market(&instructions.market(), &market);
+    assert_eq!(shown["market_id"], 3);
+    assert_eq!(shown["type"], "manual");
+    assert_eq!(shown["winning_outcome"], "undecided");
+    let listed = output::market_summary(&instructions.market(), &market);
+    assert_eq!(listed["address"], instructions.market().to_string());
+}
+
+#[tokio::test]
+async fn trades_fill_at_the_quote_and_the_slippage_limit() {
+    let (mut env, creator, instructions) = setup().await;
+    let market = instructions.market().to_string();
+    let user = creator.pubkey();
+
+    let state = env.market(&instructions.market()).await;
+    let args = trade_args(&["buy", &market, "no", "200000"]);
+    let (buy, quote) = commands::buy_instructions(&user, &state, &instructions, &args).unwrap();
+    assert!(quote["max_cost"].as_u64().unwrap() > quote["total_cost"].as_u64().unwrap());
+    env.send(buy, &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&instructions.token_account(&user, &instructions.addresses.no_mint)).await, 200_000);
+
+    // An index beyond yes/no is rejected before anything is sent.
+    let args = trade_args(&["buy", &market, "2", "1"]);
+    assert!(commands::buy_instructions(&user, &state, &instructions, &args).is_err());
+
+    let state = env.market(&instructions.market()).await;
+    let args = trade_args(&["sell", &market, "1", "50000", "--limit", "1"]);
+    let (sell, quote) = commands::sell_instruction(&user, &state, &instructions, &args, None).unwrap();
+    assert_eq!(quote["min_payout"], 1);
+    assert_eq!(sell, instructions.sell_shares(&user, ShareOutcome::No, 50_000, 1));
+    env.send(vec![sell], &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&instructions.token_account(&user, &instructions.addresses.no_mint)).await, 150_000);
+}
+
+#[tokio::test]
+async fn resolve_and_redeem_pay_out_the_winning_side() {
+    let (mut env, creator, instructions) = setup().await;
+    let user = creator.pubkey();
+    let state = env.market(&instructions.market()).await;
+    let args = trade_args(&["buy", &instructions.market().to_string(), "yes", "300000"]);
+    let (buy, _) = commands::buy_instructions(&user, &state, &instructions, &args).unwrap();
+    env.send(buy, &[&creator.keypair]).await.unwrap();
+
+    assert!(commands::resolve_instruction(&user, &state, &instructions, None).is_err());
+    assert!(commands::resolve_instruction(&user, &state, &instructions, Some(ResolveOutcome::Outcome(2))).is_err());
+    env.warp(DAY + 1).await;
+    let resolve = commands::resolve_instruction(&user, &state, &instructions, Some(ResolveOutcome::Outcome(0))).unwrap();
+    env.send(vec![resolve], &[&creator.keypair]).await.unwrap();
+    let state = env.market(&instructions.market()).await;
+    assert!(state.winning_outcome == WinningOutcome::Yes);
+
+    let mut balances = HashMap::new();
+    for mint in [instructions.addresses.yes_mint, instructions.addresses.no_mint] {
+        balances.insert(mint, env.balance(&instructions.token_account(&user, &mint)).await);
+    }
+    let (redeem, redeemed) = commands::redeem_instructions(&user, &state, &instructions, |mint| balances[mint]).unwrap();
+    assert_eq!(redeemed["redeemed"][0]["amount"], 300_000);
+    assert_eq!(redeemed["redeemed"].as_array().unwrap().len(), 1);
+    let before = env.balance(&instructions.collateral_account(&user)).await;
+    env.send(redeem, &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&instructions.collateral_account(&user)).await, before + 300_000);
+
+    assert!(commands::redeem_instructions(&user, &state, &instructions, |_| 0).is_err());
+}
+
+#[tokio::test]
+async fn categorical_markets_resolve_to_an_outcome_index() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateCategoricalMarket {
+        market_id: 0,
+        question: "Who wins the league?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: creator.pubkey(),
+        resolver: None,
+        outcome_count: 3,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+    };
+    let mut create = vec![keys.create_ix(&creator, args)];
+    create.extend((0..3).map(|index| keys.init_outcome_mint_ix(&creator.pubkey(), index)));
+    env.send(create, &[&creator.keypair]).await.unwrap();
+
+    let state = env.market(&keys.market).await;
+    let instructions = MarketInstructions::for_market(&state, spl_token::ID);
+    let user = creator.pubkey();
+    for outcome in [ResolveOutcome::Yes, ResolveOutcome::Outcome(3)] {
+        assert!(commands::resolve_instruction(&user, &state, &instructions, Some(outcome)).is_err());
+    }
+
+    env.warp(DAY + 1).await;
+    let resolve = commands::resolve_instruction(&user, &state, &instructions, Some(ResolveOutcome::Outcome(2))).unwrap();
+    env.send(vec![resolve], &[&creator.keypair]).await.unwrap();
+    let state = env.market(&keys.market).await;
+    assert!(state.winning_outcome == WinningOutcome::Outcome(2));
+    assert_eq!(output::market(&keys.market, &state)["winning_outcome"], 2);
+}
+
+#[tokio::test]
+async fn emergency_resolve_maps_the_outcome_onto_the_market() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = env.create_market(&creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap();
+    let state = env.market(&keys.market).await;
+    let instructions = MarketInstructions::for_market(&state, spl_token::ID);
+
+    // Oracle markets need no outcome to resolve.
+    let resolve = commands::resolve_instruction(&creator.pubkey(), &state, &instructions, None).unwrap();
+    assert_eq!(resolve, instructions.resolve_market(&creator.pubkey(), feed, None));
+
+    env.warp(DAY + prediction_market::EMERGENCY_PERIOD + 1).await;
+    let outcome = commands::winning_outcome(&state, ResolveOutcome::Outcome(1)).unwrap();
+    let emergency = instructions.emergency_resolve_market(&creator.pubkey(), outcome);
+    env.send(vec![emergency], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::No);
+}
@@ -0,0 +1,708 @@
+//! Builders for every program instruction. Account lists come from the program's
+//! own `accounts` structs, so they cannot drift from what the program expects.
+//!
+//! Users' token accounts are taken to be their associated token accounts under the
+//! market's token program; build the `prediction_market::accounts` structs directly
+//! to use any other account.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+
+use prediction_market::{accounts, instruction, FeeRecipient, Market, MarketKind, OrderSide, ShareOutcome, WinningOutcome, ID};
+
+use crate::pda::{self, MarketAddresses};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn initialize_config(admin: &Pubkey, args: instruction::InitializeConfig) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            admin: *admin,
+            config: pda::config(),
+            program_data: pda::program_data(),
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_config(admin: &Pubkey, args: instruction::UpdateConfig) -> Instruction {
+    build(update_config_accounts(admin), args)
+}
+
+pub fn add_collateral_mint(admin: &Pubkey, mint: Pubkey) -> Instruction {
+    build(update_config_accounts(admin), instruction::AddCollateralMint { mint })
+}
+
+pub fn remove_collateral_mint(admin: &Pubkey, mint: Pubkey) -> Instruction {
+    build(update_config_accounts(admin), instruction::RemoveCollateralMint { mint })
+}
+
+pub fn set_paused(admin: &Pubkey, paused: bool) -> Instruction {
+    build(update_config_accounts(admin), instruction::SetPaused { paused })
+}
+
+pub fn propose_admin(admin: &Pubkey, new_admin: Pubkey) -> Instruction {
+    build(update_config_accounts(admin), instruction::ProposeAdmin { new_admin })
+}
+
+pub fn accept_admin(new_admin: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAdmin {
+            config: pda::config(),
+            new_admin: *new_admin,
+        },
+        instruction::AcceptAdmin {},
+    )
+}
+
+fn update_config_accounts(admin: &Pubkey) -> accounts::UpdateConfig {
+    accounts::UpdateConfig {
+        config: pda::config(),
+        admin: *admin,
+    }
+}
+
+/// Instruction builders for one market.
+#[derive(Clone, Copy, Debug)]
+pub struct MarketInstructions {
+    pub addresses: MarketAddresses,
+    pub collateral_mint: Pubkey,
+    /// Token program that owns the collateral mint; the market's own mints and
+    /// vaults are created under the same program.
+    pub token_program: Pubkey,
+    /// Categorical markets have no YES/NO mints.
+    pub categorical: bool,
+}
+
+impl MarketInstructions {
+    /// Builders for the two-outcome (binary or scalar) market `market_id` of `creator`.
+    pub fn new(creator: &Pubkey, market_id: u64, collateral_mint: Pubkey, token_program: Pubkey) -> Self {
+        Self {
+            addresses: MarketAddresses::new(creator, market_id),
+            collateral_mint,
+            token_program,
+            categorical: false,
+        }
+    }
+
+    /// Builders for the categorical market `market_id` of `creator`.
+    pub fn new_categorical(creator: &Pubkey, market_id: u64, collateral_mint: Pubkey, token_program: Pubkey) -> Self {
+        Self {
+            categorical: true,
+            ..Self::new(creator, market_id, collateral_mint, token_program)
+        }
+    }
+
+    /// Builders for an existing market, read from its account.
+    pub fn for_market(market: &Market, token_program: Pubkey) -> Self {
+        Self {
+            categorical: market.market_kind == MarketKind::Categorical,
+            ..Self::new(&market.creator, market.market_id, market.collateral_mint, token_program)
+        }
+    }
+
+    pub fn market(&self) -> Pubkey {
+        self.addresses.market
+    }
+
+    /// Associated token account of `owner` for `mint` under the market's token program.
+    pub fn token_account(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    pub fn collateral_account(&self, owner: &Pubkey) -> Pubkey {
+        self.token_account(owner, &self.collateral_mint)
+    }
+
+    pub fn share_mint(&self, outcome: &ShareOutcome) -> Pubkey {
+        match outcome {
+            ShareOutcome::Yes => self.addresses.yes_mint,
+            ShareOutcome::No => self.addresses.no_mint,
+        }
+    }
+
+    fn book_share_vault(&self, outcome: &ShareOutcome) -> Pubkey {
+        match outcome {
+            ShareOutcome::Yes => self.addresses.book_yes_vault,
+            ShareOutcome::No => self.addresses.book_no_vault,
+        }
+    }
+
+    pub fn create_market(&self, args: instruction::CreateMarket) -> Instruction {
+        build(self.create_accounts(), args)
+    }
+
+    pub fn create_scalar_market(&self, args: instruction::CreateScalarMarket) -> Instruction {
+        build(self.create_accounts(), args)
+    }
+
+    fn create_accounts(&self) -> accounts::CreateMarket {
+        let creator = &self.addresses.creator;
+        accounts::CreateMarket {
+            config: pda::config(),
+            creator: *creator,
+            market: self.addresses.market,
+            collateral_mint: self.collateral_mint,
+            yes_token_mint: self.addresses.yes_mint,
+            no_token_mint: self.addresses.no_mint,
+            market_authority: self.addresses.authority,
+            collateral_vault: self.addresses.collateral_vault,
+            creator_fee_vault: self.addresses.creator_fee_vault,
+            protocol_fee_vault: self.addresses.protocol_fee_vault,
+            lp_token_mint: self.addresses.lp_mint,
+            creator_lp_token_account: self.token_account(creator, &self.addresses.lp_mint),
+            creator_collateral_account: self.collateral_account(creator),
+            system_program: system_program::ID,
+            token_program: self.token_program,
+            associated_token_program: associated_token::ID,
+            rent: sysvar::rent::ID,
+        }
+    }
+
+    pub fn create_categorical_market(&self, args: instruction::CreateCategoricalMarket) -> Instruction {
+        let creator = &self.addresses.creator;
+        build(
+            accounts::CreateCategoricalMarket {
+                config: pda::config(),
+                creator: *creator,
+                market: self.addresses.market,
+                collateral_mint: self.collateral_mint,
+                market_authority: self.addresses.authority,
+                collateral_vault: self.addresses.collateral_vault,
+                creator_fee_vault: self.addresses.creator_fee_vault,
+                protocol_fee_vault: self.addresses.protocol_fee_vault,
+                lp_token_mint: self.addresses.lp_mint,
+                creator_lp_token_account: self.token_account(creator, &self.addresses.lp_mint),
+                creator_collateral_account: self.collateral_account(creator),
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+            },
+            args,
+        )
+    }
+
+    pub fn init_outcome_mint(&self, outcome_index: u8) -> Instruction {
+        build(
+            accounts::InitOutcomeMint {
+                config: pda::config(),
+                market: self.addresses.market,
+                creator: self.addresses.creator,
+                collateral_mint: self.collateral_mint,
+                outcome_mint: self.addresses.outcome_mint(outcome_index),
+                market_authority: self.addresses.authority,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                rent: sysvar::rent::ID,
+            },
+            instruction::InitOutcomeMint { outcome_index },
+        )
+    }
+
+    /// Buys from the AMM alone.
+    pub fn buy_shares(&self, user: &Pubkey, outcome: ShareOutcome, max_cost: u64, shares_desired: u64) -> Instruction {
+        self.buy(user, outcome, max_cost, shares_desired, false)
+    }
+
+    /// Buys from resting asks first and the AMM for the rest. `makers` own the
+    /// asks the buy fills completely, in fill order; those orders pay out to their
+    /// token accounts as they leave the book.
+    pub fn buy_shares_with_book(&self, user: &Pubkey, outcome: ShareOutcome, max_cost: u64, shares_desired: u64, makers: &[Pubkey]) -> Instruction {
+        let mut ix = self.buy(user, outcome.clone(), max_cost, shares_desired, true);
+        ix.accounts.extend(self.maker_accounts(&outcome, makers));
+        ix
+    }
+
+    fn buy(&self, user: &Pubkey, outcome: ShareOutcome, max_cost: u64, shares_desired: u64, book: bool) -> Instruction {
+        build(self.buy_accounts(user, &outcome, book), instruction::BuyShares { outcome, max_cost, shares_desired })
+    }
+
+    /// Spends at most `collateral_in`, fees included, on as many shares as it buys
+    /// from the AMM, failing below `min_shares_out`.
+    pub fn buy_with_collateral(&self, user: &Pubkey, outcome: ShareOutcome, collateral_in: u64, min_shares_out: u64) -> Instruction {
+        build(self.buy_accounts(user, &outcome, false), instruction::BuyWithCollateral { outcome, collateral_in, min_shares_out })
+    }
+
+    fn buy_accounts(&self, user: &Pubkey, outcome: &ShareOutcome, book: bool) -> accounts::BuyShares {
+        let (order_book, book_collateral_vault, book_share_vault) = self.book_accounts(outcome, book);
+        accounts::BuyShares {
+            config: pda::config(),
+            market: self.addresses.market,
+            user: *user,
+            user_collateral_account: self.collateral_account(user),
+            user_yes_token_account: Some(self.token_account(user, &self.addresses.yes_mint)),
+            user_no_token_account: Some(self.token_account(user, &self.addresses.no_mint)),
+            collateral_vault: self.addresses.collateral_vault,
+            creator_fee_vault: self.addresses.creator_fee_vault,
+            protocol_fee_vault: self.addresses.protocol_fee_vault,
+            yes_token_mint: self.addresses.yes_mint,
+            no_token_mint: self.addresses.no_mint,
+            order_book,
+            book_collateral_vault,
+            book_share_vault,
+            market_authority: self.addresses.authority,
+            collateral_mint: self.collateral_mint,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Sells to the AMM alone.
+    pub fn sell_shares(&self, user: &Pubkey, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64) -> Instruction {
+        self.sell(user, outcome, shares_to_sell, min_payout, false)
+    }
+
+    /// Sells into resting bids first and the AMM for the rest. `makers` own the
+    /// bids the sale fills completely, in fill order, as for `buy_shares_with_book`.
+    pub fn sell_shares_with_book(&self, user: &Pubkey, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64, makers: &[Pubkey]) -> Instruction {
+        let mut ix = self.sell(user, outcome.clone(), shares_to_sell, min_payout, true);
+        ix.accounts.extend(self.maker_accounts(&outcome, makers));
+        ix
+    }
+
+    fn sell(&self, user: &Pubkey, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64, book: bool) -> Instruction {
+        build(self.sell_accounts(user, &outcome, book), instruction::SellShares { outcome, shares_to_sell, min_payout })
+    }
+
+    /// Sells the fewest shares, at most `max_shares_in`, that net `collateral_out`
+    /// from the AMM after fees.
+    pub fn sell_for_collateral(&self, user: &Pubkey, outcome: ShareOutcome, collateral_out: u64, max_shares_in: u64) -> Instruction {
+        build(self.sell_accounts(user, &outcome, false), instruction::SellForCollateral { outcome, collateral_out, max_shares_in })
+    }
+
+    fn sell_accounts(&self, user: &Pubkey, outcome: &ShareOutcome, book: bool) -> accounts::SellShares {
+        let (order_book, book_collateral_vault, book_share_vault) = self.book_accounts(outcome, book);
+        accounts::SellShares {
+            config: pda::config(),
+            market: self.addresses.market,
+            user: *user,
+            user_collateral_account: self.collateral_account(user),
+            user_yes_token_account: self.token_account(user, &self.addresses.yes_mint),
+            user_no_token_account: self.token_account(user, &self.addresses.no_mint),
+            collateral_vault: self.addresses.collateral_vault,
+            creator_fee_vault: self.addresses.creator_fee_vault,
+            protocol_fee_vault: self.addresses.protocol_fee_vault,
+            yes_token_mint: self.addresses.yes_mint,
+            no_token_mint: self.addresses.no_mint,
+            order_book,
+            book_collateral_vault,
+            book_share_vault,
+            market_authority: self.addresses.authority,
+            collateral_mint: self.collateral_mint,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Each filled order's payout accounts: the owner's collateral account, then
+    /// their account for the traded outcome's token.
+    fn maker_accounts(&self, outcome: &ShareOutcome, makers: &[Pubkey]) -> Vec<AccountMeta> {
+        let share_mint = self.share_mint(outcome);
+        makers
+            .iter()
+            .flat_map(|maker| [AccountMeta::new(self.collateral_account(maker), false), AccountMeta::new(self.token_account(maker, &share_mint), false)])
+            .collect()
+    }
+
+    fn book_accounts(&self, outcome: &ShareOutcome, book: bool) -> (Option<Pubkey>, Option<Pubkey>, Option<Pubkey>) {
+        if !book {
+            return (None, None, None);
+        }
+        (Some(self.addresses.order_book), Some(self.addresses.book_collateral_vault), Some(self.book_share_vault(outcome)))
+    }
+
+    pub fn buy_outcome_shares(&self, user: &Pubkey, outcome_index: u8, max_cost: u64, shares_desired: u64) -> Instruction {
+        build(self.outcome_trade_accounts(user, outcome_index), instruction::BuyOutcomeShares { outcome_index, max_cost, shares_desired })
+    }
+
+    pub fn sell_outcome_shares(&self, user: &Pubkey, outcome_index: u8, shares_to_sell: u64, min_payout: u64) -> Instruction {
+        build(self.outcome_trade_accounts(user, outcome_index), instruction::SellOutcomeShares { outcome_index, shares_to_sell, min_payout })
+    }
+
+    fn outcome_trade_accounts(&self, user: &Pubkey, outcome_index: u8) -> accounts::TradeOutcomeShares {
+        let outcome_mint = self.addresses.outcome_mint(outcome_index);
+        accounts::TradeOutcomeShares {
+            config: pda::config(),
+            market: self.addresses.market,
+            user: *user,
+            user_collateral_account: self.collateral_account(user),
+            user_outcome_token_account: self.token_account(user, &outcome_mint),
+            collateral_vault: self.addresses.collateral_vault,
+            creator_fee_vault: self.addresses.creator_fee_vault,
+            protocol_fee_vault: self.addresses.protocol_fee_vault,
+            outcome_mint,
+            market_authority: self.addresses.authority,
+            collateral_mint: self.collateral_mint,
+            token_program: self.token_program,
+        }
+    }
+
+    pub fn init_order_book(&self, payer: &Pubkey) -> Instruction {
+        build(
+            accounts::InitOrderBook {
+                config: pda::config(),
+                market: self.addresses.market,
+                payer: *payer,
+                order_book: self.addresses.order_book,
+                collateral_mint: self.collateral_mint,
+                yes_token_mint: self.addresses.yes_mint,
+                no_token_mint: self.addresses.no_mint,
+                book_collateral_vault: self.addresses.book_collateral_vault,
+                book_yes_vault: self.addresses.book_yes_vault,
+                book_no_vault: self.addresses.book_no_vault,
+                market_authority: self.addresses.authority,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                rent: sysvar::rent::ID,
+            },
+            instruction::InitOrderBook {},
+        )
+    }
+
+    /// Bids escrow collateral; asks escrow shares of `outcome`.
+    pub fn place_order(&self, owner: &Pubkey, outcome: ShareOutcome, side: OrderSide, price: u64, shares: u64) -> Instruction {
+        let (mint, escrow_vault) = match side {
+            OrderSide::Bid => (self.collateral_mint, self.addresses.book_collateral_vault),
+            OrderSide::Ask => (self.share_mint(&outcome), self.book_share_vault(&outcome)),
+        };
+        build(
+            accounts::PlaceOrder {
+                config: pda::config(),
+                market: self.addresses.market,
+                order_book: self.addresses.order_book,
+                owner: *owner,
+                owner_token_account: self.token_account(owner, &mint),
+                escrow_vault,
+                mint,
+                token_program: self.token_program,
+            },
+            instruction::PlaceOrder { outcome, side, price, shares },
+        )
+    }
+
+    pub fn cancel_order(&self, owner: &Pubkey, outcome: ShareOutcome, order_id: u64) -> Instruction {
+        let share_mint = self.share_mint(&outcome);
+        build(
+            accounts::CancelOrder {
+                config: pda::config(),
+                market: self.addresses.market,
+                order_book: self.addresses.order_book,
+                owner: *owner,
+                owner_collateral_account: self.collateral_account(owner),
+                owner_share_account: self.token_account(owner, &share_mint),
+                book_collateral_vault: self.addresses.book_collateral_vault,
+                book_share_vault: self.book_share_vault(&outcome),
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                share_mint,
+                token_program: self.token_program,
+            },
+            instruction::CancelOrder { order_id },
+        )
+    }
+
+    pub fn mint_complete_set(&self, user: &Pubkey, amount: u64) -> Instruction {
+        build(
+            accounts::MintCompleteSet {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_yes_token_account: self.token_account(user, &self.addresses.yes_mint),
+                user_no_token_account: self.token_account(user, &self.addresses.no_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                yes_token_mint: self.addresses.yes_mint,
+                no_token_mint: self.addresses.no_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::MintCompleteSet { amount },
+        )
+    }
+
+    pub fn merge_complete_set(&self, user: &Pubkey, amount: u64) -> Instruction {
+        build(
+            accounts::MergeCompleteSet {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_yes_token_account: self.token_account(user, &self.addresses.yes_mint),
+                user_no_token_account: self.token_account(user, &self.addresses.no_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                yes_token_mint: self.addresses.yes_mint,
+                no_token_mint: self.addresses.no_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::MergeCompleteSet { amount },
+        )
+    }
+
+    pub fn add_liquidity(&self, user: &Pubkey, amount: u64, min_lp_tokens: u64) -> Instruction {
+        build(
+            accounts::AddLiquidity {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_yes_token_account: self.token_account(user, &self.addresses.yes_mint),
+                user_no_token_account: self.token_account(user, &self.addresses.no_mint),
+                user_lp_token_account: self.token_account(user, &self.addresses.lp_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                yes_token_mint: self.addresses.yes_mint,
+                no_token_mint: self.addresses.no_mint,
+                lp_token_mint: self.addresses.lp_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::AddLiquidity { amount, min_lp_tokens },
+        )
+    }
+
+    pub fn remove_liquidity(&self, user: &Pubkey, lp_tokens: u64, min_collateral: u64) -> Instruction {
+        build(
+            accounts::RemoveLiquidity {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_yes_token_account: self.token_account(user, &self.addresses.yes_mint),
+                user_no_token_account: self.token_account(user, &self.addresses.no_mint),
+                user_lp_token_account: self.token_account(user, &self.addresses.lp_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                yes_token_mint: self.addresses.yes_mint,
+                no_token_mint: self.addresses.no_mint,
+                lp_token_mint: self.addresses.lp_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::RemoveLiquidity { lp_tokens, min_collateral },
+        )
+    }
+
+    /// `resolution_source` is the market's Pyth feed for oracle markets and the
+    /// resolver itself for manual ones.
+    pub fn resolve_market(&self, resolver: &Pubkey, resolution_source: Pubkey, manual_outcome: Option<WinningOutcome>) -> Instruction {
+        build(
+            accounts::ResolveMarket {
+                config: pda::config(),
+                market: self.addresses.market,
+                resolution_source,
+                resolver: *resolver,
+            },
+            instruction::ResolveMarket { manual_outcome },
+        )
+    }
+
+    pub fn emergency_resolve_market(&self, resolver: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        build(
+            accounts::EmergencyResolveMarket {
+                config: pda::config(),
+                market: self.addresses.market,
+                resolver: *resolver,
+            },
+            instruction::EmergencyResolveMarket { outcome },
+        )
+    }
+
+    pub fn vote_resolution(&self, voter: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        build(
+            accounts::VoteResolution {
+                config: pda::config(),
+                market: self.addresses.market,
+                voter: *voter,
+            },
+            instruction::VoteResolution { outcome },
+        )
+    }
+
+    /// Redeems `amount` tokens of `winning_mint`: the winning share mint, or either
+    /// share mint once a market is canceled or scalar-resolved.
+    pub fn redeem_winnings(&self, user: &Pubkey, winning_mint: Pubkey, amount: u64) -> Instruction {
+        build(
+            accounts::RedeemWinnings {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_winning_token_account: self.token_account(user, &winning_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                winning_token_mint: winning_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::RedeemWinnings { amount },
+        )
+    }
+
+    pub fn withdraw_remaining_liquidity(&self, user: &Pubkey, lp_tokens: u64) -> Instruction {
+        let (yes_token_mint, no_token_mint) = match self.categorical {
+            true => (None, None),
+            false => (Some(self.addresses.yes_mint), Some(self.addresses.no_mint)),
+        };
+        build(
+            accounts::WithdrawRemainingLiquidity {
+                config: pda::config(),
+                market: self.addresses.market,
+                user: *user,
+                user_collateral_account: self.collateral_account(user),
+                user_lp_token_account: self.token_account(user, &self.addresses.lp_mint),
+                collateral_vault: self.addresses.collateral_vault,
+                yes_token_mint,
+                no_token_mint,
+                lp_token_mint: self.addresses.lp_mint,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::WithdrawRemainingLiquidity { lp_tokens },
+        )
+    }
+
+    pub fn claim_fees(&self, claimer: &Pubkey, recipient: FeeRecipient, destination: Pubkey) -> Instruction {
+        let fee_vault = match recipient {
+            FeeRecipient::Creator => self.addresses.creator_fee_vault,
+            FeeRecipient::Protocol => self.addresses.protocol_fee_vault,
+        };
+        build(
+            accounts::ClaimFees {
+                config: pda::config(),
+                market: self.addresses.market,
+                claimer: *claimer,
+                fee_vault,
+                destination,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::ClaimFees { recipient },
+        )
+    }
+
+    pub fn pause_market(&self) -> Instruction {
+        build(self.manage_accounts(), instruction::PauseMarket {})
+    }
+
+    pub fn unpause_market(&self) -> Instruction {
+        build(self.manage_accounts(), instruction::UnpauseMarket {})
+    }
+
+    pub fn cancel_market(&self) -> Instruction {
+        build(self.manage_accounts(), instruction::CancelMarket {})
+    }
+
+    fn manage_accounts(&self) -> accounts::ManageMarket {
+        accounts::ManageMarket {
+            config: pda::config(),
+            market: self.addresses.market,
+            creator: self.addresses.creator,
+        }
+    }
+
+    pub fn configure_dispute(&self, arbiter: Pubkey, bond_amount: u64, dispute_window: i64) -> Instruction {
+        build(
+            accounts::ConfigureDispute {
+                config: pda::config(),
+                market: self.addresses.market,
+                creator: self.addresses.creator,
+                collateral_mint: self.collateral_mint,
+                bond_vault: self.addresses.bond_vault,
+                market_authority: self.addresses.authority,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                rent: sysvar::rent::ID,
+            },
+            instruction::ConfigureDispute { arbiter, bond_amount, dispute_window },
+        )
+    }
+
+    pub fn propose_resolution(&self, bonder: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        build(self.bond_accounts(bonder), instruction::ProposeResolution { outcome })
+    }
+
+    pub fn dispute_resolution(&self, bonder: &Pubkey) -> Instruction {
+        build(self.bond_accounts(bonder), instruction::DisputeResolution {})
+    }
+
+    fn bond_accounts(&self, bonder: &Pubkey) -> accounts::PostBond {
+        accounts::PostBond {
+            config: pda::config(),
+            market: self.addresses.market,
+            bonder: *bonder,
+            bonder_collateral_account: self.collateral_account(bonder),
+            bond_vault: self.addresses.bond_vault,
+            collateral_mint: self.collateral_mint,
+            token_program: self.token_program,
+        }
+    }
+
+    /// `winner` is the bonder whose outcome stands and who collects the bonds.
+    pub fn finalize_resolution(&self, resolver: &Pubkey, winner: &Pubkey) -> Instruction {
+        build(self.settle_accounts(resolver, winner), instruction::FinalizeResolution {})
+    }
+
+    pub fn arbitrate_resolution(&self, arbiter: &Pubkey, winner: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        build(self.settle_accounts(arbiter, winner), instruction::ArbitrateResolution { outcome })
+    }
+
+    /// Cancels a dispute the arbiter never settled and returns both bonds.
+    pub fn expire_dispute(&self, caller: &Pubkey, proposer: &Pubkey, disputer: &Pubkey) -> Instruction {
+        build(
+            accounts::ExpireDispute {
+                config: pda::config(),
+                market: self.addresses.market,
+                caller: *caller,
+                proposer_collateral_account: self.collateral_account(proposer),
+                disputer_collateral_account: self.collateral_account(disputer),
+                bond_vault: self.addresses.bond_vault,
+                market_authority: self.addresses.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::ExpireDispute {},
+        )
+    }
+
+    fn settle_accounts(&self, resolver: &Pubkey, winner: &Pubkey) -> accounts::SettleResolution {
+        accounts::SettleResolution {
+            config: pda::config(),
+            market: self.addresses.market,
+            resolver: *resolver,
+            winner_collateral_account: self.collateral_account(winner),
+            bond_vault: self.addresses.bond_vault,
+            market_authority: self.addresses.authority,
+            collateral_mint: self.collateral_mint,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Read-only; simulate it and read the price from the return data.
+    pub fn get_market_price(&self, outcome: ShareOutcome) -> Instruction {
+        build(accounts::GetMarketPrice { market: self.addresses.market }, instruction::GetMarketPrice { outcome })
+    }
+
+    /// Read-only; simulate it and read a `TradeQuote` from the return data.
+    pub fn quote_trade(&self, outcome: ShareOutcome, side: OrderSide, shares: u64) -> Instruction {
+        build(accounts::GetMarketPrice { market: self.addresses.market }, instruction::QuoteTrade { outcome, side, shares })
+    }
+
+    /// Read-only; simulate it and read the price from the return data.
+    pub fn get_outcome_price(&self, outcome_index: u8) -> Instruction {
+        build(accounts::GetMarketPrice { market: self.addresses.market }, instruction::GetOutcomePrice { outcome_index })
+    }
+}
@@ -0,0 +1,15 @@
+//! Rust client for the `prediction_market` program: address derivation,
+//! instruction builders, account fetching and off-chain trade quotes.
+//!
+//! Account and instruction types are the program's own, re-exported from
+//! [`program`], so the client always matches the program it is built against.
+
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+pub mod rpc;
+
+pub use instructions::MarketInstructions;
+pub use pda::MarketAddresses;
+pub use prediction_market as program;
+pub use prediction_market::ID;
@@ -0,0 +1,125 @@
+//! Program-derived addresses. Every market account is derived from the creator
+//! and their market id, so a market can be addressed without fetching anything.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+
+use prediction_market::ID;
+
+pub fn config() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &ID).0
+}
+
+/// ProgramData account of the deployed program, whose upgrade authority may
+/// initialize the config.
+pub fn program_data() -> Pubkey {
+    Pubkey::find_program_address(&[ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+pub fn market(creator: &Pubkey, market_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"market", creator.as_ref(), &market_id.to_le_bytes()], &ID).0
+}
+
+/// Signer for the market's mints and vaults.
+pub fn market_authority(market: &Pubkey) -> Pubkey {
+    seeded(b"authority", market)
+}
+
+pub fn yes_mint(market: &Pubkey) -> Pubkey {
+    seeded(b"yes_mint", market)
+}
+
+pub fn no_mint(market: &Pubkey) -> Pubkey {
+    seeded(b"no_mint", market)
+}
+
+pub fn outcome_mint(market: &Pubkey, outcome_index: u8) -> Pubkey {
+    Pubkey::find_program_address(&[b"outcome_mint", market.as_ref(), &[outcome_index]], &ID).0
+}
+
+pub fn lp_mint(market: &Pubkey) -> Pubkey {
+    seeded(b"lp_mint", market)
+}
+
+pub fn collateral_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"collateral_vault", market)
+}
+
+pub fn creator_fee_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"creator_fees", market)
+}
+
+pub fn protocol_fee_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"protocol_fees", market)
+}
+
+pub fn bond_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"bond_vault", market)
+}
+
+pub fn order_book(market: &Pubkey) -> Pubkey {
+    seeded(b"order_book", market)
+}
+
+pub fn book_collateral_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"book_collateral", market)
+}
+
+pub fn book_yes_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"book_yes", market)
+}
+
+pub fn book_no_vault(market: &Pubkey) -> Pubkey {
+    seeded(b"book_no", market)
+}
+
+fn seeded(seed: &[u8], market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seed, market.as_ref()], &ID).0
+}
+
+/// Every fixed address of one market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketAddresses {
+    pub creator: Pubkey,
+    pub market_id: u64,
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub yes_mint: Pubkey,
+    pub no_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub creator_fee_vault: Pubkey,
+    pub protocol_fee_vault: Pubkey,
+    pub bond_vault: Pubkey,
+    pub order_book: Pubkey,
+    pub book_collateral_vault: Pubkey,
+    pub book_yes_vault: Pubkey,
+    pub book_no_vault: Pubkey,
+}
+
+impl MarketAddresses {
+    pub fn new(creator: &Pubkey, market_id: u64) -> Self {
+        let market = market(creator, market_id);
+        Self {
+            creator: *creator,
+            market_id,
+            market,
+            authority: market_authority(&market),
+            yes_mint: yes_mint(&market),
+            no_mint: no_mint(&market),
+            lp_mint: lp_mint(&market),
+            collateral_vault: collateral_vault(&market),
+            creator_fee_vault: creator_fee_vault(&market),
+            protocol_fee_vault: protocol_fee_vault(&market),
+            bond_vault: bond_vault(&market),
+            order_book: order_book(&market),
+            book_collateral_vault: book_collateral_vault(&market),
+            book_yes_vault: book_yes_vault(&market),
+            book_no_vault: book_no_vault(&market),
+        }
+    }
+
+    pub fn outcome_mint(&self, outcome_index: u8) -> Pubkey {
+        outcome_mint(&self.market, outcome_index)
+    }
+}
@@ -0,0 +1,90 @@
+//! Off-chain trade quotes. These run the program's own `math` module on a fetched
+//! `Market`, so a quote matches what the program charges or pays against the AMM
+//! as long as the market has not moved in between.
+//!
+//! Order book fills are not modelled: the book only fills asks at or below the
+//! AMM price and bids at or above it, so an AMM quote is a safe bound either way.
+
+use anchor_lang::Result;
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFee;
+
+use prediction_market::math;
+use prediction_market::{Market, MarketError, BPS_DENOMINATOR};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuyQuote {
+    /// Collateral paid into the AMM for the shares.
+    pub cost: u64,
+    pub creator_fee: u64,
+    pub protocol_fee: u64,
+    /// What the program checks against `max_cost`.
+    pub total_cost: u64,
+}
+
+impl BuyQuote {
+    /// `max_cost` allowing the price to move against the buyer by `slippage_bps`.
+    pub fn max_cost(&self, slippage_bps: u16) -> Result<u64> {
+        math::mul_div_ceil(self.total_cost, BPS_DENOMINATOR as u64 + slippage_bps as u64, BPS_DENOMINATOR as u64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SellQuote {
+    /// Collateral the AMM releases for the shares.
+    pub payout: u64,
+    pub creator_fee: u64,
+    pub protocol_fee: u64,
+    /// Token-2022 transfer fee on moving the trading fees, borne by the seller.
+    pub fee_transfer_cost: u64,
+    /// What the program checks against `min_payout`.
+    pub net_payout: u64,
+}
+
+impl SellQuote {
+    /// `min_payout` allowing the price to move against the seller by `slippage_bps`.
+    pub fn min_payout(&self, slippage_bps: u16) -> Result<u64> {
+        let kept_bps = BPS_DENOMINATOR.saturating_sub(slippage_bps);
+        math::mul_div_floor(self.net_payout, kept_bps as u64, BPS_DENOMINATOR as u64)
+    }
+}
+
+/// Quote for buying `shares` of outcome `index` (0 = YES, 1 = NO on two-outcome
+/// markets) from the AMM. Mirrors `buy_shares` and `buy_outcome_shares`.
+pub fn quote_buy(market: &Market, index: usize, shares: u64) -> Result<BuyQuote> {
+    let cost = math::buy_cost(&market.shares(), market.liquidity_parameter, index, shares)?;
+    let (creator_fee, protocol_fee) = math::calculate_fees(cost, market.fee_bps)?;
+    let total_cost = cost
+        .checked_add(creator_fee)
+        .and_then(|total| total.checked_add(protocol_fee))
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(BuyQuote { cost, creator_fee, protocol_fee, total_cost })
+}
+
+/// Quote for selling `shares` of outcome `index` to the AMM. Mirrors `sell_shares`
+/// and `sell_outcome_shares`; pass the collateral mint's current-epoch transfer
+/// fee for Token-2022 collateral that has one.
+pub fn quote_sell(market: &Market, index: usize, shares: u64, transfer_fee: Option<&TransferFee>) -> Result<SellQuote> {
+    let payout = math::sell_payout(&market.shares(), market.liquidity_parameter, index, shares)?;
+    let (creator_fee, protocol_fee) = math::calculate_fees(payout, market.fee_bps)?;
+    let fee_transfer_cost = inverse_transfer_fee(transfer_fee, creator_fee)?
+        .checked_add(inverse_transfer_fee(transfer_fee, protocol_fee)?)
+        .ok_or(MarketError::MathOverflow)?;
+    let net_payout = payout
+        .checked_sub(creator_fee)
+        .and_then(|net| net.checked_sub(protocol_fee))
+        .and_then(|net| net.checked_sub(fee_transfer_cost))
+        .ok_or(MarketError::MathUnderflow)?;
+    Ok(SellQuote { payout, creator_fee, protocol_fee, fee_transfer_cost, net_payout })
+}
+
+/// Spot price of outcome `index` in `PRICE_PRECISION` units, as `get_market_price` reports it.
+pub fn price(market: &Market, index: usize) -> Result<u64> {
+    math::price(&market.shares(), market.liquidity_parameter, index)
+}
+
+fn inverse_transfer_fee(transfer_fee: Option<&TransferFee>, amount: u64) -> Result<u64> {
+    match transfer_fee {
+        Some(fee) if amount > 0 => Ok(fee.calculate_inverse_fee(amount).ok_or(MarketError::MathOverflow)?),
+        _ => Ok(0),
+    }
+}
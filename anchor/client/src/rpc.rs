@@ -0,0 +1,106 @@
+//! Fetching program accounts over JSON-RPC.
+
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::token_interface::spl_token_2022::{
+    self,
+    extension::{transfer_fee::{TransferFee, TransferFeeConfig}, BaseStateWithExtensions, StateWithExtensions},
+};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as RpcError;
+use solana_rpc_client_api::config::RpcProgramAccountsConfig;
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+
+use prediction_market::{Config, Market, OrderBook, ID};
+
+use crate::pda;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Rpc(Box<RpcError>),
+    /// The account exists but does not hold what was asked for.
+    Account(anchor_lang::error::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Rpc(err) => write!(f, "rpc error: {err}"),
+            ClientError::Account(err) => write!(f, "account error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<RpcError> for ClientError {
+    fn from(err: RpcError) -> Self {
+        ClientError::Rpc(Box::new(err))
+    }
+}
+
+impl From<anchor_lang::error::Error> for ClientError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        ClientError::Account(err)
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// Fetches and deserializes an Anchor account, checking its discriminator.
+pub fn fetch<T: AccountDeserialize>(rpc: &RpcClient, address: &Pubkey) -> ClientResult<T> {
+    let data = rpc.get_account_data(address)?;
+    Ok(T::try_deserialize(&mut data.as_slice())?)
+}
+
+pub fn fetch_market(rpc: &RpcClient, address: &Pubkey) -> ClientResult<Market> {
+    fetch(rpc, address)
+}
+
+pub fn fetch_config(rpc: &RpcClient) -> ClientResult<Config> {
+    fetch(rpc, &pda::config())
+}
+
+pub fn fetch_order_book(rpc: &RpcClient, market: &Pubkey) -> ClientResult<OrderBook> {
+    fetch(rpc, &pda::order_book(market))
+}
+
+/// Every market of the program, optionally only those of one creator.
+pub fn list_markets(rpc: &RpcClient, creator: Option<&Pubkey>) -> ClientResult<Vec<(Pubkey, Market)>> {
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, Market::DISCRIMINATOR.to_vec()))];
+    if let Some(creator) = creator {
+        // `creator` is the first field after the discriminator.
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, creator.to_bytes().to_vec())));
+    }
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    rpc.get_program_accounts_with_config(&ID, config)?
+        .into_iter()
+        .map(|(address, account)| Ok((address, Market::try_deserialize(&mut account.data.as_slice())?)))
+        .collect()
+}
+
+/// Token program that owns `mint`, which the market's instructions must be built with.
+pub fn token_program(rpc: &RpcClient, mint: &Pubkey) -> ClientResult<Pubkey> {
+    Ok(rpc.get_account(mint)?.owner)
+}
+
+/// Transfer fee `mint` charges this epoch, if it is a Token-2022 mint with one.
+pub fn transfer_fee(rpc: &RpcClient, mint: &Pubkey) -> ClientResult<Option<TransferFee>> {
+    let account = rpc.get_account(mint)?;
+    if account.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+        .map_err(anchor_lang::error::Error::from)?;
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(None);
+    };
+    let epoch = rpc.get_epoch_info()?.epoch;
+    Ok(Some(*fee_config.get_epoch_fee(epoch)))
+}
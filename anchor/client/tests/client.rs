@@ -0,0 +1,76 @@
+//! Runs the client's builders and quotes against the program in a
+//! `solana-program-test` bank and checks they agree with what it charges.
+
+use anchor_spl::token::spl_token;
+use prediction_market::ShareOutcome;
+use prediction_market_client::{instructions, pda, quote, MarketInstructions};
+use prediction_market_test_utils::*;
+use solana_sdk::signer::Signer;
+
+/// Starts a bank with an initialized config and a manual market created through
+/// the client, returning the funded creator.
+async fn setup() -> (TestEnv, User, MarketInstructions) {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(100_000_000).await;
+    let market = MarketInstructions::new(&creator.pubkey(), 7, env.collateral_mint, spl_token::ID);
+
+    let now = env.now().await;
+    let create = market.create_market(manual_market_args(7, now, DAY, creator.pubkey()));
+    env.send(vec![create], &[&creator.keypair]).await.unwrap();
+    env.create_atas(&creator.pubkey(), &[market.addresses.yes_mint, market.addresses.no_mint]).await;
+    (env, creator, market)
+}
+
+#[tokio::test]
+async fn derived_addresses_match_the_created_market() {
+    let (mut env, creator, instructions) = setup().await;
+    let market = env.market(&instructions.market()).await;
+
+    assert_eq!(instructions.addresses.market, pda::market(&creator.pubkey(), 7));
+    assert_eq!(market.market_authority, pda::market_authority(&instructions.market()));
+    assert_eq!(market.yes_token_mint, instructions.addresses.yes_mint);
+    assert_eq!(market.no_token_mint, instructions.addresses.no_mint);
+    assert_eq!(market.collateral_vault, instructions.addresses.collateral_vault);
+    assert_eq!(market.lp_token_mint, instructions.addresses.lp_mint);
+    assert_eq!(market.creator_fee_vault, instructions.addresses.creator_fee_vault);
+    assert_eq!(market.protocol_fee_vault, instructions.addresses.protocol_fee_vault);
+
+    assert_eq!(pda::config(), config_pda());
+    assert_eq!(pda::program_data(), program_data_address());
+    let admin = env.admin.pubkey();
+    assert_eq!(instructions::initialize_config(&admin, config_args(&admin, env.collateral_mint)), env.initialize_config_ix());
+
+    let rebuilt = MarketInstructions::for_market(&market, spl_token::ID);
+    assert_eq!(rebuilt.addresses, instructions.addresses);
+    assert!(!rebuilt.categorical);
+}
+
+#[tokio::test]
+async fn quotes_match_what_the_program_charges_and_pays() {
+    let (mut env, creator, instructions) = setup().await;
+    let user = creator.pubkey();
+    let collateral = instructions.collateral_account(&user);
+
+    for (outcome, shares) in [(ShareOutcome::Yes, 250_000), (ShareOutcome::No, 900_000), (ShareOutcome::Yes, 1)] {
+        let market = env.market(&instructions.market()).await;
+        let buy = quote::quote_buy(&market, outcome.index(), shares).unwrap();
+
+        // One unit under the quote is rejected, the quote itself fills exactly.
+        let too_tight = instructions.buy_shares(&user, outcome.clone(), buy.total_cost - 1, shares);
+        assert!(env.send(vec![too_tight], &[&creator.keypair]).await.is_err());
+        let before = env.balance(&collateral).await;
+        env.send(vec![instructions.buy_shares(&user, outcome.clone(), buy.total_cost, shares)], &[&creator.keypair]).await.unwrap();
+        assert_eq!(before - env.balance(&collateral).await, buy.total_cost);
+    }
+
+    for (outcome, shares) in [(ShareOutcome::No, 400_000), (ShareOutcome::Yes, 250_001)] {
+        let market = env.market(&instructions.market()).await;
+        let sell = quote::quote_sell(&market, outcome.index(), shares, None).unwrap();
+
+        let too_greedy = instructions.sell_shares(&user, outcome.clone(), shares, sell.net_payout + 1);
+        assert!(env.send(vec![too_greedy], &[&creator.keypair]).await.is_err());
+        let before = env.balance(&collateral).await;
+        env.send(vec![instructions.sell_shares(&user, outcome.clone(), shares, sell.net_payout)], &[&creator.keypair]).await.unwrap();
+        assert_eq!(env.balance(&collateral).await - before, sell.net_payout);
+    }
+}
@@ -1,16 +1,46 @@
 #![allow(clippy::result_large_err)]
 #![allow(unexpected_cfgs)]
+#![allow(clippy::too_many_arguments)]
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount, transfer, Transfer, burn, mint_to, Burn, MintTo};
-use pyth_sdk_solana::{state::SolanaPriceAccount, PriceFeed};
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, transfer_checked, TransferChecked, burn, mint_to, Burn, MintTo};
+use anchor_spl::token_interface::spl_token_2022::{self, extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions}};
+use pyth_sdk_solana::{state::SolanaPriceAccount, Price, PriceFeed};
+
+pub mod math;
+
+use math::{calculate_fees, lmsr_cost, mul_div_ceil, mul_div_floor};
 
-pub const LIQUIDITY_PARAMETER: u64 = 1000;
 pub const MIN_PRICE: u64 = 10;
 pub const MAX_PRICE: u64 = 990;
 pub const PRICE_PRECISION: u64 = 1000;
 pub const EMERGENCY_PERIOD: i64 = 86400 * 7; // 7 days
 pub const ORACLE_GRACE_PERIOD: i64 = 86400 * 3; // 3 days
+pub const ARBITRATION_PERIOD: i64 = 86400 * 7; // 7 days
+pub const BPS_DENOMINATOR: u16 = 10_000;
+pub const MAX_FEE_BPS: u16 = 1_000; // 10%
+pub const PROTOCOL_FEE_SHARE_BPS: u16 = 2_000; // 20% of every trading fee
+/// Fixed-point scale used by the LMSR math (18 decimals).
+pub const FP_ONE: u128 = 1_000_000_000_000_000_000;
+/// ln(2) scaled by `FP_ONE`.
+pub const FP_LN2: u128 = 693_147_180_559_945_309;
+/// Scale of the per-token refund rates stored on canceled markets.
+pub const REFUND_RATE_PRECISION: u64 = 1_000_000_000;
+pub const MIN_CATEGORICAL_OUTCOMES: u8 = 3;
+/// Every trade prices all outcomes, so this bounds the LMSR work per instruction.
+pub const MAX_CATEGORICAL_OUTCOMES: u8 = 16;
+pub const MAX_COMMITTEE_MEMBERS: usize = 10;
+pub const MAX_ALLOWED_COLLATERAL_MINTS: usize = 16;
+/// Resting orders an order book can hold.
+pub const MAX_ORDERS: usize = 32;
+/// Smallest order, in whole units of collateral at the order's price, so that a
+/// few dust orders cannot take every slot in the book.
+pub const MIN_ORDER_VALUE: u64 = 1;
+/// Predicate evaluations an exact-collateral trade may spend sizing itself. Each
+/// one prices the trade on the curve; a close estimate settles in a handful.
+pub const MAX_SEARCH_EVALUATIONS: u32 = 24;
 
 
 declare_id!("9rHEF2zsthD6hz6Rt1kNDZAWtoNnSM1rBFYBu5fqSKFQ");
@@ -19,60 +49,167 @@ declare_id!("9rHEF2zsthD6hz6Rt1kNDZAWtoNnSM1rBFYBu5fqSKFQ");
 pub mod prediction_market {
     use super::*;
 
-    pub fn create_market(ctx: Context<CreateMarket>, question: String, end_timestamp: i64, market_type: MarketType, resolution_source: Pubkey, initial_liquidity: u64, oracle_threashold: Option<i64>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        market.creator = ctx.accounts.creator.key();
-        market.question = question;
-        market.end_timestamp = end_timestamp;
-        market.resolved = false;
-        market.winning_outcome = WinningOutcome::Undecided;
-        market.market_type = market_type.clone();
-        market.resolution_source = resolution_source;
-        market.oracle_threshold = oracle_threashold;
+    /// Creates the program-wide config. Only the program's upgrade authority can
+    /// do this, and only once; it becomes the first admin.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, treasury: Pubkey, default_fee_bps: u16, allowed_collateral_mints: Vec<Pubkey>, min_market_duration: i64, max_market_duration: i64) -> Result<()> {
+        require!(
+            ctx.accounts.program_data.upgrade_authority_address == Some(ctx.accounts.admin.key()),
+            MarketError::UnauthorizedAdmin
+        );
 
-        market.collateral_mint = ctx.accounts.collateral_mint.key();
-        market.yes_token_mint = ctx.accounts.yes_token_mint.key();
-        market.no_token_mint = ctx.accounts.no_token_mint.key();
-        market.collateral_vault = ctx.accounts.collateral_vault.key();
-        market.market_authority = ctx.accounts.market_authority.key();
-        market.bump = ctx.bumps.market_authority;
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.pending_admin = None;
+        config.paused = false;
+        config.bump = ctx.bumps.config;
+        config.apply(treasury, default_fee_bps, allowed_collateral_mints, min_market_duration, max_market_duration)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, treasury: Pubkey, default_fee_bps: u16, allowed_collateral_mints: Vec<Pubkey>, min_market_duration: i64, max_market_duration: i64) -> Result<()> {
+        ctx.accounts.config.apply(treasury, default_fee_bps, allowed_collateral_mints, min_market_duration, max_market_duration)
+    }
+
+    /// Allows `mint` as collateral for new markets.
+    pub fn add_collateral_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(!config.allowed_collateral_mints.contains(&mint), MarketError::InvalidConfig);
+        require!(config.allowed_collateral_mints.len() < MAX_ALLOWED_COLLATERAL_MINTS, MarketError::InvalidConfig);
+        config.allowed_collateral_mints.push(mint);
+        Ok(())
+    }
+
+    /// Stops new markets from using `mint`. Existing markets are unaffected.
+    pub fn remove_collateral_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let position = config.allowed_collateral_mints
+            .iter()
+            .position(|allowed| *allowed == mint)
+            .ok_or(MarketError::CollateralNotAllowed)?;
+        config.allowed_collateral_mints.remove(position);
+        Ok(())
+    }
+
+    /// Halts (or resumes) every market instruction program-wide.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.paused = paused;
+
+        let clock = Clock::get()?;
+        emit!(ProtocolPauseChanged {
+            admin: config.admin,
+            paused,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// First step of an admin handover; the new admin has to accept it.
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.config.pending_admin = Some(new_admin);
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let new_admin = ctx.accounts.new_admin.key();
+        require!(config.pending_admin == Some(new_admin), MarketError::UnauthorizedAdmin);
 
-        market.yes_shares_outstanding = initial_liquidity;
-        market.no_shares_outstanding = initial_liquidity;
-        market.total_liquidity = initial_liquidity * 2;
+        let previous_admin = config.admin;
+        config.admin = new_admin;
+        config.pending_admin = None;
 
+        let clock = Clock::get()?;
+        emit!(AdminTransferred {
+            previous_admin,
+            new_admin,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn create_market(ctx: Context<CreateMarket>, market_id: u64, question: String, end_timestamp: i64, trading_close_timestamp: Option<i64>, market_type: MarketType, resolution_source: Pubkey, resolver: Option<ResolverConfig>, initial_liquidity: u64, liquidity_parameter: u64, fee_bps: Option<u16>, oracle_threashold: Option<i64>, oracle_threshold_expo: i32, oracle_comparison: OracleComparison, oracle_max_age: u64, oracle_max_conf_bps: u16) -> Result<()> {
         if market_type == MarketType::Oracle {
             require!(oracle_threashold.is_some(), MarketError::OracleThresholdRequired);
+            require!(oracle_max_age > 0, MarketError::InvalidOracleConfig);
+            require!(oracle_max_conf_bps > 0 && oracle_max_conf_bps <= BPS_DENOMINATOR, MarketError::InvalidOracleConfig);
         }
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.creator_collateral_account.to_account_info(),
-            to: ctx.accounts.collateral_vault.to_account_info(),
-            authority: ctx.accounts.creator.to_account_info()
-        };
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, initial_liquidity * 2)?;
+        let resolver = resolver.unwrap_or(ResolverConfig::Single(ctx.accounts.creator.key()));
+        validate_resolver(&resolver)?;
 
-        Ok(())
+        let market = &mut ctx.accounts.market;
+        market.market_kind = MarketKind::Binary;
+        market.resolver = resolver;
+        market.oracle_threshold = oracle_threashold;
+        market.oracle_threshold_expo = oracle_threshold_expo;
+        market.oracle_comparison = oracle_comparison;
+        market.oracle_max_age = oracle_max_age;
+        market.oracle_max_conf_bps = oracle_max_conf_bps;
+
+        open_market(ctx.accounts, ctx.bumps.market_authority, market_id, question, end_timestamp, trading_close_timestamp, market_type, resolution_source, initial_liquidity, liquidity_parameter, fee_bps)
+    }
+
+    /// Creates an oracle market over the range `[lower_bound, upper_bound]` (at
+    /// `bound_expo`). The YES and NO mints act as LONG and SHORT: at resolution the
+    /// oracle price is clamped into the range and LONG is paid
+    /// `(price - lower) / (upper - lower)` of a unit, SHORT the rest.
+    pub fn create_scalar_market(ctx: Context<CreateMarket>, market_id: u64, question: String, end_timestamp: i64, trading_close_timestamp: Option<i64>, resolution_source: Pubkey, lower_bound: i64, upper_bound: i64, bound_expo: i32, initial_liquidity: u64, liquidity_parameter: u64, fee_bps: Option<u16>, oracle_max_age: u64, oracle_max_conf_bps: u16) -> Result<()> {
+        require!(lower_bound < upper_bound, MarketError::InvalidScalarBounds);
+        require!(oracle_max_age > 0, MarketError::InvalidOracleConfig);
+        require!(oracle_max_conf_bps > 0 && oracle_max_conf_bps <= BPS_DENOMINATOR, MarketError::InvalidOracleConfig);
+
+        let market = &mut ctx.accounts.market;
+        market.market_kind = MarketKind::Scalar;
+        market.resolver = ResolverConfig::Single(ctx.accounts.creator.key());
+        market.scalar_lower_bound = lower_bound;
+        market.scalar_upper_bound = upper_bound;
+        market.oracle_threshold = None;
+        market.oracle_threshold_expo = bound_expo;
+        market.oracle_max_age = oracle_max_age;
+        market.oracle_max_conf_bps = oracle_max_conf_bps;
+
+        open_market(ctx.accounts, ctx.bumps.market_authority, market_id, question, end_timestamp, trading_close_timestamp, MarketType::Oracle, resolution_source, initial_liquidity, liquidity_parameter, fee_bps)
+    }
+
+    pub fn buy_shares<'info>(ctx: Context<'_, '_, '_, 'info, BuyShares<'info>>, outcome: ShareOutcome, max_cost: u64, shares_desired: u64) -> Result<()> {
+        execute_buy(ctx, outcome, max_cost, shares_desired, u64::MAX)
     }
 
-    pub fn buy_shares(ctx: Context<BuyShares>, outcome: ShareOutcome, max_cost: u64, shares_desired: u64) -> Result<()> {
+    pub fn sell_shares<'info>(ctx: Context<'_, '_, '_, 'info, SellShares<'info>>, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64) -> Result<()> {
         let market = &mut ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
         require!(!market.resolved, MarketError::MarketResolved);
-        require!(shares_desired > 0, MarketError::ZeroAmount);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(shares_to_sell > 0, MarketError::ZeroAmount);
+
+        let (user_share_account, share_mint) = match outcome {
+            ShareOutcome::Yes => (&ctx.accounts.user_yes_token_account, &ctx.accounts.yes_token_mint),
+            ShareOutcome::No => (&ctx.accounts.user_no_token_account, &ctx.accounts.no_token_mint),
+        };
 
-        let actual_cost = calculate_buy_cost(market, &outcome, shares_desired)?;
-        require!(actual_cost <= max_cost, MarketError::SlippageExceeded);
+        // Resting bids priced at or above the AMM quote are filled first; only the
+        // remainder trades against the AMM. Book fills are peer-to-peer and fee-free.
+        let (book_shares, book_payout, filled_orders) = match ctx.accounts.order_book.as_mut() {
+            Some(order_book) => {
+                let amm_price = calculate_price(market, outcome.index())?;
+                match_orders(order_book, &outcome, OrderSide::Ask, shares_to_sell, amm_price)?
+            },
+            None => (0, 0, Vec::new()),
+        };
+        let amm_shares = shares_to_sell - book_shares;
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_collateral_account.to_account_info(),
-            to: ctx.accounts.collateral_vault.to_account_info(),
-            authority: ctx.accounts.user.to_account_info()
+        let payout = if amm_shares > 0 {
+            calculate_sell_payout(market, outcome.index(), amm_shares)?
+        } else {
+            0
         };
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, actual_cost)?;
+        let (creator_fee, protocol_fee, net_payout) = split_sell_payout(&TransferFeeSchedule::load(&ctx.accounts.collateral_mint)?, payout, market.fee_bps)?;
+        require!(net_payout + book_payout >= min_payout, MarketError::SlippageExceeded);
 
         let market_key = market.key();
         let authority_seeds = &[
@@ -82,98 +219,280 @@ pub mod prediction_market {
         ];
         let signer_seeds = &[&authority_seeds[..]];
 
-        match outcome {
-            ShareOutcome::Yes => {
-                let user_yes_account = ctx.accounts.user_yes_token_account
-                .as_ref()
-                .ok_or(MarketError::InvalidTokenAccount)?;
+        if book_shares > 0 {
+            let (book_collateral_vault, book_share_vault) = book_vaults(
+                ctx.accounts.order_book.as_deref(),
+                ctx.accounts.book_collateral_vault.as_deref(),
+                ctx.accounts.book_share_vault.as_deref(),
+                &outcome,
+            )?;
 
-                market.yes_shares_outstanding = market.yes_shares_outstanding
-                    .checked_add(shares_desired)
-                    .ok_or(MarketError::MathOverflow)?;
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: user_share_account.to_account_info(),
+                mint: share_mint.to_account_info(),
+                to: book_share_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer_checked(cpi_ctx, book_shares, share_mint.decimals)?;
+
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: book_collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.user_collateral_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, book_payout, ctx.accounts.collateral_mint.decimals)?;
+
+            pay_filled_orders(
+                market.key(),
+                &filled_orders,
+                ctx.remaining_accounts,
+                book_collateral_vault,
+                book_share_vault,
+                &ctx.accounts.collateral_mint,
+                share_mint,
+                &ctx.accounts.market_authority,
+                &ctx.accounts.token_program,
+                signer_seeds,
+            )?;
+        }
+
+        if amm_shares > 0 {
+            match outcome {
+                ShareOutcome::Yes => {
+                    market.yes_shares_outstanding = market.yes_shares_outstanding
+                        .checked_sub(amm_shares)
+                        .ok_or(MarketError::MathUnderflow)?;
+                },
+                ShareOutcome::No => {
+                    market.no_shares_outstanding = market.no_shares_outstanding
+                        .checked_sub(amm_shares)
+                        .ok_or(MarketError::MathUnderflow)?;
+                },
+            }
+
+            let burn_accounts = Burn {
+                mint: share_mint.to_account_info(),
+                from: user_share_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+            burn(burn_ctx, amm_shares)?;
+
+            // Update total liquidity
+            market.total_liquidity = market.total_liquidity
+                .checked_sub(payout)
+                .ok_or(MarketError::MathUnderflow)?;
+
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let transfer_accounts = TransferChecked {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.user_collateral_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+            transfer_checked(transfer_ctx, net_payout, ctx.accounts.collateral_mint.decimals)?;
 
+            if creator_fee > 0 {
                 let cpi_program = ctx.accounts.token_program.to_account_info();
-                let cpi_accounts = MintTo {
-                    mint: ctx.accounts.yes_token_mint.to_account_info(),
-                    to: user_yes_account.to_account_info(),
+                let transfer_accounts = TransferChecked {
+                    from: ctx.accounts.collateral_vault.to_account_info(),
+                    mint: ctx.accounts.collateral_mint.to_account_info(),
+                    to: ctx.accounts.creator_fee_vault.to_account_info(),
                     authority: ctx.accounts.market_authority.to_account_info()
                 };
-                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-                mint_to(cpi_ctx, shares_desired)?;
-            },
-            ShareOutcome::No => {
-                let user_no_account = ctx.accounts.user_no_token_account
-                .as_ref()
-                .ok_or(MarketError::InvalidTokenAccount)?;
-
-                market.no_shares_outstanding = market.no_shares_outstanding
-                    .checked_add(shares_desired)
-                    .ok_or(MarketError::MathOverflow)?;
+                let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+                transfer_checked(transfer_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, creator_fee)?, ctx.accounts.collateral_mint.decimals)?;
+            }
 
+            if protocol_fee > 0 {
                 let cpi_program = ctx.accounts.token_program.to_account_info();
-                let cpi_accounts = MintTo {
-                    mint: ctx.accounts.no_token_mint.to_account_info(),
-                    to: user_no_account.to_account_info(),
+                let transfer_accounts = TransferChecked {
+                    from: ctx.accounts.collateral_vault.to_account_info(),
+                    mint: ctx.accounts.collateral_mint.to_account_info(),
+                    to: ctx.accounts.protocol_fee_vault.to_account_info(),
                     authority: ctx.accounts.market_authority.to_account_info()
                 };
-                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-                mint_to(cpi_ctx, shares_desired)?;
+                let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+                transfer_checked(transfer_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, protocol_fee)?, ctx.accounts.collateral_mint.decimals)?;
             }
+
+            market.creator_fees_accrued = market.creator_fees_accrued
+                .checked_add(creator_fee)
+                .ok_or(MarketError::MathOverflow)?;
+            market.protocol_fees_accrued = market.protocol_fees_accrued
+                .checked_add(protocol_fee)
+                .ok_or(MarketError::MathOverflow)?;
         }
 
-        market.total_liquidity = market.total_liquidity
-            .checked_add(actual_cost)
-            .ok_or(MarketError::MathOverflow)?;
+        let clock = Clock::get()?;
+        emit!(SharesSold {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            outcome,
+            shares: shares_to_sell,
+            book_shares,
+            payout: net_payout + book_payout,
+            fee: creator_fee + protocol_fee,
+            yes_price: calculate_price(market, ShareOutcome::Yes.index())?,
+            no_price: calculate_price(market, ShareOutcome::No.index())?,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    pub fn sell_shares(ctx: Context<SellShares>, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+    /// Exact-input buy: debits at most `collateral_in`, fees and any collateral
+    /// transfer fee included, for the largest number of `outcome` shares that budget
+    /// affords against the AMM curve. The buy trades with the AMM alone: an order
+    /// book passed in is left untouched, since asks priced off the curve would leave
+    /// part of the budget unspent.
+    pub fn buy_with_collateral<'info>(ctx: Context<'_, '_, '_, 'info, BuyShares<'info>>, outcome: ShareOutcome, collateral_in: u64, min_shares_out: u64) -> Result<()> {
+        ctx.accounts.order_book = None;
+        let market = &ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(collateral_in > 0, MarketError::ZeroAmount);
+
+        let transfer_fees = TransferFeeSchedule::load(&ctx.accounts.collateral_mint)?;
+        let shares = affordable_shares(market, &transfer_fees, outcome.index(), collateral_in)?;
+        require!(shares >= min_shares_out, MarketError::SlippageExceeded);
+        execute_buy(ctx, outcome, collateral_in, shares, collateral_in)
+    }
+
+    /// Exact-output sell: sells the fewest `outcome` shares, at most `max_shares_in`,
+    /// that net at least `collateral_out` from the AMM after fees. Like
+    /// `buy_with_collateral` it trades with the AMM alone and ignores any order book.
+    pub fn sell_for_collateral<'info>(ctx: Context<'_, '_, '_, 'info, SellShares<'info>>, outcome: ShareOutcome, collateral_out: u64, max_shares_in: u64) -> Result<()> {
+        ctx.accounts.order_book = None;
+        let market = &ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(collateral_out > 0, MarketError::ZeroAmount);
+
+        let transfer_fees = TransferFeeSchedule::load(&ctx.accounts.collateral_mint)?;
+        let shares = shares_to_sell_for(market, &transfer_fees, outcome.index(), collateral_out, max_shares_in)?
+            .ok_or(MarketError::SlippageExceeded)?;
+        sell_shares(ctx, outcome, shares, collateral_out)
+    }
+
+    /// Creates the market's order book and the escrow vaults resting orders lock
+    /// their collateral (bids) or outcome tokens (asks) in.
+    pub fn init_order_book(ctx: Context<InitOrderBook>) -> Result<()> {
+        require!(ctx.accounts.market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+
+        let order_book = &mut ctx.accounts.order_book;
+        order_book.market = ctx.accounts.market.key();
+        order_book.collateral_vault = ctx.accounts.book_collateral_vault.key();
+        order_book.yes_vault = ctx.accounts.book_yes_vault.key();
+        order_book.no_vault = ctx.accounts.book_no_vault.key();
+        order_book.next_order_id = 0;
+        order_book.bump = ctx.bumps.order_book;
+        order_book.orders = Vec::new();
+        Ok(())
+    }
+
+    /// Rests a limit order on the book. Bids escrow `ceil(shares * price)` collateral,
+    /// asks escrow the outcome tokens themselves. `price` uses `PRICE_PRECISION`.
+    pub fn place_order(ctx: Context<PlaceOrder>, outcome: ShareOutcome, side: OrderSide, price: u64, shares: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
         require!(!market.resolved, MarketError::MarketResolved);
-        require!(shares_to_sell > 0, MarketError::ZeroAmount);
-    
-        let payout = calculate_sell_payout(market, &outcome, shares_to_sell)?;
-        require!(payout >= min_payout, MarketError::SlippageExceeded);
-    
-        // First, update the market state and burn tokens
-        match outcome {
-            ShareOutcome::Yes => {
-                market.yes_shares_outstanding = market.yes_shares_outstanding
-                    .checked_sub(shares_to_sell)
-                    .ok_or(MarketError::MathUnderflow)?;
-    
-                let burn_accounts = Burn {
-                    mint: ctx.accounts.yes_token_mint.to_account_info(),
-                    from: ctx.accounts.user_yes_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                };
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
-                burn(burn_ctx, shares_to_sell)?;
-            },
-            ShareOutcome::No => {
-                market.no_shares_outstanding = market.no_shares_outstanding
-                    .checked_sub(shares_to_sell)
-                    .ok_or(MarketError::MathUnderflow)?;
-    
-                let burn_accounts = Burn {
-                    mint: ctx.accounts.no_token_mint.to_account_info(),
-                    from: ctx.accounts.user_no_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                };
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
-                burn(burn_ctx, shares_to_sell)?;
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(shares > 0, MarketError::ZeroAmount);
+        require!((MIN_PRICE..=MAX_PRICE).contains(&price), MarketError::InvalidOrderPrice);
+        let min_value = 10u64
+            .checked_pow(ctx.accounts.mint.decimals.into())
+            .and_then(|unit| unit.checked_mul(MIN_ORDER_VALUE))
+            .ok_or(MarketError::MathOverflow)?;
+        require!(mul_div_floor(shares, price, PRICE_PRECISION)? >= min_value, MarketError::OrderTooSmall);
+
+        let order_book = &mut ctx.accounts.order_book;
+        require!(order_book.orders.len() < MAX_ORDERS, MarketError::OrderBookFull);
+
+        let (escrow_mint, escrow_vault, escrow_amount) = match side {
+            OrderSide::Bid => (market.collateral_mint, order_book.collateral_vault, mul_div_ceil(shares, price, PRICE_PRECISION)?),
+            OrderSide::Ask => match outcome {
+                ShareOutcome::Yes => (market.yes_token_mint, order_book.yes_vault, shares),
+                ShareOutcome::No => (market.no_token_mint, order_book.no_vault, shares),
             },
-        }
-    
-        // Update total liquidity
-        market.total_liquidity = market.total_liquidity
-            .checked_sub(payout)
-            .ok_or(MarketError::MathUnderflow)?;
-    
-        // Transfer payout to user
+        };
+        require!(ctx.accounts.owner_token_account.mint == escrow_mint, MarketError::InvalidTokenMint);
+        require!(ctx.accounts.mint.key() == escrow_mint, MarketError::InvalidTokenMint);
+        require!(ctx.accounts.escrow_vault.key() == escrow_vault, MarketError::InvalidTokenAccount);
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.escrow_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.mint, escrow_amount)?, ctx.accounts.mint.decimals)?;
+
+        let order_id = order_book.next_order_id;
+        order_book.next_order_id = order_id
+            .checked_add(1)
+            .ok_or(MarketError::MathOverflow)?;
+        order_book.orders.push(Order {
+            id: order_id,
+            owner: ctx.accounts.owner.key(),
+            outcome: outcome.clone(),
+            side: side.clone(),
+            price,
+            remaining_shares: shares,
+            escrowed_collateral: if side == OrderSide::Bid { escrow_amount } else { 0 },
+            proceeds_collateral: 0,
+            proceeds_shares: 0,
+        });
+
+        let clock = Clock::get()?;
+        emit!(OrderPlaced {
+            market: market.key(),
+            owner: ctx.accounts.owner.key(),
+            order_id,
+            outcome,
+            side,
+            price,
+            shares,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Removes an order from the book and pays its owner everything it holds: the
+    /// unfilled escrow plus whatever fills have credited to it. An order a trade
+    /// fills completely is paid out by that trade; a partly filled one stays on the
+    /// book until its owner cancels it this way, even after resolution.
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let order_book = &mut ctx.accounts.order_book;
+        let position = order_book.orders
+            .iter()
+            .position(|order| order.id == order_id)
+            .ok_or(MarketError::OrderNotFound)?;
+        require!(order_book.orders[position].owner == ctx.accounts.owner.key(), MarketError::UnauthorizedOrderOwner);
+        let order = order_book.orders.remove(position);
+
+        let (share_mint, share_vault) = match order.outcome {
+            ShareOutcome::Yes => (market.yes_token_mint, order_book.yes_vault),
+            ShareOutcome::No => (market.no_token_mint, order_book.no_vault),
+        };
+        require!(ctx.accounts.owner_share_account.mint == share_mint, MarketError::InvalidTokenMint);
+        require!(ctx.accounts.share_mint.key() == share_mint, MarketError::InvalidTokenMint);
+        require!(ctx.accounts.book_collateral_vault.key() == order_book.collateral_vault, MarketError::InvalidTokenAccount);
+        require!(ctx.accounts.book_share_vault.key() == share_vault, MarketError::InvalidTokenAccount);
+
+        let (collateral_out, shares_out) = order_payout(&order)?;
+
         let market_key = market.key();
         let authority_seeds = &[
             b"authority",
@@ -181,106 +500,117 @@ pub mod prediction_market {
             &[market.bump]
         ];
         let signer_seeds = &[&authority_seeds[..]];
-    
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let transfer_accounts = Transfer {
-            from: ctx.accounts.collateral_vault.to_account_info(),
-            to: ctx.accounts.user_collateral_account.to_account_info(),
-            authority: ctx.accounts.market_authority.to_account_info()
-        };
-        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
-        transfer(transfer_ctx, payout)?;
-    
+
+        if collateral_out > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.book_collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.owner_collateral_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, collateral_out, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        if shares_out > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.book_share_vault.to_account_info(),
+                mint: ctx.accounts.share_mint.to_account_info(),
+                to: ctx.accounts.owner_share_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, shares_out, ctx.accounts.share_mint.decimals)?;
+        }
+
+        let clock = Clock::get()?;
+        emit!(OrderCanceled {
+            market: market.key(),
+            owner: ctx.accounts.owner.key(),
+            order_id,
+            collateral_out,
+            shares_out,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn resolve_market(ctx: Context<ResolveMarket>, manual_outcome: Option<WinningOutcome>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+    /// Creates a manually resolved market with `outcome_count` mutually exclusive
+    /// outcomes priced by the same LMSR. The outcome mints are created afterwards
+    /// with `init_outcome_mint`, one per index, before trading opens.
+    pub fn create_categorical_market(ctx: Context<CreateCategoricalMarket>, market_id: u64, question: String, end_timestamp: i64, trading_close_timestamp: Option<i64>, resolution_source: Pubkey, resolver: Option<ResolverConfig>, outcome_count: u8, initial_liquidity: u64, liquidity_parameter: u64, fee_bps: Option<u16>) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(end_timestamp > clock.unix_timestamp, MarketError::InvalidEndTimestamp);
+        let trading_close_timestamp = trading_close_timestamp.unwrap_or(end_timestamp);
+        require!(trading_close_timestamp > clock.unix_timestamp && trading_close_timestamp <= end_timestamp, MarketError::InvalidTradingCloseTimestamp);
+        require!((MIN_CATEGORICAL_OUTCOMES..=MAX_CATEGORICAL_OUTCOMES).contains(&outcome_count), MarketError::InvalidOutcomeCount);
+        ctx.accounts.config.check_new_market(&ctx.accounts.collateral_mint.key(), clock.unix_timestamp, end_timestamp)?;
+        let fee_bps = fee_bps.unwrap_or(ctx.accounts.config.default_fee_bps);
+        let resolver = resolver.unwrap_or(ResolverConfig::Single(ctx.accounts.creator.key()));
+        validate_resolver(&resolver)?;
 
-        let clock = Clock::get()?.unix_timestamp;
-        require!(clock > market.end_timestamp, MarketError::MarketNotEnd);
+        let market = &mut ctx.accounts.market;
+        market.creator = ctx.accounts.creator.key();
+        market.market_id = market_id;
+        market.question = question;
+        market.end_timestamp = end_timestamp;
+        market.trading_close_timestamp = trading_close_timestamp;
+        market.resolved = false;
+        market.winning_outcome = WinningOutcome::Undecided;
+        market.market_type = MarketType::Manual;
+        market.market_kind = MarketKind::Categorical;
+        market.resolver = resolver;
+        market.resolution_source = resolution_source;
+        market.oracle_threshold = None;
 
-        match market.market_type {
-            MarketType::Oracle => {
-                let price_feed: PriceFeed   = SolanaPriceAccount::account_info_to_feed(&ctx.accounts.resolution_source)
-                    .map_err(|_| error!(MarketError::InvalidOracleFeed))?;
+        market.collateral_mint = ctx.accounts.collateral_mint.key();
+        market.yes_token_mint = Pubkey::default();
+        market.no_token_mint = Pubkey::default();
+        market.collateral_vault = ctx.accounts.collateral_vault.key();
+        market.market_authority = ctx.accounts.market_authority.key();
+        market.bump = ctx.bumps.market_authority;
 
-                let current_price = price_feed.get_price_unchecked();
-                require!(current_price.price > 0, MarketError::OraclePriceStale);
-            },
-            MarketType::Manual => {
-                require!(ctx.accounts.resolver.key() == market.creator, MarketError::UnauthorizedResolver);
-                require!(manual_outcome.is_some(), MarketError::OutcomeRequired);
-                market.winning_outcome = manual_outcome.unwrap();
-            }
-        }
+        require!(fee_bps <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        market.fee_bps = fee_bps;
+        market.creator_fee_vault = ctx.accounts.creator_fee_vault.key();
+        market.protocol_fee_vault = ctx.accounts.protocol_fee_vault.key();
+        market.creator_fees_accrued = 0;
+        market.protocol_fees_accrued = 0;
 
-        market.resolved = true;
-        Ok(())
-    }
+        require!(initial_liquidity > 0, MarketError::ZeroAmount);
+        let funding = initial_liquidity
+            .checked_mul(outcome_count as u64)
+            .ok_or(MarketError::MathOverflow)?;
 
-    pub fn redeem_winnings(ctx: Context<RedeemWinnings>, amount: u64) -> Result<()> {
-        let market = &ctx.accounts.market;
-        require!(market.resolved, MarketError::MarketNotResolved);
-        require!(amount > 0, MarketError::ZeroAmount);
+        // With N outcomes the LMSR can lose at most `C(0) = b * ln(N)`.
+        require!(liquidity_parameter > 0, MarketError::InvalidLiquidityParameter);
+        let max_loss = lmsr_cost(&vec![0; outcome_count as usize], liquidity_parameter)?;
+        let funded = (funding as u128)
+            .checked_mul(FP_ONE)
+            .ok_or(MarketError::MathOverflow)?;
+        require!(max_loss <= funded, MarketError::InsufficientLiquidity);
+        market.liquidity_parameter = liquidity_parameter;
 
-        match market.winning_outcome {
-            WinningOutcome::Yes => {
-                require!(ctx.accounts.user_winning_token_account.mint == market.yes_token_mint, MarketError::InvalidTokenMint);
-            },
-            WinningOutcome::No => {
-                require!(ctx.accounts.user_winning_token_account.mint == market.no_token_mint, MarketError::InvalidTokenMint);
-            },
-            WinningOutcome::Canceled => {
-                // For canceled markets, allow redemption of both YES and NO tokens
-                // at proportional rate based on original liquidity
-                let is_yes_token = ctx.accounts.user_winning_token_account.mint == market.yes_token_mint;
-                let is_no_token = ctx.accounts.user_winning_token_account.mint == market.no_token_mint;
-                require!(is_yes_token || is_no_token, MarketError::InvalidTokenMint);
-                
-                // Calculate proportional refund
-                let total_original_shares = market.yes_shares_outstanding + market.no_shares_outstanding;
-                let refund_amount = (amount * market.total_liquidity) / total_original_shares;
-                
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let burn_accounts = Burn {
-                    mint: ctx.accounts.winning_token_mint.to_account_info(),
-                    from: ctx.accounts.user_winning_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info()
-                };
-                let burn_ctx = CpiContext::new(cpi_program.clone(), burn_accounts);
-                burn(burn_ctx, amount)?;
-
-                let market_key = market.key();
-                let authority_seeds = &[
-                    b"authority",
-                    market_key.as_ref(),
-                    &[market.bump]
-                ];
-                let signer_seeds = &[&authority_seeds[..]];
-
-                let transfer_accounts = Transfer {
-                    from: ctx.accounts.collateral_vault.to_account_info(),
-                    to: ctx.accounts.user_collateral_account.to_account_info(),
-                    authority: ctx.accounts.market_authority.to_account_info(),
-                };
-                let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
-                transfer(transfer_ctx, refund_amount)?;
-                
-                return Ok(());
-            },
-            WinningOutcome::Undecided => return err!(MarketError::MarketNotResolved),
-        }
+        market.outcome_mints = Vec::with_capacity(outcome_count as usize);
+        market.outcome_shares = vec![0; outcome_count as usize];
+        market.canceled_outcome_refund_rates = Vec::new();
+        market.total_liquidity = funding;
 
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let burn_accounts = Burn {
-            mint: ctx.accounts.winning_token_mint.to_account_info(),
-            from: ctx.accounts.user_winning_token_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info()
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.creator_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.creator.to_account_info()
         };
-        let burn_ctx = CpiContext::new(cpi_program.clone(), burn_accounts);
-        burn(burn_ctx, amount)?;
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, funding)?, ctx.accounts.collateral_mint.decimals)?;
+
+        market.lp_token_mint = ctx.accounts.lp_token_mint.key();
 
         let market_key = market.key();
         let authority_seeds = &[
@@ -290,270 +620,3290 @@ pub mod prediction_market {
         ];
         let signer_seeds = &[&authority_seeds[..]];
 
-        let transfer_accounts = Transfer {
-            from: ctx.accounts.collateral_vault.to_account_info(),
-            to: ctx.accounts.user_collateral_account.to_account_info(),
-            authority: ctx.accounts.market_authority.to_account_info(),
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.lp_token_mint.to_account_info(),
+            to: ctx.accounts.creator_lp_token_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
         };
-        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
-        transfer(transfer_ctx, amount)?;
-        
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, funding)?;
+
+        emit!(MarketCreated {
+            market: market.key(),
+            creator: market.creator,
+            market_id: market.market_id,
+            question: market.question.clone(),
+            market_type: market.market_type.clone(),
+            end_timestamp: market.end_timestamp,
+            collateral_mint: market.collateral_mint,
+            initial_liquidity: funding,
+            liquidity_parameter: market.liquidity_parameter,
+            fee_bps: market.fee_bps,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn emergency_resolve_market(ctx: Context<EmergencyResolveMarket>, outcome: WinningOutcome) -> Result<()> {
+    /// Creates the mint for the next outcome of a categorical market. Mints are
+    /// PDAs of the market and outcome index and have to be created in order.
+    pub fn init_outcome_mint(ctx: Context<InitOutcomeMint>, outcome_index: u8) -> Result<()> {
         let market = &mut ctx.accounts.market;
-        require!(!market.resolved, MarketError::MarketAlreadyResolved);
-        require!(outcome != WinningOutcome::Undecided, MarketError::InvalidOutcome);
-
-        let clock = Clock::get()?.unix_timestamp;
-        require!(clock > market.end_timestamp + EMERGENCY_PERIOD, MarketError::EmergencyPeriodNotReached);
+        require!(market.market_kind == MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(ctx.accounts.creator.key() == market.creator, MarketError::UnauthorizedResolver);
+        require!(
+            outcome_index as usize == market.outcome_mints.len() && market.outcome_mints.len() < market.outcome_shares.len(),
+            MarketError::InvalidOutcomeIndex
+        );
 
-        require!(ctx.accounts.resolver.key() == market.creator, MarketError::UnauthorizedResolver);
-
-        market.winning_outcome = outcome;
-        market.resolved = true;
+        market.outcome_mints.push(ctx.accounts.outcome_mint.key());
         Ok(())
     }
 
-    pub fn get_market_price(ctx: Context<GetMarketPrice>, outcome: ShareOutcome) -> Result<u64> {
-        let market = &ctx.accounts.market;
-        calculate_price(market, &outcome)
-    }
-}
+    pub fn buy_outcome_shares(ctx: Context<TradeOutcomeShares>, outcome_index: u8, max_cost: u64, shares_desired: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind == MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(market.outcome_mints.len() == market.outcome_shares.len(), MarketError::OutcomeMintsPending);
+        require!(market.outcome_mints.get(outcome_index as usize) == Some(&ctx.accounts.outcome_mint.key()), MarketError::InvalidTokenMint);
+        require!(!market.resolved, MarketError::MarketResolved);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(shares_desired > 0, MarketError::ZeroAmount);
 
-fn calculate_price(market: &Market, outcome: &ShareOutcome) -> Result<u64> {
-    let total_shares = market.yes_shares_outstanding
-        .checked_add(market.no_shares_outstanding)
-        .ok_or(MarketError::MathOverflow)?;
+        let index = outcome_index as usize;
+        let actual_cost = calculate_buy_cost(market, index, shares_desired)?;
+        let (creator_fee, protocol_fee) = calculate_fees(actual_cost, market.fee_bps)?;
+        let total_cost = actual_cost
+            .checked_add(creator_fee)
+            .and_then(|cost| cost.checked_add(protocol_fee))
+            .ok_or(MarketError::MathOverflow)?;
+        require!(total_cost <= max_cost, MarketError::SlippageExceeded);
 
-    require!(total_shares > 0, MarketError::MathOverflow);
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, actual_cost)?, ctx.accounts.collateral_mint.decimals)?;
 
-    let price = match outcome {
-        ShareOutcome::Yes => {
-            //Yes price = no_shares / (yes_shares + no_shares)
-            (market.no_shares_outstanding * PRICE_PRECISION) / total_shares
-        },
-        ShareOutcome::No => {
-            //No price = yes_shares / (yes_shares + no_shares)
-            (market.yes_shares_outstanding * PRICE_PRECISION) / total_shares
+        if creator_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_collateral_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.creator_fee_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, creator_fee)?, ctx.accounts.collateral_mint.decimals)?;
         }
-    };
-
-    let bounded_price = price.max(MIN_PRICE).min(MAX_PRICE);
-    Ok(bounded_price)
-}
 
-fn calculate_buy_cost(market: &Market, outcome: &ShareOutcome, shares: u64) -> Result<u64> {
-    let current_price = calculate_price(market, outcome)?;
-    let price_impact = shares * PRICE_PRECISION / LIQUIDITY_PARAMETER;
-    let adjusted_price = current_price + price_impact;
+        if protocol_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_collateral_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.protocol_fee_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, protocol_fee)?, ctx.accounts.collateral_mint.decimals)?;
+        }
 
-    let cost = (shares * adjusted_price) / PRICE_PRECISION;
-    Ok(cost)
-}
+        market.creator_fees_accrued = market.creator_fees_accrued
+            .checked_add(creator_fee)
+            .ok_or(MarketError::MathOverflow)?;
+        market.protocol_fees_accrued = market.protocol_fees_accrued
+            .checked_add(protocol_fee)
+            .ok_or(MarketError::MathOverflow)?;
 
-fn calculate_sell_payout(market: &Market, outcome: &ShareOutcome, shares: u64) -> Result<u64> {
-    let current_price = calculate_price(market, outcome)?;
-    let price_impact = shares * PRICE_PRECISION / LIQUIDITY_PARAMETER;
-    let adjusted_price = current_price.saturating_sub(price_impact);
+        market.outcome_shares[index] = market.outcome_shares[index]
+            .checked_add(shares_desired)
+            .ok_or(MarketError::MathOverflow)?;
+        market.total_liquidity = market.total_liquidity
+            .checked_add(actual_cost)
+            .ok_or(MarketError::MathOverflow)?;
 
-    let payout = (shares * adjusted_price) / PRICE_PRECISION;
-    Ok(payout)
-}
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.outcome_mint.to_account_info(),
+            to: ctx.accounts.user_outcome_token_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, shares_desired)?;
+
+        let clock = Clock::get()?;
+        emit!(OutcomeSharesBought {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            outcome_index,
+            shares: shares_desired,
+            cost: actual_cost,
+            fee: creator_fee + protocol_fee,
+            prices: outcome_prices(market)?,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn sell_outcome_shares(ctx: Context<TradeOutcomeShares>, outcome_index: u8, shares_to_sell: u64, min_payout: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind == MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(market.outcome_mints.len() == market.outcome_shares.len(), MarketError::OutcomeMintsPending);
+        require!(market.outcome_mints.get(outcome_index as usize) == Some(&ctx.accounts.outcome_mint.key()), MarketError::InvalidTokenMint);
+        require!(!market.resolved, MarketError::MarketResolved);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(shares_to_sell > 0, MarketError::ZeroAmount);
+
+        let index = outcome_index as usize;
+        let payout = calculate_sell_payout(market, index, shares_to_sell)?;
+        let (creator_fee, protocol_fee, net_payout) = split_sell_payout(&TransferFeeSchedule::load(&ctx.accounts.collateral_mint)?, payout, market.fee_bps)?;
+        require!(net_payout >= min_payout, MarketError::SlippageExceeded);
+
+        market.outcome_shares[index] = market.outcome_shares[index]
+            .checked_sub(shares_to_sell)
+            .ok_or(MarketError::MathUnderflow)?;
+        market.total_liquidity = market.total_liquidity
+            .checked_sub(payout)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        let burn_accounts = Burn {
+            mint: ctx.accounts.outcome_mint.to_account_info(),
+            from: ctx.accounts.user_outcome_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+        burn(burn_ctx, shares_to_sell)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.collateral_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.user_collateral_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+        transfer_checked(transfer_ctx, net_payout, ctx.accounts.collateral_mint.decimals)?;
+
+        if creator_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let transfer_accounts = TransferChecked {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.creator_fee_vault.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+            transfer_checked(transfer_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, creator_fee)?, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        if protocol_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let transfer_accounts = TransferChecked {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.protocol_fee_vault.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+            transfer_checked(transfer_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, protocol_fee)?, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        market.creator_fees_accrued = market.creator_fees_accrued
+            .checked_add(creator_fee)
+            .ok_or(MarketError::MathOverflow)?;
+        market.protocol_fees_accrued = market.protocol_fees_accrued
+            .checked_add(protocol_fee)
+            .ok_or(MarketError::MathOverflow)?;
+
+        let clock = Clock::get()?;
+        emit!(OutcomeSharesSold {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            outcome_index,
+            shares: shares_to_sell,
+            payout: net_payout,
+            fee: creator_fee + protocol_fee,
+            prices: outcome_prices(market)?,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Deposits `amount` collateral and mints `amount` YES plus `amount` NO tokens.
+    /// A full set always pays out exactly one unit of collateral, so this leaves the
+    /// LMSR prices unchanged. Like trading, it is closed once the market is resolved
+    /// or while it is paused.
+    pub fn mint_complete_set(ctx: Context<MintCompleteSet>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(!market.resolved, MarketError::MarketResolved);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(amount > 0, MarketError::ZeroAmount);
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, amount)?, ctx.accounts.collateral_mint.decimals)?;
+
+        market.yes_shares_outstanding = market.yes_shares_outstanding
+            .checked_add(amount)
+            .ok_or(MarketError::MathOverflow)?;
+        market.no_shares_outstanding = market.no_shares_outstanding
+            .checked_add(amount)
+            .ok_or(MarketError::MathOverflow)?;
+        market.total_liquidity = market.total_liquidity
+            .checked_add(amount)
+            .ok_or(MarketError::MathOverflow)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.yes_token_mint.to_account_info(),
+            to: ctx.accounts.user_yes_token_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, amount)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.no_token_mint.to_account_info(),
+            to: ctx.accounts.user_no_token_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, amount)?;
+
+        let clock = Clock::get()?;
+        emit!(CompleteSetMinted {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            amount,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Burns `amount` YES plus `amount` NO tokens and returns `amount` collateral at par.
+    pub fn merge_complete_set(ctx: Context<MergeCompleteSet>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(market.winning_outcome != WinningOutcome::Canceled, MarketError::MarketCanceled);
+        require!(amount > 0, MarketError::ZeroAmount);
+
+        market.yes_shares_outstanding = market.yes_shares_outstanding
+            .checked_sub(amount)
+            .ok_or(MarketError::MathUnderflow)?;
+        market.no_shares_outstanding = market.no_shares_outstanding
+            .checked_sub(amount)
+            .ok_or(MarketError::MathUnderflow)?;
+        market.total_liquidity = market.total_liquidity
+            .checked_sub(amount)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_accounts = Burn {
+            mint: ctx.accounts.yes_token_mint.to_account_info(),
+            from: ctx.accounts.user_yes_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+        burn(burn_ctx, amount)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_accounts = Burn {
+            mint: ctx.accounts.no_token_mint.to_account_info(),
+            from: ctx.accounts.user_no_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+        burn(burn_ctx, amount)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.collateral_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.user_collateral_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+        transfer_checked(transfer_ctx, amount, ctx.accounts.collateral_mint.decimals)?;
+
+        let clock = Clock::get()?;
+        emit!(CompleteSetMerged {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            amount,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Adds `amount` collateral to the AMM pool at the current prices and mints
+    /// LP tokens pro-rata to the deepest side of the pool. The part of the deposit
+    /// the pool cannot absorb without moving prices is returned as outcome tokens.
+    pub fn add_liquidity(ctx: Context<AddLiquidity>, amount: u64, min_lp_tokens: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(!market.resolved, MarketError::MarketResolved);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(amount > 0, MarketError::ZeroAmount);
+
+        let lp_supply = ctx.accounts.lp_token_mint.supply;
+        let min_shares = market.yes_shares_outstanding.min(market.no_shares_outstanding);
+        let pool_depth = market.total_liquidity
+            .checked_sub(min_shares)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        let lp_tokens = mul_div_floor(amount, lp_supply, pool_depth)?;
+        require!(lp_tokens > 0, MarketError::ZeroAmount);
+        require!(lp_tokens >= min_lp_tokens, MarketError::SlippageExceeded);
+
+        let yes_returned = mul_div_floor(amount, market.yes_shares_outstanding - min_shares, pool_depth)?;
+        let no_returned = mul_div_floor(amount, market.no_shares_outstanding - min_shares, pool_depth)?;
+        let added_depth = mul_div_floor(market.liquidity_parameter, amount, pool_depth)?;
+
+        market.total_liquidity = market.total_liquidity
+            .checked_add(amount)
+            .ok_or(MarketError::MathOverflow)?;
+        market.yes_shares_outstanding = market.yes_shares_outstanding
+            .checked_add(yes_returned)
+            .ok_or(MarketError::MathOverflow)?;
+        market.no_shares_outstanding = market.no_shares_outstanding
+            .checked_add(no_returned)
+            .ok_or(MarketError::MathOverflow)?;
+        market.liquidity_parameter = market.liquidity_parameter
+            .checked_add(added_depth)
+            .ok_or(MarketError::MathOverflow)?;
+        require_pool_solvent(market)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, amount)?, ctx.accounts.collateral_mint.decimals)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.lp_token_mint.to_account_info(),
+            to: ctx.accounts.user_lp_token_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, lp_tokens)?;
+
+        if yes_returned > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.yes_token_mint.to_account_info(),
+                to: ctx.accounts.user_yes_token_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            mint_to(cpi_ctx, yes_returned)?;
+        }
+
+        if no_returned > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.no_token_mint.to_account_info(),
+                to: ctx.accounts.user_no_token_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            mint_to(cpi_ctx, no_returned)?;
+        }
+
+        let clock = Clock::get()?;
+        emit!(LiquidityAdded {
+            market: market.key(),
+            provider: ctx.accounts.user.key(),
+            collateral_in: amount,
+            lp_tokens,
+            yes_tokens_out: yes_returned,
+            no_tokens_out: no_returned,
+            liquidity_parameter: market.liquidity_parameter,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Burns `lp_tokens` for a pro-rata share of the pool: collateral for the part
+    /// covered on both sides plus the leftover outcome-token inventory. The last LP
+    /// tokens stay locked until resolution so the AMM always has depth.
+    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, lp_tokens: u64, min_collateral: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(!market.resolved, MarketError::MarketResolved);
+        require!(!market.paused, MarketError::MarketPaused);
+        require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+        require!(lp_tokens > 0, MarketError::ZeroAmount);
+
+        let lp_supply = ctx.accounts.lp_token_mint.supply;
+        require!(lp_tokens < lp_supply, MarketError::LiquidityLocked);
+
+        let max_shares = market.yes_shares_outstanding.max(market.no_shares_outstanding);
+        let free_collateral = market.total_liquidity
+            .checked_sub(max_shares)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        let collateral_out = mul_div_floor(free_collateral, lp_tokens, lp_supply)?;
+        require!(collateral_out >= min_collateral, MarketError::SlippageExceeded);
+
+        let yes_out = mul_div_floor(max_shares - market.yes_shares_outstanding, lp_tokens, lp_supply)?;
+        let no_out = mul_div_floor(max_shares - market.no_shares_outstanding, lp_tokens, lp_supply)?;
+        let removed_depth = mul_div_ceil(market.liquidity_parameter, lp_tokens, lp_supply)?;
+
+        market.total_liquidity = market.total_liquidity
+            .checked_sub(collateral_out)
+            .ok_or(MarketError::MathUnderflow)?;
+        market.yes_shares_outstanding = market.yes_shares_outstanding
+            .checked_add(yes_out)
+            .ok_or(MarketError::MathOverflow)?;
+        market.no_shares_outstanding = market.no_shares_outstanding
+            .checked_add(no_out)
+            .ok_or(MarketError::MathOverflow)?;
+        market.liquidity_parameter = market.liquidity_parameter
+            .checked_sub(removed_depth)
+            .ok_or(MarketError::MathUnderflow)?;
+        require_pool_solvent(market)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_accounts = Burn {
+            mint: ctx.accounts.lp_token_mint.to_account_info(),
+            from: ctx.accounts.user_lp_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+        burn(burn_ctx, lp_tokens)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        if collateral_out > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let transfer_accounts = TransferChecked {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.user_collateral_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+            transfer_checked(transfer_ctx, collateral_out, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        if yes_out > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.yes_token_mint.to_account_info(),
+                to: ctx.accounts.user_yes_token_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            mint_to(cpi_ctx, yes_out)?;
+        }
+
+        if no_out > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.no_token_mint.to_account_info(),
+                to: ctx.accounts.user_no_token_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            mint_to(cpi_ctx, no_out)?;
+        }
+
+        let clock = Clock::get()?;
+        emit!(LiquidityRemoved {
+            market: market.key(),
+            provider: ctx.accounts.user.key(),
+            lp_tokens,
+            collateral_out,
+            yes_tokens_out: yes_out,
+            no_tokens_out: no_out,
+            liquidity_parameter: market.liquidity_parameter,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn resolve_market(ctx: Context<ResolveMarket>, manual_outcome: Option<WinningOutcome>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+
+        let clock = Clock::get()?;
+        require!(clock.unix_timestamp > market.end_timestamp, MarketError::MarketNotEnd);
+
+        let mut oracle_price = None;
+        match market.market_type {
+            MarketType::Oracle => {
+                // Scalar markets have no threshold; their confidence check is
+                // measured against the width of the range instead.
+                let reference = if market.market_kind == MarketKind::Scalar {
+                    market.scalar_upper_bound
+                        .checked_sub(market.scalar_lower_bound)
+                        .ok_or(MarketError::MathOverflow)?
+                } else {
+                    market.oracle_threshold.ok_or(MarketError::OracleThresholdRequired)?
+                };
+
+                match read_oracle_price(&ctx.accounts.resolution_source, market, reference, clock.unix_timestamp) {
+                    Ok(price) => {
+                        if market.market_kind == MarketKind::Scalar {
+                            market.scalar_long_payout_rate = scalar_long_payout_rate(&price, market.scalar_lower_bound, market.scalar_upper_bound, market.oracle_threshold_expo)?;
+                            market.winning_outcome = WinningOutcome::Scalar;
+                        } else {
+                            market.winning_outcome = oracle_outcome(&price, reference, market.oracle_threshold_expo, &market.oracle_comparison)?;
+                        }
+                        oracle_price = Some(price);
+                    },
+                    Err(err) => {
+                        // If no usable price shows up within the grace period, cancel
+                        // the market so holders can get refunded instead of waiting forever.
+                        let grace_end = market.end_timestamp
+                            .checked_add(ORACLE_GRACE_PERIOD)
+                            .ok_or(MarketError::MathOverflow)?;
+                        if clock.unix_timestamp <= grace_end {
+                            return Err(err);
+                        }
+                        market.winning_outcome = WinningOutcome::Canceled;
+                    }
+                }
+            },
+            MarketType::Manual => {
+                require!(market.dispute_config.is_none(), MarketError::OptimisticResolutionRequired);
+                authorize_resolver(market, &ctx.accounts.resolver.key())?;
+                let outcome = manual_outcome.ok_or(MarketError::OutcomeRequired)?;
+                validate_outcome(market, &outcome)?;
+                market.winning_outcome = outcome;
+            }
+        }
+
+        if market.winning_outcome == WinningOutcome::Canceled {
+            snapshot_refund_rates(market)?;
+        }
+        market.resolved = true;
+
+        emit!(MarketResolved {
+            market: market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            outcome: market.winning_outcome.clone(),
+            oracle_price: oracle_price.as_ref().map(|price| price.price),
+            oracle_expo: oracle_price.as_ref().map(|price| price.expo),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn redeem_winnings(ctx: Context<RedeemWinnings>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.resolved, MarketError::MarketNotResolved);
+        require!(amount > 0, MarketError::ZeroAmount);
+
+        let token_mint = ctx.accounts.user_winning_token_account.mint;
+        let payout = match market.winning_outcome {
+            WinningOutcome::Yes => {
+                require!(token_mint == market.yes_token_mint, MarketError::InvalidTokenMint);
+                amount
+            },
+            WinningOutcome::No => {
+                require!(token_mint == market.no_token_mint, MarketError::InvalidTokenMint);
+                amount
+            },
+            WinningOutcome::Outcome(index) => {
+                require!(market.outcome_mints.get(index as usize) == Some(&token_mint), MarketError::InvalidTokenMint);
+                let outcome_shares = &mut market.outcome_shares[index as usize];
+                *outcome_shares = outcome_shares.checked_sub(amount).ok_or(MarketError::MathUnderflow)?;
+                amount
+            },
+            WinningOutcome::Scalar => {
+                // LONG gets the interpolated share of each unit, SHORT the remainder
+                let payout_rate = if token_mint == market.yes_token_mint {
+                    market.scalar_long_payout_rate
+                } else if token_mint == market.no_token_mint {
+                    REFUND_RATE_PRECISION - market.scalar_long_payout_rate
+                } else {
+                    return err!(MarketError::InvalidTokenMint);
+                };
+                mul_div_floor(amount, payout_rate, REFUND_RATE_PRECISION)?
+            },
+            WinningOutcome::Canceled => {
+                // For canceled markets, allow redemption of every outcome token
+                // at the fixed rates snapshotted when the market was canceled
+                let refund_rate = if token_mint == market.yes_token_mint {
+                    market.canceled_yes_refund_rate
+                } else if token_mint == market.no_token_mint {
+                    market.canceled_no_refund_rate
+                } else if let Some(index) = market.outcome_mints.iter().position(|mint| *mint == token_mint) {
+                    let outcome_shares = &mut market.outcome_shares[index];
+                    *outcome_shares = outcome_shares.checked_sub(amount).ok_or(MarketError::MathUnderflow)?;
+                    market.canceled_outcome_refund_rates[index]
+                } else {
+                    return err!(MarketError::InvalidTokenMint);
+                };
+                mul_div_floor(amount, refund_rate, REFUND_RATE_PRECISION)?
+            },
+            WinningOutcome::Undecided => return err!(MarketError::MarketNotResolved),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_accounts = Burn {
+            mint: ctx.accounts.winning_token_mint.to_account_info(),
+            from: ctx.accounts.user_winning_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let burn_ctx = CpiContext::new(cpi_program.clone(), burn_accounts);
+        burn(burn_ctx, amount)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.collateral_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.user_collateral_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+        transfer_checked(transfer_ctx, payout, ctx.accounts.collateral_mint.decimals)?;
+
+        let clock = Clock::get()?;
+        emit!(WinningsRedeemed {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            outcome: market.winning_outcome.clone(),
+            tokens_burned: amount,
+            payout,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// After resolution, pays LP token holders their share of whatever collateral
+    /// the vault holds beyond what the outstanding winning tokens can still claim.
+    /// Obligations are taken from the mints' actual supply, so redemptions before or
+    /// after a withdrawal never change anyone's share.
+    pub fn withdraw_remaining_liquidity(ctx: Context<WithdrawRemainingLiquidity>, lp_tokens: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(market.resolved, MarketError::MarketNotResolved);
+        require!(lp_tokens > 0, MarketError::ZeroAmount);
+
+        // Two-outcome obligations come from the mints' supply; categorical markets
+        // keep their per-outcome supply in `outcome_shares`, which redemptions decrement.
+        let (supplies, refund_rates) = match market.market_kind {
+            MarketKind::Binary | MarketKind::Scalar => {
+                let yes_supply = ctx.accounts.yes_token_mint.as_ref().ok_or(MarketError::InvalidTokenMint)?.supply;
+                let no_supply = ctx.accounts.no_token_mint.as_ref().ok_or(MarketError::InvalidTokenMint)?.supply;
+                (vec![yes_supply, no_supply], vec![market.canceled_yes_refund_rate, market.canceled_no_refund_rate])
+            },
+            MarketKind::Categorical => (market.outcome_shares.clone(), market.canceled_outcome_refund_rates.clone()),
+        };
+        let obligations = match market.winning_outcome {
+            WinningOutcome::Yes => supplies[0],
+            WinningOutcome::No => supplies[1],
+            WinningOutcome::Outcome(index) => supplies[index as usize],
+            WinningOutcome::Scalar => {
+                let long_rate = market.scalar_long_payout_rate;
+                rated_obligations(&supplies, &[long_rate, REFUND_RATE_PRECISION - long_rate])?
+            },
+            WinningOutcome::Canceled => rated_obligations(&supplies, &refund_rates)?,
+            WinningOutcome::Undecided => return err!(MarketError::MarketNotResolved),
+        };
+
+        let excess = ctx.accounts.collateral_vault.amount
+            .checked_sub(obligations)
+            .ok_or(MarketError::MathUnderflow)?;
+        let payout = mul_div_floor(excess, lp_tokens, ctx.accounts.lp_token_mint.supply)?;
+        require!(payout > 0, MarketError::NoLiquidity);
+
+        market.total_liquidity = market.total_liquidity
+            .checked_sub(payout)
+            .ok_or(MarketError::MathUnderflow)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let burn_accounts = Burn {
+            mint: ctx.accounts.lp_token_mint.to_account_info(),
+            from: ctx.accounts.user_lp_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(cpi_program, burn_accounts);
+        burn(burn_ctx, lp_tokens)?;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.collateral_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.user_collateral_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+        transfer_checked(transfer_ctx, payout, ctx.accounts.collateral_mint.decimals)?;
+
+        let clock = Clock::get()?;
+        emit!(RemainingLiquidityWithdrawn {
+            market: market.key(),
+            provider: ctx.accounts.user.key(),
+            lp_tokens,
+            collateral_out: payout,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Switches a manual market to optimistic resolution: outcomes are proposed
+    /// with a bond, can be challenged with a counter-bond during `dispute_window`
+    /// seconds, and disputes are settled by `arbiter`. Must be set before the
+    /// market ends and cannot be changed afterwards.
+    pub fn configure_dispute(ctx: Context<ConfigureDispute>, arbiter: Pubkey, bond_amount: u64, dispute_window: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(ctx.accounts.creator.key() == market.creator, MarketError::UnauthorizedResolver);
+        require!(market.market_type == MarketType::Manual, MarketError::UnsupportedMarketKind);
+        require!(market.dispute_config.is_none(), MarketError::DisputeAlreadyConfigured);
+        require!(Clock::get()?.unix_timestamp < market.end_timestamp, MarketError::InvalidDisputeConfig);
+        require!(bond_amount > 0 && dispute_window > 0, MarketError::InvalidDisputeConfig);
+
+        market.dispute_config = Some(DisputeConfig {
+            arbiter,
+            bond_amount,
+            dispute_window,
+        });
+        market.bond_vault = ctx.accounts.bond_vault.key();
+        Ok(())
+    }
+
+    /// Proposes the outcome of an optimistic market once it has ended, locking
+    /// the configured bond. Anyone can propose.
+    pub fn propose_resolution(ctx: Context<PostBond>, outcome: WinningOutcome) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let config = market.dispute_config.clone().ok_or(MarketError::DisputeNotConfigured)?;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        require!(market.proposal.is_none(), MarketError::ResolutionPending);
+        validate_outcome(market, &outcome)?;
+
+        let clock = Clock::get()?;
+        require!(clock.unix_timestamp > market.end_timestamp, MarketError::MarketNotEnd);
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.bonder_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.bond_vault.to_account_info(),
+            authority: ctx.accounts.bonder.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, config.bond_amount)?, ctx.accounts.collateral_mint.decimals)?;
+
+        market.proposal = Some(ResolutionProposal {
+            proposer: ctx.accounts.bonder.key(),
+            outcome: outcome.clone(),
+            proposed_at: clock.unix_timestamp,
+            disputer: None,
+            disputed_at: 0,
+        });
+
+        emit!(ResolutionProposed {
+            market: market.key(),
+            proposer: ctx.accounts.bonder.key(),
+            outcome,
+            bond: config.bond_amount,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Challenges the pending proposal with a matching bond, escalating the market
+    /// to the arbiter.
+    pub fn dispute_resolution(ctx: Context<PostBond>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let config = market.dispute_config.clone().ok_or(MarketError::DisputeNotConfigured)?;
+        let proposal = market.proposal.as_mut().ok_or(MarketError::NoResolutionProposed)?;
+        require!(proposal.disputer.is_none(), MarketError::AlreadyDisputed);
+
+        let clock = Clock::get()?;
+        let window_end = proposal.proposed_at
+            .checked_add(config.dispute_window)
+            .ok_or(MarketError::MathOverflow)?;
+        require!(clock.unix_timestamp <= window_end, MarketError::DisputeWindowClosed);
+
+        proposal.disputer = Some(ctx.accounts.bonder.key());
+        proposal.disputed_at = clock.unix_timestamp;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.bonder_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.bond_vault.to_account_info(),
+            authority: ctx.accounts.bonder.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, gross_up_transfer_fee(&ctx.accounts.collateral_mint, config.bond_amount)?, ctx.accounts.collateral_mint.decimals)?;
+
+        emit!(ResolutionDisputed {
+            market: market.key(),
+            disputer: ctx.accounts.bonder.key(),
+            bond: config.bond_amount,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Finalizes an undisputed proposal after the dispute window and returns the
+    /// proposer's bond. Anyone can call it.
+    pub fn finalize_resolution(ctx: Context<SettleResolution>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let config = market.dispute_config.clone().ok_or(MarketError::DisputeNotConfigured)?;
+        let proposal = market.proposal.clone().ok_or(MarketError::NoResolutionProposed)?;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        require!(proposal.disputer.is_none(), MarketError::AlreadyDisputed);
+
+        let clock = Clock::get()?;
+        let window_end = proposal.proposed_at
+            .checked_add(config.dispute_window)
+            .ok_or(MarketError::MathOverflow)?;
+        require!(clock.unix_timestamp > window_end, MarketError::DisputeWindowOpen);
+
+        settle_resolution(ctx, proposal.outcome, proposal.proposer, config.bond_amount, clock)
+    }
+
+    /// Settles a disputed proposal. The arbiter picks the final outcome; whichever
+    /// side it agrees with receives both bonds.
+    pub fn arbitrate_resolution(ctx: Context<SettleResolution>, outcome: WinningOutcome) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let config = market.dispute_config.clone().ok_or(MarketError::DisputeNotConfigured)?;
+        let proposal = market.proposal.clone().ok_or(MarketError::NoResolutionProposed)?;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        require!(ctx.accounts.resolver.key() == config.arbiter, MarketError::UnauthorizedResolver);
+        let disputer = proposal.disputer.ok_or(MarketError::NotDisputed)?;
+        validate_outcome(market, &outcome)?;
+
+        let winner = if outcome == proposal.outcome { proposal.proposer } else { disputer };
+        let bonds = config.bond_amount
+            .checked_mul(2)
+            .ok_or(MarketError::MathOverflow)?;
+        settle_resolution(ctx, outcome, winner, bonds, Clock::get()?)
+    }
+
+    /// Cancels a disputed market the arbiter left unsettled for
+    /// `ARBITRATION_PERIOD` and returns both bonds to whoever posted them.
+    /// Anyone can call it.
+    pub fn expire_dispute(ctx: Context<ExpireDispute>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let config = market.dispute_config.clone().ok_or(MarketError::DisputeNotConfigured)?;
+        let proposal = market.proposal.clone().ok_or(MarketError::NoResolutionProposed)?;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        let disputer = proposal.disputer.ok_or(MarketError::NotDisputed)?;
+        require!(ctx.accounts.proposer_collateral_account.owner == proposal.proposer, MarketError::InvalidTokenAccount);
+        require!(ctx.accounts.disputer_collateral_account.owner == disputer, MarketError::InvalidTokenAccount);
+
+        let clock = Clock::get()?;
+        let deadline = proposal.disputed_at
+            .checked_add(ARBITRATION_PERIOD)
+            .ok_or(MarketError::MathOverflow)?;
+        require!(clock.unix_timestamp > deadline, MarketError::ArbitrationPeriodNotReached);
+
+        market.winning_outcome = WinningOutcome::Canceled;
+        snapshot_refund_rates(market)?;
+        market.resolved = true;
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        for bonder_account in [&ctx.accounts.proposer_collateral_account, &ctx.accounts.disputer_collateral_account] {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.bond_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: bonder_account.to_account_info(),
+                authority: ctx.accounts.market_authority.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, config.bond_amount, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        emit!(MarketResolved {
+            market: market_key,
+            resolver: ctx.accounts.caller.key(),
+            outcome: WinningOutcome::Canceled,
+            oracle_price: None,
+            oracle_expo: None,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Records a committee member's vote for `outcome`, replacing any earlier vote
+    /// of theirs, and resolves the market as soon as `threshold` members agree.
+    /// Manual markets accept votes once they end; oracle markets only after the
+    /// emergency period, as the committee's stand-in for emergency resolution.
+    pub fn vote_resolution(ctx: Context<VoteResolution>, outcome: WinningOutcome) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        require!(market.dispute_config.is_none(), MarketError::OptimisticResolutionRequired);
+        let (members, threshold) = match &market.resolver {
+            ResolverConfig::Committee { members, threshold } => (members.clone(), *threshold),
+            ResolverConfig::Single(_) => return err!(MarketError::NotACommitteeMarket),
+        };
+        let voter = ctx.accounts.voter.key();
+        require!(members.contains(&voter), MarketError::UnauthorizedResolver);
+        validate_outcome(market, &outcome)?;
+
+        let clock = Clock::get()?;
+        let voting_opens = match market.market_type {
+            MarketType::Manual => market.end_timestamp,
+            MarketType::Oracle => market.end_timestamp
+                .checked_add(EMERGENCY_PERIOD)
+                .ok_or(MarketError::MathOverflow)?,
+        };
+        require!(clock.unix_timestamp > voting_opens, MarketError::MarketNotEnd);
+
+        market.resolution_votes.retain(|vote| vote.member != voter);
+        market.resolution_votes.push(ResolutionVote {
+            member: voter,
+            outcome: outcome.clone(),
+        });
+        let agreeing = market.resolution_votes
+            .iter()
+            .filter(|vote| vote.outcome == outcome)
+            .count();
+
+        emit!(ResolutionVoted {
+            market: market.key(),
+            member: voter,
+            outcome: outcome.clone(),
+            votes: agreeing as u8,
+            threshold,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        if agreeing >= threshold as usize {
+            market.winning_outcome = outcome;
+            if market.winning_outcome == WinningOutcome::Canceled {
+                snapshot_refund_rates(market)?;
+            }
+            market.resolved = true;
+
+            emit!(MarketResolved {
+                market: market.key(),
+                resolver: voter,
+                outcome: market.winning_outcome.clone(),
+                oracle_price: None,
+                oracle_expo: None,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Halts trading and liquidity changes on a single market. Resolution and
+    /// redemption are unaffected.
+    pub fn pause_market(ctx: Context<ManageMarket>) -> Result<()> {
+        set_market_paused(ctx, true)
+    }
+
+    pub fn unpause_market(ctx: Context<ManageMarket>) -> Result<()> {
+        set_market_paused(ctx, false)
+    }
+
+    /// Cancels the market right away, e.g. for a malformed question or an event
+    /// that was called off. Holders redeem at the AMM prices at cancellation.
+    pub fn cancel_market(ctx: Context<ManageMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(ctx.accounts.creator.key() == market.creator, MarketError::UnauthorizedResolver);
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        // Past the end the outcome is known; canceling then would let a creator on
+        // the losing side trade resolution for a refund at the AMM price. It also
+        // keeps cancellation clear of proposals, which only open after the end.
+        require!(Clock::get()?.unix_timestamp < market.end_timestamp, MarketError::TradingClosed);
+
+        market.winning_outcome = WinningOutcome::Canceled;
+        snapshot_refund_rates(market)?;
+        market.resolved = true;
+        market.paused = false;
+
+        let clock = Clock::get()?;
+        emit!(MarketCanceledByCreator {
+            market: market.key(),
+            creator: ctx.accounts.creator.key(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn emergency_resolve_market(ctx: Context<EmergencyResolveMarket>, outcome: WinningOutcome) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.resolved, MarketError::MarketAlreadyResolved);
+        // Bonds locked in a pending proposal can only be released by settling it
+        // or, once the arbiter has missed its deadline, by `expire_dispute`.
+        require!(market.proposal.is_none(), MarketError::ResolutionPending);
+        validate_outcome(market, &outcome)?;
+
+        let clock = Clock::get()?;
+        require!(clock.unix_timestamp > market.end_timestamp + EMERGENCY_PERIOD, MarketError::EmergencyPeriodNotReached);
+
+        authorize_resolver(market, &ctx.accounts.resolver.key())?;
+
+        market.winning_outcome = outcome;
+        if market.winning_outcome == WinningOutcome::Canceled {
+            snapshot_refund_rates(market)?;
+        }
+        market.resolved = true;
+
+        emit!(EmergencyResolved {
+            market: market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            outcome: market.winning_outcome.clone(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sweeps the accrued creator or protocol fees of a market to `destination`.
+    /// Protocol fees can only be claimed by the config admin, to a treasury account.
+    pub fn claim_fees(ctx: Context<ClaimFees>, recipient: FeeRecipient) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let claimer = ctx.accounts.claimer.key();
+
+        let amount = match recipient {
+            FeeRecipient::Creator => {
+                require!(claimer == market.creator, MarketError::UnauthorizedFeeClaim);
+                require!(ctx.accounts.fee_vault.key() == market.creator_fee_vault, MarketError::InvalidTokenAccount);
+                std::mem::take(&mut market.creator_fees_accrued)
+            },
+            FeeRecipient::Protocol => {
+                require!(claimer == ctx.accounts.config.admin, MarketError::UnauthorizedFeeClaim);
+                require!(ctx.accounts.destination.owner == ctx.accounts.config.treasury, MarketError::InvalidTokenAccount);
+                require!(ctx.accounts.fee_vault.key() == market.protocol_fee_vault, MarketError::InvalidTokenAccount);
+                std::mem::take(&mut market.protocol_fees_accrued)
+            }
+        };
+        require!(amount > 0, MarketError::ZeroAmount);
+
+        let market_key = market.key();
+        let authority_seeds = &[
+            b"authority",
+            market_key.as_ref(),
+            &[market.bump]
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.fee_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.destination.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let transfer_ctx = CpiContext::new_with_signer(cpi_program, transfer_accounts, signer_seeds);
+        transfer_checked(transfer_ctx, amount, ctx.accounts.collateral_mint.decimals)?;
+
+        let clock = Clock::get()?;
+        emit!(FeesClaimed {
+            market: market.key(),
+            recipient,
+            claimer,
+            amount,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_market_price(ctx: Context<GetMarketPrice>, outcome: ShareOutcome) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        calculate_price(market, outcome.index())
+    }
+
+    pub fn get_outcome_price(ctx: Context<GetMarketPrice>, outcome_index: u8) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(market.market_kind == MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        calculate_price(market, outcome_index as usize)
+    }
+
+    /// Previews buying (`Bid`) or selling (`Ask`) `shares` against the AMM without
+    /// executing anything; simulate it and read the quote from the return data.
+    pub fn quote_trade(ctx: Context<GetMarketPrice>, outcome: ShareOutcome, side: OrderSide, shares: u64) -> Result<TradeQuote> {
+        let market = &ctx.accounts.market;
+        require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+        require!(shares > 0, MarketError::ZeroAmount);
+        preview_trade(market, &outcome, &side, shares)
+    }
+}
+
+/// Amount to send so that `amount` arrives after the mint's Token-2022 transfer
+/// fee is withheld. Legacy SPL mints and mints without the extension carry no fee.
+fn gross_up_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    if amount == 0 {
+        return Ok(amount);
+    }
+    TransferFeeSchedule::load(mint)?.gross_up(amount)
+}
+
+/// A collateral mint's transfer fee as of the current epoch, read once so that
+/// sizing a trade can price many amounts without re-parsing the mint.
+struct TransferFeeSchedule {
+    fee_config: Option<TransferFeeConfig>,
+    epoch: u64,
+}
+
+impl TransferFeeSchedule {
+    fn load(mint: &InterfaceAccount<Mint>) -> Result<Self> {
+        let mint_info = mint.to_account_info();
+        if *mint_info.owner != spl_token_2022::ID {
+            return Ok(Self { fee_config: None, epoch: 0 });
+        }
+
+        let mint_data = mint_info.try_borrow_data()?;
+        let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+        match mint_state.get_extension::<TransferFeeConfig>() {
+            Ok(fee_config) => Ok(Self { fee_config: Some(*fee_config), epoch: Clock::get()?.epoch }),
+            Err(_) => Ok(Self { fee_config: None, epoch: 0 }),
+        }
+    }
+
+    /// Fee withheld from a transfer of `amount`.
+    fn fee(&self, amount: u64) -> Result<u64> {
+        match &self.fee_config {
+            Some(fee_config) => fee_config.calculate_epoch_fee(self.epoch, amount).ok_or(error!(MarketError::MathOverflow)),
+            None => Ok(0),
+        }
+    }
+
+    /// Amount to send so that `amount` arrives.
+    fn gross_up(&self, amount: u64) -> Result<u64> {
+        let fee = match &self.fee_config {
+            Some(fee_config) if amount > 0 => fee_config
+                .calculate_inverse_epoch_fee(self.epoch, amount)
+                .ok_or(MarketError::MathOverflow)?,
+            _ => 0,
+        };
+        amount.checked_add(fee).ok_or(error!(MarketError::MathOverflow))
+    }
+}
+
+/// Splits an AMM sell payout into `(creator_fee, protocol_fee, net_payout)`. The fee
+/// vaults must receive exactly what is accrued, so any transfer fee on moving the
+/// fees out of the collateral vault comes out of the seller's payout.
+fn split_sell_payout(transfer_fees: &TransferFeeSchedule, payout: u64, fee_bps: u16) -> Result<(u64, u64, u64)> {
+    let (creator_fee, protocol_fee) = calculate_fees(payout, fee_bps)?;
+    let fee_transfer_cost = (transfer_fees.gross_up(creator_fee)? - creator_fee)
+        .checked_add(transfer_fees.gross_up(protocol_fee)? - protocol_fee)
+        .ok_or(MarketError::MathOverflow)?;
+    let net_payout = payout
+        .checked_sub(creator_fee)
+        .and_then(|net| net.checked_sub(protocol_fee))
+        .and_then(|net| net.checked_sub(fee_transfer_cost))
+        .ok_or(MarketError::MathUnderflow)?;
+    Ok((creator_fee, protocol_fee, net_payout))
+}
+
+/// Shared setup for two-outcome markets: stores the common fields, pulls the
+/// creator's funding into the vault and mints the creator's LP position.
+fn open_market<'info>(accounts: &mut CreateMarket<'info>, authority_bump: u8, market_id: u64, question: String, end_timestamp: i64, trading_close_timestamp: Option<i64>, market_type: MarketType, resolution_source: Pubkey, initial_liquidity: u64, liquidity_parameter: u64, fee_bps: Option<u16>) -> Result<()> {
+    let clock = Clock::get()?;
+    require!(end_timestamp > clock.unix_timestamp, MarketError::InvalidEndTimestamp);
+    accounts.config.check_new_market(&accounts.collateral_mint.key(), clock.unix_timestamp, end_timestamp)?;
+    let fee_bps = fee_bps.unwrap_or(accounts.config.default_fee_bps);
+    let trading_close_timestamp = trading_close_timestamp.unwrap_or(end_timestamp);
+    require!(trading_close_timestamp > clock.unix_timestamp && trading_close_timestamp <= end_timestamp, MarketError::InvalidTradingCloseTimestamp);
+
+    let market = &mut accounts.market;
+    market.creator = accounts.creator.key();
+    market.market_id = market_id;
+    market.question = question;
+    market.end_timestamp = end_timestamp;
+    market.trading_close_timestamp = trading_close_timestamp;
+    market.resolved = false;
+    market.winning_outcome = WinningOutcome::Undecided;
+    market.market_type = market_type;
+    market.resolution_source = resolution_source;
+
+    market.collateral_mint = accounts.collateral_mint.key();
+    market.yes_token_mint = accounts.yes_token_mint.key();
+    market.no_token_mint = accounts.no_token_mint.key();
+    market.collateral_vault = accounts.collateral_vault.key();
+    market.market_authority = accounts.market_authority.key();
+    market.bump = authority_bump;
+
+    require!(fee_bps <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+    market.fee_bps = fee_bps;
+    market.creator_fee_vault = accounts.creator_fee_vault.key();
+    market.protocol_fee_vault = accounts.protocol_fee_vault.key();
+    market.creator_fees_accrued = 0;
+    market.protocol_fees_accrued = 0;
+
+    require!(initial_liquidity > 0, MarketError::ZeroAmount);
+    let funding = initial_liquidity
+        .checked_mul(2)
+        .ok_or(MarketError::MathOverflow)?;
+
+    // The LMSR market maker can lose at most `C(0) = b * ln(2)` on a two-outcome
+    // market, so the creator's deposit has to cover that for the chosen depth.
+    require!(liquidity_parameter > 0, MarketError::InvalidLiquidityParameter);
+    let max_loss = lmsr_cost(&[0, 0], liquidity_parameter)?;
+    let funded = (funding as u128)
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?;
+    require!(max_loss <= funded, MarketError::InsufficientLiquidity);
+    market.liquidity_parameter = liquidity_parameter;
+
+    market.yes_shares_outstanding = 0;
+    market.no_shares_outstanding = 0;
+    market.total_liquidity = funding;
+
+    let cpi_program = accounts.token_program.to_account_info();
+    let cpi_accounts = TransferChecked {
+        from: accounts.creator_collateral_account.to_account_info(),
+        mint: accounts.collateral_mint.to_account_info(),
+        to: accounts.collateral_vault.to_account_info(),
+        authority: accounts.creator.to_account_info()
+    };
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    transfer_checked(cpi_ctx, gross_up_transfer_fee(&accounts.collateral_mint, funding)?, accounts.collateral_mint.decimals)?;
+
+    // The creator's deposit is the first LP position, one LP token per unit of funding.
+    let market = &mut accounts.market;
+    market.lp_token_mint = accounts.lp_token_mint.key();
+
+    let market_key = market.key();
+    let authority_seeds = &[
+        b"authority",
+        market_key.as_ref(),
+        &[market.bump]
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    let cpi_program = accounts.token_program.to_account_info();
+    let cpi_accounts = MintTo {
+        mint: accounts.lp_token_mint.to_account_info(),
+        to: accounts.creator_lp_token_account.to_account_info(),
+        authority: accounts.market_authority.to_account_info()
+    };
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    mint_to(cpi_ctx, funding)?;
+
+    let market = &accounts.market;
+    emit!(MarketCreated {
+        market: market.key(),
+        creator: market.creator,
+        market_id: market.market_id,
+        question: market.question.clone(),
+        market_type: market.market_type.clone(),
+        end_timestamp: market.end_timestamp,
+        collateral_mint: market.collateral_mint,
+        initial_liquidity: funding,
+        liquidity_parameter: market.liquidity_parameter,
+        fee_bps: market.fee_bps,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Fills resting orders on the opposite side of a taker for up to `shares`, best
+/// price first and oldest first within a price. Only orders that beat the AMM's
+/// current quote (`amm_price`) are taken. Returns the shares filled, the
+/// collateral that changes hands (what a buying taker owes, rounded up, or what a
+/// selling taker receives, rounded down) and the orders filled completely, which
+/// leave the book in fill order for `pay_filled_orders`. A partly filled order is
+/// credited in place.
+fn match_orders(order_book: &mut OrderBook, outcome: &ShareOutcome, taker_side: OrderSide, shares: u64, amm_price: u64) -> Result<(u64, u64, Vec<Order>)> {
+    let mut candidates: Vec<usize> = order_book.orders
+        .iter()
+        .enumerate()
+        .filter(|(_, order)| {
+            order.outcome == *outcome && order.side != taker_side && order.remaining_shares > 0 && match taker_side {
+                OrderSide::Bid => order.price <= amm_price,
+                OrderSide::Ask => order.price >= amm_price,
+            }
+        })
+        .map(|(index, _)| index)
+        .collect();
+    candidates.sort_by_key(|&index| {
+        let order = &order_book.orders[index];
+        let rank = match taker_side {
+            OrderSide::Bid => order.price,
+            OrderSide::Ask => u64::MAX - order.price,
+        };
+        (rank, order.id)
+    });
+
+    let mut filled = 0u64;
+    let mut collateral = 0u64;
+    let mut completed = Vec::new();
+    for index in candidates {
+        if filled == shares {
+            break;
+        }
+        let order = &mut order_book.orders[index];
+        let fill = order.remaining_shares.min(shares - filled);
+        let amount = match taker_side {
+            OrderSide::Bid => {
+                let cost = mul_div_ceil(fill, order.price, PRICE_PRECISION)?;
+                order.proceeds_collateral = order.proceeds_collateral
+                    .checked_add(cost)
+                    .ok_or(MarketError::MathOverflow)?;
+                cost
+            },
+            OrderSide::Ask => {
+                let payout = mul_div_floor(fill, order.price, PRICE_PRECISION)?;
+                order.escrowed_collateral = order.escrowed_collateral
+                    .checked_sub(payout)
+                    .ok_or(MarketError::MathUnderflow)?;
+                order.proceeds_shares = order.proceeds_shares
+                    .checked_add(fill)
+                    .ok_or(MarketError::MathOverflow)?;
+                payout
+            },
+        };
+        order.remaining_shares -= fill;
+        if order.remaining_shares == 0 {
+            completed.push(order.id);
+        }
+        filled += fill;
+        collateral = collateral
+            .checked_add(amount)
+            .ok_or(MarketError::MathOverflow)?;
+    }
+
+    let mut filled_orders = Vec::with_capacity(completed.len());
+    for id in completed {
+        let position = order_book.orders
+            .iter()
+            .position(|order| order.id == id)
+            .ok_or(MarketError::OrderNotFound)?;
+        filled_orders.push(order_book.orders.remove(position));
+    }
+
+    Ok((filled, collateral, filled_orders))
+}
+
+/// What an order owes its owner when it leaves the book, as collateral and
+/// outcome tokens: the unfilled escrow plus whatever fills have credited to it.
+fn order_payout(order: &Order) -> Result<(u64, u64)> {
+    let collateral_out = order.escrowed_collateral
+        .checked_add(order.proceeds_collateral)
+        .ok_or(MarketError::MathOverflow)?;
+    let escrowed_shares = if order.side == OrderSide::Ask { order.remaining_shares } else { 0 };
+    let shares_out = escrowed_shares
+        .checked_add(order.proceeds_shares)
+        .ok_or(MarketError::MathOverflow)?;
+    Ok((collateral_out, shares_out))
+}
+
+/// Pays out the orders a trade filled completely. `maker_accounts` are the
+/// trade's remaining accounts: two per filled order, in fill order, the owner's
+/// collateral account and then their account for the traded outcome's token.
+fn pay_filled_orders<'info>(
+    market: Pubkey,
+    filled_orders: &[Order],
+    maker_accounts: &[AccountInfo<'info>],
+    book_collateral_vault: &InterfaceAccount<'info, TokenAccount>,
+    book_share_vault: &InterfaceAccount<'info, TokenAccount>,
+    collateral_mint: &InterfaceAccount<'info, Mint>,
+    share_mint: &InterfaceAccount<'info, Mint>,
+    market_authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(maker_accounts.len() == filled_orders.len() * 2, MarketError::InvalidTokenAccount);
+
+    let clock = Clock::get()?;
+    for (order, accounts) in filled_orders.iter().zip(maker_accounts.chunks_exact(2)) {
+        let (collateral_account, share_account) = (&accounts[0], &accounts[1]);
+        maker_token_account(collateral_account, token_program, &order.owner, &collateral_mint.key())?;
+        maker_token_account(share_account, token_program, &order.owner, &share_mint.key())?;
+
+        let (collateral_out, shares_out) = order_payout(order)?;
+        if collateral_out > 0 {
+            let cpi_accounts = TransferChecked {
+                from: book_collateral_vault.to_account_info(),
+                mint: collateral_mint.to_account_info(),
+                to: collateral_account.clone(),
+                authority: market_authority.clone()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, collateral_out, collateral_mint.decimals)?;
+        }
+        if shares_out > 0 {
+            let cpi_accounts = TransferChecked {
+                from: book_share_vault.to_account_info(),
+                mint: share_mint.to_account_info(),
+                to: share_account.clone(),
+                authority: market_authority.clone()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, shares_out, share_mint.decimals)?;
+        }
+
+        emit!(OrderFilled {
+            market,
+            owner: order.owner,
+            order_id: order.id,
+            collateral_out,
+            shares_out,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that a remaining account is a token account of `owner` for `mint`.
+fn maker_token_account(info: &AccountInfo, token_program: &Interface<TokenInterface>, owner: &Pubkey, mint: &Pubkey) -> Result<()> {
+    require!(info.owner == &token_program.key(), MarketError::InvalidTokenAccount);
+    let account = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(account.owner == *owner && account.mint == *mint, MarketError::InvalidTokenAccount);
+    Ok(())
+}
+
+/// Checks the escrow vaults passed to a trade that hit the book against the ones
+/// the book was created with.
+fn book_vaults<'a, 'info>(
+    order_book: Option<&Account<'info, OrderBook>>,
+    collateral_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    share_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    outcome: &ShareOutcome,
+) -> Result<(&'a InterfaceAccount<'info, TokenAccount>, &'a InterfaceAccount<'info, TokenAccount>)> {
+    let order_book = order_book.ok_or(MarketError::InvalidTokenAccount)?;
+    let collateral_vault = collateral_vault.ok_or(MarketError::InvalidTokenAccount)?;
+    let share_vault = share_vault.ok_or(MarketError::InvalidTokenAccount)?;
+
+    let expected_share_vault = match outcome {
+        ShareOutcome::Yes => order_book.yes_vault,
+        ShareOutcome::No => order_book.no_vault,
+    };
+    require!(collateral_vault.key() == order_book.collateral_vault, MarketError::InvalidTokenAccount);
+    require!(share_vault.key() == expected_share_vault, MarketError::InvalidTokenAccount);
+    Ok((collateral_vault, share_vault))
+}
+
+/// Buys `shares_desired` shares, routing through the order book when one is passed.
+/// `max_cost` bounds the price paid; `max_debit` bounds what actually leaves the
+/// user's account once every transfer is grossed up for the mint's transfer fee.
+fn execute_buy<'info>(ctx: Context<'_, '_, '_, 'info, BuyShares<'info>>, outcome: ShareOutcome, max_cost: u64, shares_desired: u64, max_debit: u64) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    require!(market.market_kind != MarketKind::Categorical, MarketError::UnsupportedMarketKind);
+    require!(!market.resolved, MarketError::MarketResolved);
+    require!(!market.paused, MarketError::MarketPaused);
+    require!(Clock::get()?.unix_timestamp < market.trading_close_timestamp, MarketError::TradingClosed);
+    require!(shares_desired > 0, MarketError::ZeroAmount);
+
+    let (user_share_account, share_mint) = match outcome {
+        ShareOutcome::Yes => (ctx.accounts.user_yes_token_account.as_ref(), &ctx.accounts.yes_token_mint),
+        ShareOutcome::No => (ctx.accounts.user_no_token_account.as_ref(), &ctx.accounts.no_token_mint),
+    };
+    let user_share_account = user_share_account.ok_or(MarketError::InvalidTokenAccount)?;
+
+    // Resting asks priced at or below the AMM quote are filled first; only the
+    // remainder trades against the AMM. Book fills are peer-to-peer and fee-free.
+    let (book_shares, book_cost, filled_orders) = match ctx.accounts.order_book.as_mut() {
+        Some(order_book) => {
+            let amm_price = calculate_price(market, outcome.index())?;
+            match_orders(order_book, &outcome, OrderSide::Bid, shares_desired, amm_price)?
+        },
+        None => (0, 0, Vec::new()),
+    };
+    let amm_shares = shares_desired - book_shares;
+
+    let actual_cost = if amm_shares > 0 {
+        calculate_buy_cost(market, outcome.index(), amm_shares)?
+    } else {
+        0
+    };
+    let (creator_fee, protocol_fee) = calculate_fees(actual_cost, market.fee_bps)?;
+    let total_cost = actual_cost
+        .checked_add(creator_fee)
+        .and_then(|cost| cost.checked_add(protocol_fee))
+        .and_then(|cost| cost.checked_add(book_cost))
+        .ok_or(MarketError::MathOverflow)?;
+    require!(total_cost <= max_cost, MarketError::SlippageExceeded);
+
+    let collateral_mint = &ctx.accounts.collateral_mint;
+    let book_debit = gross_up_transfer_fee(collateral_mint, book_cost)?;
+    let cost_debit = gross_up_transfer_fee(collateral_mint, actual_cost)?;
+    let creator_fee_debit = gross_up_transfer_fee(collateral_mint, creator_fee)?;
+    let protocol_fee_debit = gross_up_transfer_fee(collateral_mint, protocol_fee)?;
+    let debit = book_debit
+        .checked_add(cost_debit)
+        .and_then(|debit| debit.checked_add(creator_fee_debit))
+        .and_then(|debit| debit.checked_add(protocol_fee_debit))
+        .ok_or(MarketError::MathOverflow)?;
+    require!(debit <= max_debit, MarketError::SlippageExceeded);
+
+    let market_key = market.key();
+    let authority_seeds = &[
+        b"authority",
+        market_key.as_ref(),
+        &[market.bump]
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    if book_shares > 0 {
+        let (book_collateral_vault, book_share_vault) = book_vaults(
+            ctx.accounts.order_book.as_deref(),
+            ctx.accounts.book_collateral_vault.as_deref(),
+            ctx.accounts.book_share_vault.as_deref(),
+            &outcome,
+        )?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: book_collateral_vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, book_debit, ctx.accounts.collateral_mint.decimals)?;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: book_share_vault.to_account_info(),
+            mint: share_mint.to_account_info(),
+            to: user_share_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, book_shares, share_mint.decimals)?;
+
+        pay_filled_orders(
+            market.key(),
+            &filled_orders,
+            ctx.remaining_accounts,
+            book_collateral_vault,
+            book_share_vault,
+            &ctx.accounts.collateral_mint,
+            share_mint,
+            &ctx.accounts.market_authority,
+            &ctx.accounts.token_program,
+            signer_seeds,
+        )?;
+    }
+
+    if amm_shares > 0 {
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_collateral_account.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, cost_debit, ctx.accounts.collateral_mint.decimals)?;
+
+        if creator_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_collateral_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.creator_fee_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer_checked(cpi_ctx, creator_fee_debit, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        if protocol_fee > 0 {
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_collateral_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.protocol_fee_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer_checked(cpi_ctx, protocol_fee_debit, ctx.accounts.collateral_mint.decimals)?;
+        }
+
+        market.creator_fees_accrued = market.creator_fees_accrued
+            .checked_add(creator_fee)
+            .ok_or(MarketError::MathOverflow)?;
+        market.protocol_fees_accrued = market.protocol_fees_accrued
+            .checked_add(protocol_fee)
+            .ok_or(MarketError::MathOverflow)?;
+
+        match outcome {
+            ShareOutcome::Yes => {
+                market.yes_shares_outstanding = market.yes_shares_outstanding
+                    .checked_add(amm_shares)
+                    .ok_or(MarketError::MathOverflow)?;
+            },
+            ShareOutcome::No => {
+                market.no_shares_outstanding = market.no_shares_outstanding
+                    .checked_add(amm_shares)
+                    .ok_or(MarketError::MathOverflow)?;
+            }
+        }
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = MintTo {
+            mint: share_mint.to_account_info(),
+            to: user_share_account.to_account_info(),
+            authority: ctx.accounts.market_authority.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, amm_shares)?;
+
+        market.total_liquidity = market.total_liquidity
+            .checked_add(actual_cost)
+            .ok_or(MarketError::MathOverflow)?;
+    }
+
+    let clock = Clock::get()?;
+    emit!(SharesBought {
+        market: market.key(),
+        user: ctx.accounts.user.key(),
+        outcome,
+        shares: shares_desired,
+        book_shares,
+        cost: actual_cost + book_cost,
+        fee: creator_fee + protocol_fee,
+        yes_price: calculate_price(market, ShareOutcome::Yes.index())?,
+        no_price: calculate_price(market, ShareOutcome::No.index())?,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Resolves an optimistic market to `outcome` and pays `bonds` from the bond vault
+/// to `winner`, whose collateral account must be the one passed in.
+fn settle_resolution(ctx: Context<SettleResolution>, outcome: WinningOutcome, winner: Pubkey, bonds: u64, clock: Clock) -> Result<()> {
+    require!(ctx.accounts.winner_collateral_account.owner == winner, MarketError::InvalidTokenAccount);
+
+    let market = &mut ctx.accounts.market;
+    market.winning_outcome = outcome;
+    if market.winning_outcome == WinningOutcome::Canceled {
+        snapshot_refund_rates(market)?;
+    }
+    market.resolved = true;
+
+    let market_key = market.key();
+    let authority_seeds = &[
+        b"authority",
+        market_key.as_ref(),
+        &[market.bump]
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.bond_vault.to_account_info(),
+        mint: ctx.accounts.collateral_mint.to_account_info(),
+        to: ctx.accounts.winner_collateral_account.to_account_info(),
+        authority: ctx.accounts.market_authority.to_account_info()
+    };
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    transfer_checked(cpi_ctx, bonds, ctx.accounts.collateral_mint.decimals)?;
+
+    emit!(MarketResolved {
+        market: market.key(),
+        resolver: ctx.accounts.resolver.key(),
+        outcome: market.winning_outcome.clone(),
+        oracle_price: None,
+        oracle_expo: None,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn set_market_paused(ctx: Context<ManageMarket>, paused: bool) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    require!(ctx.accounts.creator.key() == market.creator, MarketError::UnauthorizedResolver);
+    require!(!market.resolved, MarketError::MarketAlreadyResolved);
+    market.paused = paused;
+
+    let clock = Clock::get()?;
+    emit!(MarketPauseChanged {
+        market: market.key(),
+        paused,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn validate_resolver(resolver: &ResolverConfig) -> Result<()> {
+    if let ResolverConfig::Committee { members, threshold } = resolver {
+        require!(!members.is_empty() && members.len() <= MAX_COMMITTEE_MEMBERS, MarketError::InvalidResolverConfig);
+        require!(*threshold > 0 && *threshold as usize <= members.len(), MarketError::InvalidResolverConfig);
+        let has_duplicates = members
+            .iter()
+            .enumerate()
+            .any(|(index, member)| members[..index].contains(member));
+        require!(!has_duplicates, MarketError::InvalidResolverConfig);
+    }
+    Ok(())
+}
+
+/// Fails unless `resolver` may settle the market on its own. Committee markets
+/// only resolve through `vote_resolution`.
+fn authorize_resolver(market: &Market, resolver: &Pubkey) -> Result<()> {
+    match &market.resolver {
+        ResolverConfig::Single(key) => require!(key == resolver, MarketError::UnauthorizedResolver),
+        ResolverConfig::Committee { .. } => return err!(MarketError::CommitteeResolutionRequired),
+    }
+    Ok(())
+}
+
+fn calculate_price(market: &Market, index: usize) -> Result<u64> {
+    math::price(&market.shares(), market.liquidity_parameter, index)
+}
+
+fn outcome_prices(market: &Market) -> Result<Vec<u64>> {
+    math::prices(&market.shares(), market.liquidity_parameter)
+}
+
+/// Prices a two-outcome trade exactly as `buy_shares` and `sell_shares` price
+/// their AMM leg. Resting orders and Token-2022 transfer fees are left out.
+fn preview_trade(market: &Market, outcome: &ShareOutcome, side: &OrderSide, shares: u64) -> Result<TradeQuote> {
+    let index = outcome.index();
+    let before = market.shares();
+    let mut after = before.clone();
+    let amount = match side {
+        OrderSide::Bid => {
+            after[index] = after[index].checked_add(shares).ok_or(MarketError::MathOverflow)?;
+            calculate_buy_cost(market, index, shares)?
+        },
+        OrderSide::Ask => {
+            after[index] = after[index].checked_sub(shares).ok_or(MarketError::MathUnderflow)?;
+            calculate_sell_payout(market, index, shares)?
+        },
+    };
+
+    let (creator_fee, protocol_fee) = calculate_fees(amount, market.fee_bps)?;
+    let fee = creator_fee.checked_add(protocol_fee).ok_or(MarketError::MathOverflow)?;
+    let total = match side {
+        OrderSide::Bid => amount.checked_add(fee).ok_or(MarketError::MathOverflow)?,
+        OrderSide::Ask => amount.checked_sub(fee).ok_or(MarketError::MathUnderflow)?,
+    };
+
+    let b = market.liquidity_parameter;
+    let price_before = math::lmsr_price(&before, b, index)?;
+    let price_after = math::lmsr_price(&after, b, index)?;
+    let price_move = price_after
+        .abs_diff(price_before)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(MarketError::MathOverflow)?;
+    // Far enough out, a price rounds to zero and any move off it is unbounded.
+    let price_impact_bps = match price_move.checked_div(price_before) {
+        Some(impact) => u64::try_from(impact).unwrap_or(u64::MAX),
+        None if price_move == 0 => 0,
+        None => u64::MAX,
+    };
+
+    Ok(TradeQuote {
+        amount,
+        fee,
+        total,
+        average_price: mul_div_floor(amount, PRICE_PRECISION, shares)?,
+        yes_price_after: math::price(&after, b, ShareOutcome::Yes.index())?,
+        no_price_after: math::price(&after, b, ShareOutcome::No.index())?,
+        price_impact_bps,
+    })
+}
+
+/// Largest number of `index` shares the AMM sells for at most `budget` collateral,
+/// fees and the collateral's transfer fee included. The closed-form estimate lands
+/// within a few shares of the answer and the search settles the rounding, keeping
+/// the instruction's compute bounded.
+fn affordable_shares(market: &Market, transfer_fees: &TransferFeeSchedule, index: usize, budget: u64) -> Result<u64> {
+    let shares = market.shares();
+    let b = market.liquidity_parameter;
+    let outcome_shares = shares[index];
+
+    // Buying `s` shares costs at least `q_i + s - C(q)`, which bounds the search.
+    let cost_before = math::lmsr_cost(&shares, b)?;
+    let upper = budget
+        .saturating_add(u64::try_from(cost_before.div_ceil(FP_ONE)).map_err(|_| error!(MarketError::MathOverflow))?)
+        .saturating_sub(outcome_shares)
+        .min(u64::MAX - shares.iter().copied().max().unwrap_or(0));
+
+    // Each leg of the buy pays its own, possibly capped, transfer fee, so size the
+    // legs as if the budget were fee-free and take their fees off it.
+    let spend_of = |budget: u64| mul_div_floor(budget, BPS_DENOMINATOR as u64, (BPS_DENOMINATOR + market.fee_bps) as u64);
+    let cost = spend_of(budget)?;
+    let (creator_fee, protocol_fee) = calculate_fees(cost, market.fee_bps)?;
+    let mut net_budget = budget;
+    for leg in [cost, creator_fee, protocol_fee] {
+        net_budget = net_budget.saturating_sub(transfer_fees.fee(leg)?);
+    }
+
+    let estimate = math::shares_for_cost(&shares, b, index, spend_of(net_budget)?)?;
+    let over_budget = |amount: u64| -> Result<bool> {
+        let cost = math::buy_cost_from(&shares, b, cost_before, index, amount)?;
+        let (creator_fee, protocol_fee) = calculate_fees(cost, market.fee_bps)?;
+        let debit = [cost, creator_fee, protocol_fee]
+            .into_iter()
+            .try_fold(0u64, |debit, amount| transfer_fees.gross_up(amount).ok().and_then(|amount| debit.checked_add(amount)));
+        Ok(debit.is_none_or(|debit| debit > budget))
+    };
+    let affordable = match math::first_reaching(1, upper, estimate.saturating_add(1), over_budget)? {
+        Some(first_over) => first_over - 1,
+        None => upper,
+    };
+    Ok(affordable)
+}
+
+/// Fewest `index` shares, at most `max_shares`, whose AMM sale nets `target`
+/// collateral after fees, or `None` if no sale within the limit does.
+fn shares_to_sell_for(market: &Market, transfer_fees: &TransferFeeSchedule, index: usize, target: u64, max_shares: u64) -> Result<Option<u64>> {
+    let shares = market.shares();
+    let b = market.liquidity_parameter;
+    let upper = max_shares.min(shares[index]);
+    let cost_before = math::lmsr_cost(&shares, b)?;
+
+    // Moving the fees out of the vault costs transfer fees the seller covers too.
+    let payout_of = |target: u64| mul_div_ceil(target, BPS_DENOMINATOR as u64, (BPS_DENOMINATOR - market.fee_bps) as u64);
+    let (creator_fee, protocol_fee) = calculate_fees(payout_of(target)?, market.fee_bps)?;
+    let fee_transfer_cost = (transfer_fees.gross_up(creator_fee)? - creator_fee)
+        .checked_add(transfer_fees.gross_up(protocol_fee)? - protocol_fee)
+        .ok_or(MarketError::MathOverflow)?;
+    let payout = payout_of(target.saturating_add(fee_transfer_cost))?;
+
+    let estimate = math::shares_for_payout(&shares, b, index, payout)?;
+    let enough = |amount: u64| -> Result<bool> {
+        let payout = math::sell_payout_from(&shares, b, cost_before, index, amount)?;
+        // A payout too small to cover the fees' transfer cost nets nothing.
+        let net_payout = split_sell_payout(transfer_fees, payout, market.fee_bps).map_or(0, |(_, _, net_payout)| net_payout);
+        Ok(net_payout >= target)
+    };
+    math::first_reaching(1, upper, estimate, enough)
+}
+
+fn calculate_buy_cost(market: &Market, index: usize, shares: u64) -> Result<u64> {
+    math::buy_cost(&market.shares(), market.liquidity_parameter, index, shares)
+}
+
+fn calculate_sell_payout(market: &Market, index: usize, shares: u64) -> Result<u64> {
+    math::sell_payout(&market.shares(), market.liquidity_parameter, index, shares)
+}
+
+/// Freezes the per-token refund of a canceled market at the AMM prices it was
+/// canceled at. Prices sum to at most one and `sum(price_i * q_i) <= C(q)`, so the
+/// vault always covers every refund and the remainder belongs to the LPs.
+fn snapshot_refund_rates(market: &mut Market) -> Result<()> {
+    let rates = math::refund_rates(&market.shares(), market.liquidity_parameter)?;
+
+    match market.market_kind {
+        MarketKind::Binary | MarketKind::Scalar => {
+            market.canceled_yes_refund_rate = rates[0];
+            market.canceled_no_refund_rate = rates[1];
+        },
+        MarketKind::Categorical => market.canceled_outcome_refund_rates = rates,
+    }
+    Ok(())
+}
+
+/// Fails unless the pool's collateral still covers the LMSR cost of the outstanding
+/// shares, which bounds what the market maker can lose from here on.
+fn require_pool_solvent(market: &Market) -> Result<()> {
+    let cost = lmsr_cost(&market.shares(), market.liquidity_parameter)?;
+    let collateral = (market.total_liquidity as u128)
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?;
+    require!(cost <= collateral, MarketError::InsufficientLiquidity);
+    Ok(())
+}
+
+/// Checks that a resolution outcome is one this market can actually settle to.
+fn validate_outcome(market: &Market, outcome: &WinningOutcome) -> Result<()> {
+    let valid = match (outcome, &market.market_kind) {
+        (WinningOutcome::Canceled, _) => true,
+        (WinningOutcome::Yes | WinningOutcome::No, MarketKind::Binary) => true,
+        (WinningOutcome::Outcome(index), MarketKind::Categorical) => (*index as usize) < market.outcome_shares.len(),
+        _ => false,
+    };
+    require!(valid, MarketError::InvalidOutcome);
+    Ok(())
+}
+
+/// Collateral owed to holders redeeming at fixed per-token rates, rounded up so
+/// LP withdrawals can never eat into it.
+fn rated_obligations(supplies: &[u64], rates: &[u64]) -> Result<u64> {
+    supplies.iter().zip(rates.iter()).try_fold(0u64, |total, (&supply, &rate)| {
+        total
+            .checked_add(mul_div_ceil(supply, rate, REFUND_RATE_PRECISION)?)
+            .ok_or(error!(MarketError::MathOverflow))
+    })
+}
+
+/// Loads the resolution price from the Pyth feed, rejecting prices that were
+/// published before the market ended, are older than `oracle_max_age`, or whose
+/// confidence interval is wider than `oracle_max_conf_bps` of the threshold.
+fn read_oracle_price(feed_account: &AccountInfo, market: &Market, threshold: i64, clock: i64) -> Result<Price> {
+    let price_feed: PriceFeed = SolanaPriceAccount::account_info_to_feed(feed_account)
+        .map_err(|_| error!(MarketError::InvalidOracleFeed))?;
+
+    let price = price_feed
+        .get_price_no_older_than(clock, market.oracle_max_age)
+        .ok_or(MarketError::OraclePriceStale)?;
+    require!(price.publish_time > market.end_timestamp, MarketError::OracleNotAvailable);
+    require!(price.price > 0, MarketError::OraclePriceStale);
+
+    let target_expo = price.expo.min(market.oracle_threshold_expo);
+    let conf = i64::try_from(price.conf).map_err(|_| error!(MarketError::MathOverflow))?;
+    let scaled_conf = scale_to_expo(conf, price.expo, target_expo)?;
+    let scaled_threshold = scale_to_expo(threshold, market.oracle_threshold_expo, target_expo)?;
+
+    let max_conf = scaled_threshold
+        .unsigned_abs()
+        .checked_mul(market.oracle_max_conf_bps as u128)
+        .ok_or(MarketError::MathOverflow)?;
+    let weighted_conf = (scaled_conf as u128)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(MarketError::MathOverflow)?;
+    require!(weighted_conf <= max_conf, MarketError::OracleConfidenceTooWide);
+
+    Ok(price)
+}
+
+/// Decides a binary oracle market by comparing the feed price against the stored
+/// threshold. Both values are rescaled to the finer of the two exponents first so
+/// that e.g. a threshold of `100_000` at expo `0` matches a feed price of
+/// `10_000_000_000_000` at expo `-8`.
+fn oracle_outcome(price: &Price, threshold: i64, threshold_expo: i32, comparison: &OracleComparison) -> Result<WinningOutcome> {
+    let target_expo = price.expo.min(threshold_expo);
+    let scaled_price = scale_to_expo(price.price, price.expo, target_expo)?;
+    let scaled_threshold = scale_to_expo(threshold, threshold_expo, target_expo)?;
+
+    let condition_met = match comparison {
+        OracleComparison::Above => scaled_price > scaled_threshold,
+        OracleComparison::Below => scaled_price < scaled_threshold,
+        OracleComparison::EqualOrAbove => scaled_price >= scaled_threshold,
+    };
+
+    Ok(if condition_met { WinningOutcome::Yes } else { WinningOutcome::No })
+}
+
+/// Share of a unit of collateral paid to one LONG token of a scalar market: the
+/// price is clamped into `[lower, upper]` and interpolated linearly. Rounded down,
+/// with SHORT taking the remainder so each LONG/SHORT pair pays exactly one unit.
+fn scalar_long_payout_rate(price: &Price, lower: i64, upper: i64, bound_expo: i32) -> Result<u64> {
+    let target_expo = price.expo.min(bound_expo);
+    let scaled_price = scale_to_expo(price.price, price.expo, target_expo)?;
+    let scaled_lower = scale_to_expo(lower, bound_expo, target_expo)?;
+    let scaled_upper = scale_to_expo(upper, bound_expo, target_expo)?;
+
+    let clamped = scaled_price.clamp(scaled_lower, scaled_upper);
+    let rate = (clamped - scaled_lower)
+        .checked_mul(REFUND_RATE_PRECISION as i128)
+        .ok_or(MarketError::MathOverflow)?
+        / (scaled_upper - scaled_lower);
+    let rate = u64::try_from(rate).map_err(|_| error!(MarketError::MathOverflow))?;
+    Ok(rate)
+}
+
+fn scale_to_expo(value: i64, from_expo: i32, to_expo: i32) -> Result<i128> {
+    let shift = from_expo
+        .checked_sub(to_expo)
+        .and_then(|shift| u32::try_from(shift).ok())
+        .ok_or(MarketError::MathOverflow)?;
+    let factor = 10i128
+        .checked_pow(shift)
+        .ok_or(MarketError::MathOverflow)?;
+
+    let scaled = (value as i128)
+        .checked_mul(factor)
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(scaled)
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ MarketError::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CreateMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"yes_mint", market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market_authority
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"no_mint", market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market_authority
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority 
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"collateral_vault", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"creator_fees", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"protocol_fees", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub protocol_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"lp_mint", market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market_authority
+    )]
+    pub lp_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = lp_token_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_lp_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = collateral_mint
+    )]
+    pub creator_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CreateCategoricalMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority 
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"collateral_vault", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"creator_fees", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"protocol_fees", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub protocol_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"lp_mint", market.key().as_ref()],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market_authority
+    )]
+    pub lp_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = lp_token_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_lp_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = collateral_mint
+    )]
+    pub creator_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8)]
+pub struct InitOutcomeMint<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"outcome_mint", market.key().as_ref(), &[outcome_index]],
+        bump,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market_authority
+    )]
+    pub outcome_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct TradeOutcomeShares<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = outcome_mint
+    )]
+    pub user_outcome_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.creator_fee_vault
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.protocol_fee_vault
+    )]
+    pub protocol_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Checked against `market.outcome_mints` for the traded index.
+    #[account(mut)]
+    pub outcome_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)] 
+pub struct BuyShares<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market:Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.creator_fee_vault
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.protocol_fee_vault
+    )]
+    pub protocol_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.yes_token_mint
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    /// Only needed to trade against resting orders.
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Option<Box<Account<'info, OrderBook>>>,
+    #[account(mut)]
+    pub book_collateral_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// The book's escrow vault for the traded outcome.
+    #[account(mut)]
+    pub book_share_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct SellShares<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut, 
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut, 
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.creator_fee_vault
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.protocol_fee_vault
+    )]
+    pub protocol_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut, 
+        address = market.yes_token_mint
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut, 
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    /// Only needed to trade against resting orders.
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Option<Box<Account<'info, OrderBook>>>,
+    #[account(mut)]
+    pub book_collateral_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// The book's escrow vault for the traded outcome.
+    #[account(mut)]
+    pub book_share_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
 
 #[derive(Accounts)]
-pub struct CreateMarket<'info> {
+pub struct InitOrderBook<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    pub market: Account<'info, Market>,
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub payer: Signer<'info>,
     #[account(
         init,
-        payer = creator,
-        space = 8 + Market::INIT_SPACE,
+        payer = payer,
+        space = 8 + OrderBook::INIT_SPACE,
+        seeds = [b"order_book", market.key().as_ref()],
+        bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = market.yes_token_mint)]
+    pub yes_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = market.no_token_mint)]
+    pub no_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"book_collateral", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub book_collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"book_yes", market.key().as_ref()],
+        bump,
+        token::mint = yes_token_mint,
+        token::authority = market_authority,
+    )]
+    pub book_yes_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"book_no", market.key().as_ref()],
+        bump,
+        token::mint = no_token_mint,
+        token::authority = market_authority,
+    )]
+    pub book_no_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    pub owner: Signer<'info>,
+    /// Collateral account for bids, outcome token account for asks.
+    #[account(mut)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// The book vault matching `owner_token_account`'s mint.
+    #[account(mut)]
+    pub escrow_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Mint of the escrowed token.
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub owner_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner_share_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub book_collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub book_share_vault: InterfaceAccount<'info, TokenAccount>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// Mint of the canceled order's outcome.
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct MintCompleteSet<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.yes_token_mint
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct MergeCompleteSet<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.yes_token_mint
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.lp_token_mint
+    )]
+    pub user_lp_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.yes_token_mint
+    )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.lp_token_mint
+    )]
+    pub lp_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = market.collateral_mint
+    )]
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.yes_token_mint
+    )]
+    pub user_yes_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.no_token_mint
+    )]
+    pub user_no_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = market.lp_token_mint
+    )]
+    pub user_lp_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.yes_token_mint
     )]
+    pub yes_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.no_token_mint
+    )]
+    pub no_token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = market.lp_token_mint
+    )]
+    pub lp_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    ///CHECK: Resolution source (oracle feed or admin)
+    #[account(address = market.resolution_source)]
+    pub resolution_source: AccountInfo<'info>,
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemWinnings<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
     pub market: Account<'info, Market>,
-    pub collateral_mint: Account<'info, Mint>,
+    pub user: Signer<'info>,
     #[account(
-        init,
-        payer = creator,
-        mint::decimals = collateral_mint.decimals,
-        mint::authority = market_authority
+        mut, 
+        token::mint = market.collateral_mint
     )]
-    pub yes_token_mint: Account<'info, Mint>,
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_winning_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        init,
-        payer = creator,
-        mint::decimals = collateral_mint.decimals,
-        mint::authority = market_authority
+        mut, 
+        address = market.collateral_vault
     )]
-    pub no_token_mint: Account<'info, Mint>,
-    ///CHECK: PDA authority 
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub winning_token_mint: InterfaceAccount<'info, Mint>,
+    ///CHECK: PDA authority
     #[account(
         seeds = [b"authority", market.key().as_ref()],
-        bump
+        bump = market.bump
     )]
     pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFees<'info> {
     #[account(
-        init,
-        payer = creator,
-        token::mint = collateral_mint,
-        token::authority = market_authority,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
     )]
-    pub collateral_vault: Account<'info, TokenAccount>,
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub claimer: Signer<'info>,
+    #[account(mut)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
-        token::mint = collateral_mint
+        token::mint = market.collateral_mint
     )]
-    pub creator_collateral_account: Account<'info, TokenAccount>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[derive(Accounts)] 
-pub struct BuyShares<'info> {
-    #[account(mut)]
-    pub market:Account<'info, Market>,
+#[derive(Accounts)]
+pub struct WithdrawRemainingLiquidity<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
     #[account(mut)]
+    pub market: Account<'info, Market>,
     pub user: Signer<'info>,
     #[account(
         mut,
         token::mint = market.collateral_mint
     )]
-    pub user_collateral_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        token::mint = market.yes_token_mint
-    )]
-    pub user_yes_token_account: Option<Account<'info, TokenAccount>>,
+    pub user_collateral_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
-        token::mint = market.no_token_mint
+        token::mint = market.lp_token_mint
     )]
-    pub user_no_token_account: Option<Account<'info, TokenAccount>>,
+    pub user_lp_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         address = market.collateral_vault
     )]
-    pub collateral_vault: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        address = market.yes_token_mint
-    )]
-    pub yes_token_mint: Account<'info, Mint>,
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Only needed for binary markets.
+    #[account(address = market.yes_token_mint)]
+    pub yes_token_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// Only needed for binary markets.
+    #[account(address = market.no_token_mint)]
+    pub no_token_mint: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
-        address = market.no_token_mint
+        address = market.lp_token_mint
     )]
-    pub no_token_mint: Account<'info, Mint>,
+    pub lp_token_mint: InterfaceAccount<'info, Mint>,
     ///CHECK: PDA authority
     #[account(
         seeds = [b"authority", market.key().as_ref()],
         bump = market.bump
     )]
     pub market_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct SellShares<'info> {
+pub struct GetMarketPrice<'info> {
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureDispute<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
     #[account(mut)]
     pub market: Account<'info, Market>,
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub creator: Signer<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
     #[account(
-        mut, 
-        token::mint = market.collateral_mint
+        init,
+        payer = creator,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = market_authority,
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    ///CHECK: PDA authority
+    #[account(
+        seeds = [b"authority", market.key().as_ref()],
+        bump = market.bump
+    )]
+    pub market_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PostBond<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
     )]
-    pub user_collateral_account: Account<'info, TokenAccount>,
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub bonder: Signer<'info>,
     #[account(
         mut,
-        token::mint = market.yes_token_mint
+        token::mint = market.collateral_mint
     )]
-    pub user_yes_token_account: Account<'info, TokenAccount>,
+    pub bonder_collateral_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
-        token::mint = market.no_token_mint
+        address = market.bond_vault
     )]
-    pub user_no_token_account: Account<'info, TokenAccount>,
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SettleResolution<'info> {
     #[account(
-        mut, 
-        address = market.collateral_vault
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
     )]
-    pub collateral_vault: Account<'info, TokenAccount>,
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub resolver: Signer<'info>,
     #[account(
-        mut, 
-        address = market.yes_token_mint
+        mut,
+        token::mint = market.collateral_mint
     )]
-    pub yes_token_mint: Account<'info, Mint>,
+    pub winner_collateral_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut, 
-        address = market.no_token_mint
+        mut,
+        address = market.bond_vault
     )]
-    pub no_token_mint: Account<'info, Mint>,
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
     ///CHECK: PDA authority
     #[account(
         seeds = [b"authority", market.key().as_ref()],
         bump = market.bump
     )]
     pub market_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-}
-
-#[derive(Accounts)]
-pub struct ResolveMarket<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
-    ///CHECK: Resolution source (oracle feed or admin)
-    #[account(address = market.resolution_source)]
-    pub resolution_source: AccountInfo<'info>,
-    pub resolver: Signer<'info>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct RedeemWinnings<'info> {
+pub struct ExpireDispute<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
     #[account(mut)]
     pub market: Account<'info, Market>,
-    pub user: Signer<'info>,
+    pub caller: Signer<'info>,
     #[account(
-        mut, 
+        mut,
         token::mint = market.collateral_mint
     )]
-    pub user_collateral_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_winning_token_account: Account<'info, TokenAccount>,
+    pub proposer_collateral_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut, 
-        address = market.collateral_vault
+        mut,
+        token::mint = market.collateral_mint
     )]
-    pub collateral_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub winning_token_mint: Account<'info, Mint>,
+    pub disputer_collateral_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = market.bond_vault
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
     ///CHECK: PDA authority
     #[account(
         seeds = [b"authority", market.key().as_ref()],
         bump = market.bump
     )]
     pub market_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
+    #[account(address = market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct GetMarketPrice<'info> {
+pub struct VoteResolution<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
     pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct EmergencyResolveMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ MarketError::ProtocolPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
     #[account(mut)]
     pub market: Account<'info, Market>,
     pub resolver: Signer<'info>,
 }
 
+/// Program-wide settings, stored once at the `[b"config"]` PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    /// Owner of the token accounts protocol fees are claimed to.
+    pub treasury: Pubkey,
+    /// Fee for markets created without an explicit `fee_bps`.
+    pub default_fee_bps: u16,
+    #[max_len(MAX_ALLOWED_COLLATERAL_MINTS)]
+    pub allowed_collateral_mints: Vec<Pubkey>,
+    /// Bounds on `end_timestamp - now` when a market is created, in seconds.
+    pub min_market_duration: i64,
+    pub max_market_duration: i64,
+    pub paused: bool,
+    pub bump: u8,
+}
+
+impl Config {
+    fn apply(&mut self, treasury: Pubkey, default_fee_bps: u16, allowed_collateral_mints: Vec<Pubkey>, min_market_duration: i64, max_market_duration: i64) -> Result<()> {
+        require!(default_fee_bps <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(allowed_collateral_mints.len() <= MAX_ALLOWED_COLLATERAL_MINTS, MarketError::InvalidConfig);
+        require!(min_market_duration > 0 && min_market_duration <= max_market_duration, MarketError::InvalidConfig);
+
+        self.treasury = treasury;
+        self.default_fee_bps = default_fee_bps;
+        self.allowed_collateral_mints = allowed_collateral_mints;
+        self.min_market_duration = min_market_duration;
+        self.max_market_duration = max_market_duration;
+        Ok(())
+    }
+
+    fn check_new_market(&self, collateral_mint: &Pubkey, now: i64, end_timestamp: i64) -> Result<()> {
+        require!(self.allowed_collateral_mints.contains(collateral_mint), MarketError::CollateralNotAllowed);
+        let duration = end_timestamp
+            .checked_sub(now)
+            .ok_or(MarketError::MathOverflow)?;
+        require!(
+            (self.min_market_duration..=self.max_market_duration).contains(&duration),
+            MarketError::InvalidMarketDuration
+        );
+        Ok(())
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Market {
     pub creator: Pubkey,
+    /// Creator-chosen id; the market lives at `[b"market", creator, market_id]`.
+    pub market_id: u64,
     #[max_len(200)]
     pub question: String,
     pub collateral_mint: Pubkey,
@@ -562,15 +3912,158 @@ pub struct Market {
     pub yes_token_mint: Pubkey,
     pub no_token_mint: Pubkey,
     pub end_timestamp: i64,
+    /// Buys and sells are rejected from this time on; never later than `end_timestamp`.
+    pub trading_close_timestamp: i64,
     pub resolution_source: Pubkey, 
     pub resolved: bool,
     pub winning_outcome: WinningOutcome,
     pub market_type: MarketType,
+    pub market_kind: MarketKind,
     pub yes_shares_outstanding: u64,
     pub no_shares_outstanding: u64,
     pub total_liquidity: u64,
+    pub liquidity_parameter: u64,
+    pub fee_bps: u16,
+    pub creator_fee_vault: Pubkey,
+    pub protocol_fee_vault: Pubkey,
+    pub creator_fees_accrued: u64,
+    pub protocol_fees_accrued: u64,
+    pub lp_token_mint: Pubkey,
+    pub canceled_yes_refund_rate: u64,
+    pub canceled_no_refund_rate: u64,
+    /// Categorical markets only: one mint per outcome, initialized in index order.
+    #[max_len(16)]
+    pub outcome_mints: Vec<Pubkey>,
+    /// Categorical markets only: shares outstanding per outcome.
+    #[max_len(16)]
+    pub outcome_shares: Vec<u64>,
+    /// Categorical markets only: refund rate per outcome once canceled.
+    #[max_len(16)]
+    pub canceled_outcome_refund_rates: Vec<u64>,
+    /// Scalar markets only: the range the oracle price is clamped into, at `oracle_threshold_expo`.
+    pub scalar_lower_bound: i64,
+    pub scalar_upper_bound: i64,
+    /// Scalar markets only: what one LONG token pays once resolved, scaled by `REFUND_RATE_PRECISION`.
+    pub scalar_long_payout_rate: u64,
+    /// Set when the market resolves optimistically instead of by the creator.
+    pub dispute_config: Option<DisputeConfig>,
+    pub proposal: Option<ResolutionProposal>,
+    pub bond_vault: Pubkey,
+    pub resolver: ResolverConfig,
+    /// Committee markets only: the latest vote of each member that has voted.
+    #[max_len(MAX_COMMITTEE_MEMBERS)]
+    pub resolution_votes: Vec<ResolutionVote>,
+    /// Set by the creator to halt trading on this market only.
+    pub paused: bool,
     pub bump: u8,
     pub oracle_threshold: Option<i64>,
+    pub oracle_threshold_expo: i32,
+    pub oracle_comparison: OracleComparison,
+    pub oracle_max_age: u64,
+    pub oracle_max_conf_bps: u16,
+}
+
+impl Market {
+    /// Shares outstanding per outcome, in LMSR index order.
+    pub fn shares(&self) -> Vec<u64> {
+        match self.market_kind {
+            MarketKind::Binary | MarketKind::Scalar => vec![self.yes_shares_outstanding, self.no_shares_outstanding],
+            MarketKind::Categorical => self.outcome_shares.clone(),
+        }
+    }
+}
+
+/// Resting limit orders for a two-outcome market. Escrow lives in the three vaults.
+/// A fill that completes an order pays its owner and frees the slot; a partial
+/// fill credits the order, which pays out when the owner cancels it.
+#[account]
+#[derive(InitSpace)]
+pub struct OrderBook {
+    pub market: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub yes_vault: Pubkey,
+    pub no_vault: Pubkey,
+    pub next_order_id: u64,
+    pub bump: u8,
+    #[max_len(MAX_ORDERS)]
+    pub orders: Vec<Order>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Order {
+    pub id: u64,
+    pub owner: Pubkey,
+    pub outcome: ShareOutcome,
+    pub side: OrderSide,
+    /// Collateral per share, scaled by `PRICE_PRECISION`.
+    pub price: u64,
+    pub remaining_shares: u64,
+    /// Bids only: collateral still locked for the unfilled shares.
+    pub escrowed_collateral: u64,
+    /// Asks only: collateral paid in by takers.
+    pub proceeds_collateral: u64,
+    /// Bids only: outcome tokens delivered by takers.
+    pub proceeds_shares: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum OrderSide {
+    Bid,
+    Ask
+}
+
+/// Who may resolve a manual market (and emergency-resolve any market).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum ResolverConfig {
+    Single(Pubkey),
+    Committee {
+        #[max_len(MAX_COMMITTEE_MEMBERS)]
+        members: Vec<Pubkey>,
+        threshold: u8,
+    },
+}
+
+/// Result of `quote_trade`. Prices are in `PRICE_PRECISION` units.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct TradeQuote {
+    /// Collateral the AMM charges for a buy or pays for a sell, before fees.
+    pub amount: u64,
+    /// Creator and protocol fee together.
+    pub fee: u64,
+    /// What a buy's `max_cost` or a sell's `min_payout` is checked against.
+    pub total: u64,
+    /// `amount` per share, before fees.
+    pub average_price: u64,
+    pub yes_price_after: u64,
+    pub no_price_after: u64,
+    /// How far the traded outcome's price moves, relative to where it starts;
+    /// `u64::MAX` when it starts too close to zero to measure against.
+    pub price_impact_bps: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ResolutionVote {
+    pub member: Pubkey,
+    pub outcome: WinningOutcome,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct DisputeConfig {
+    pub arbiter: Pubkey,
+    /// Collateral both the proposer and a disputer have to lock.
+    pub bond_amount: u64,
+    /// Seconds after a proposal during which it can be disputed.
+    pub dispute_window: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ResolutionProposal {
+    pub proposer: Pubkey,
+    pub outcome: WinningOutcome,
+    pub proposed_at: i64,
+    pub disputer: Option<Pubkey>,
+    /// When `disputer` posted their bond; the arbiter has `ARBITRATION_PERIOD` from then.
+    pub disputed_at: i64,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
@@ -578,7 +4071,16 @@ pub enum WinningOutcome {
     Undecided,
     Yes,
     No,
-    Canceled // for situations like match canceled due to rain
+    Canceled, // for situations like match canceled due to rain
+    Outcome(u8), // winning outcome index of a categorical market
+    Scalar // LONG and SHORT split each unit at `scalar_long_payout_rate`
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MarketKind {
+    Binary,
+    Categorical,
+    Scalar // YES/NO mints trade as LONG/SHORT over a price range
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
@@ -587,12 +4089,303 @@ pub enum MarketType {
     Manual
 }
 
+/// How the oracle price must relate to `oracle_threshold` for YES to win.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum OracleComparison {
+    Above,
+    Below,
+    EqualOrAbove
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum FeeRecipient {
+    Creator,
+    Protocol
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum ShareOutcome {
     Yes,
     No
 }
 
+impl ShareOutcome {
+    pub fn index(&self) -> usize {
+        match self {
+            ShareOutcome::Yes => 0,
+            ShareOutcome::No => 1,
+        }
+    }
+}
+
+#[event]
+pub struct ProtocolPauseChanged {
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCreated {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub market_id: u64,
+    pub question: String,
+    pub market_type: MarketType,
+    pub end_timestamp: i64,
+    pub collateral_mint: Pubkey,
+    pub initial_liquidity: u64,
+    pub liquidity_parameter: u64,
+    pub fee_bps: u16,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SharesBought {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome: ShareOutcome,
+    pub shares: u64,
+    /// Part of `shares` filled against resting orders.
+    pub book_shares: u64,
+    pub cost: u64,
+    pub fee: u64,
+    pub yes_price: u64,
+    pub no_price: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SharesSold {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome: ShareOutcome,
+    pub shares: u64,
+    /// Part of `shares` filled against resting orders.
+    pub book_shares: u64,
+    pub payout: u64,
+    pub fee: u64,
+    pub yes_price: u64,
+    pub no_price: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub outcome: ShareOutcome,
+    pub side: OrderSide,
+    pub price: u64,
+    pub shares: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderCanceled {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub collateral_out: u64,
+    pub shares_out: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderFilled {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub collateral_out: u64,
+    pub shares_out: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OutcomeSharesBought {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome_index: u8,
+    pub shares: u64,
+    pub cost: u64,
+    pub fee: u64,
+    pub prices: Vec<u64>,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OutcomeSharesSold {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome_index: u8,
+    pub shares: u64,
+    pub payout: u64,
+    pub fee: u64,
+    pub prices: Vec<u64>,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompleteSetMinted {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompleteSetMerged {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub collateral_in: u64,
+    pub lp_tokens: u64,
+    pub yes_tokens_out: u64,
+    pub no_tokens_out: u64,
+    pub liquidity_parameter: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub lp_tokens: u64,
+    pub collateral_out: u64,
+    pub yes_tokens_out: u64,
+    pub no_tokens_out: u64,
+    pub liquidity_parameter: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketResolved {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub outcome: WinningOutcome,
+    /// Pyth price (and its exponent) the outcome was decided from, for oracle markets.
+    pub oracle_price: Option<i64>,
+    pub oracle_expo: Option<i32>,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResolutionProposed {
+    pub market: Pubkey,
+    pub proposer: Pubkey,
+    pub outcome: WinningOutcome,
+    pub bond: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResolutionDisputed {
+    pub market: Pubkey,
+    pub disputer: Pubkey,
+    pub bond: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResolutionVoted {
+    pub market: Pubkey,
+    pub member: Pubkey,
+    pub outcome: WinningOutcome,
+    /// Members currently agreeing on `outcome`, including this vote.
+    pub votes: u8,
+    pub threshold: u8,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketPauseChanged {
+    pub market: Pubkey,
+    pub paused: bool,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCanceledByCreator {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyResolved {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub outcome: WinningOutcome,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WinningsRedeemed {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome: WinningOutcome,
+    pub tokens_burned: u64,
+    pub payout: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RemainingLiquidityWithdrawn {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub lp_tokens: u64,
+    pub collateral_out: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesClaimed {
+    pub market: Pubkey,
+    pub recipient: FeeRecipient,
+    pub claimer: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
 #[error_code]
 pub enum MarketError {
     #[msg("Market has already been resolved.")]
@@ -633,4 +4426,88 @@ pub enum MarketError {
     OracleThresholdRequired,  
     #[msg("Invalid Token Account.")]
     InvalidTokenAccount,  
+    #[msg("Invalid oracle configuration.")]
+    InvalidOracleConfig,
+    #[msg("Oracle confidence interval is too wide.")]
+    OracleConfidenceTooWide,
+    #[msg("Liquidity parameter must be greater than zero.")]
+    InvalidLiquidityParameter,
+    #[msg("Initial liquidity does not cover the market maker's worst-case loss.")]
+    InsufficientLiquidity,
+    #[msg("Fee exceeds the maximum allowed.")]
+    FeeTooHigh,
+    #[msg("Signer is not allowed to claim these fees.")]
+    UnauthorizedFeeClaim,
+    #[msg("The last liquidity can only be withdrawn after resolution.")]
+    LiquidityLocked,
+    #[msg("Trading is closed for this market.")]
+    TradingClosed,
+    #[msg("Market was canceled; redeem tokens for refunds instead.")]
+    MarketCanceled,
+    #[msg("Market end time must be in the future.")]
+    InvalidEndTimestamp,
+    #[msg("Trading close time must be in the future and no later than the market end time.")]
+    InvalidTradingCloseTimestamp,
+    #[msg("Instruction is not supported for this kind of market.")]
+    UnsupportedMarketKind,
+    #[msg("Categorical markets need between 3 and 16 outcomes.")]
+    InvalidOutcomeCount,
+    #[msg("Outcome mints must be initialized in order.")]
+    InvalidOutcomeIndex,
+    #[msg("Not every outcome mint has been initialized yet.")]
+    OutcomeMintsPending,
+    #[msg("Scalar lower bound must be below the upper bound.")]
+    InvalidScalarBounds,
+    #[msg("Order price must be within the allowed price range.")]
+    InvalidOrderPrice,
+    #[msg("Order book is full.")]
+    OrderBookFull,
+    #[msg("Order not found.")]
+    OrderNotFound,
+    #[msg("Only the order's owner can cancel it.")]
+    UnauthorizedOrderOwner,
+    #[msg("Market resolves optimistically; propose an outcome instead.")]
+    OptimisticResolutionRequired,
+    #[msg("Invalid dispute configuration.")]
+    InvalidDisputeConfig,
+    #[msg("Dispute settings are already configured.")]
+    DisputeAlreadyConfigured,
+    #[msg("Market is not configured for optimistic resolution.")]
+    DisputeNotConfigured,
+    #[msg("A resolution proposal is already pending.")]
+    ResolutionPending,
+    #[msg("No resolution has been proposed.")]
+    NoResolutionProposed,
+    #[msg("Proposal has already been disputed.")]
+    AlreadyDisputed,
+    #[msg("Proposal has not been disputed.")]
+    NotDisputed,
+    #[msg("Dispute window has closed.")]
+    DisputeWindowClosed,
+    #[msg("Dispute window is still open.")]
+    DisputeWindowOpen,
+    #[msg("Invalid resolver configuration.")]
+    InvalidResolverConfig,
+    #[msg("Market is resolved by committee vote.")]
+    CommitteeResolutionRequired,
+    #[msg("Market is not resolved by a committee.")]
+    NotACommitteeMarket,
+    #[msg("Protocol is paused.")]
+    ProtocolPaused,
+    #[msg("Signer is not the protocol admin.")]
+    UnauthorizedAdmin,
+    #[msg("Invalid protocol configuration.")]
+    InvalidConfig,
+    #[msg("Collateral mint is not allowed.")]
+    CollateralNotAllowed,
+    #[msg("Market duration is outside the allowed range.")]
+    InvalidMarketDuration,
+    #[msg("Trading on this market is paused.")]
+    MarketPaused,
+    #[msg("The arbiter still has time to settle the dispute.")]
+    ArbitrationPeriodNotReached,
+    #[msg("Could not size the trade within the search limit.")]
+    SearchLimitExceeded,
+    #[msg("The order is worth less than the minimum order value.")]
+    OrderTooSmall,
 }
\ No newline at end of file
@@ -0,0 +1,392 @@
+//! Fixed-point LMSR pricing and the integer helpers the instructions build on.
+//!
+//! Everything here works on plain share vectors and the liquidity parameter `b`,
+//! so it runs unchanged off-chain. Intermediates are `u128` and every operation is
+//! checked: bad inputs surface as `MathOverflow`/`MathUnderflow`, never a panic.
+
+use anchor_lang::prelude::*;
+
+use crate::{MarketError, BPS_DENOMINATOR, FP_LN2, FP_ONE, MAX_PRICE, MAX_SEARCH_EVALUATIONS, MIN_PRICE, PRICE_PRECISION, PROTOCOL_FEE_SHARE_BPS, REFUND_RATE_PRECISION};
+
+const FP_SERIES_TERMS: u128 = 30;
+
+/// Quoted price of outcome `index` in `PRICE_PRECISION` units, clamped to
+/// `[MIN_PRICE, MAX_PRICE]`.
+pub fn price(shares: &[u64], b: u64, index: usize) -> Result<u64> {
+    quoted_price(lmsr_price(shares, b, index)?)
+}
+
+/// `price` of every outcome, in index order.
+pub fn prices(shares: &[u64], b: u64) -> Result<Vec<u64>> {
+    lmsr_prices(shares, b)?.into_iter().map(quoted_price).collect()
+}
+
+fn quoted_price(lmsr_price: u128) -> Result<u64> {
+    let price = lmsr_price
+        .checked_mul(PRICE_PRECISION as u128)
+        .ok_or(MarketError::MathOverflow)?
+        / FP_ONE;
+    let price = u64::try_from(price).map_err(|_| error!(MarketError::MathOverflow))?;
+    Ok(price.clamp(MIN_PRICE, MAX_PRICE))
+}
+
+/// Collateral the AMM charges for `amount` more shares of outcome `index`.
+pub fn buy_cost(shares: &[u64], b: u64, index: usize, amount: u64) -> Result<u64> {
+    buy_cost_from(shares, b, lmsr_cost(shares, b)?, index, amount)
+}
+
+/// `buy_cost` given `cost_before = lmsr_cost(shares, b)`, for pricing many sizes
+/// of the same trade.
+pub fn buy_cost_from(shares: &[u64], b: u64, cost_before: u128, index: usize, amount: u64) -> Result<u64> {
+    let mut after = shares.to_vec();
+    let outcome_shares = after.get_mut(index).ok_or(MarketError::InvalidOutcome)?;
+    *outcome_shares = outcome_shares.checked_add(amount).ok_or(MarketError::MathOverflow)?;
+
+    // Round against the trader so the vault never pays for fixed-point error.
+    let cost = lmsr_cost(&after, b)?
+        .checked_sub(cost_before)
+        .ok_or(MarketError::MathUnderflow)?
+        .div_ceil(FP_ONE);
+    u64::try_from(cost).map_err(|_| error!(MarketError::MathOverflow))
+}
+
+/// Collateral the AMM pays for taking back `amount` shares of outcome `index`.
+pub fn sell_payout(shares: &[u64], b: u64, index: usize, amount: u64) -> Result<u64> {
+    sell_payout_from(shares, b, lmsr_cost(shares, b)?, index, amount)
+}
+
+/// `sell_payout` given `cost_before = lmsr_cost(shares, b)`.
+pub fn sell_payout_from(shares: &[u64], b: u64, cost_before: u128, index: usize, amount: u64) -> Result<u64> {
+    let mut after = shares.to_vec();
+    let outcome_shares = after.get_mut(index).ok_or(MarketError::InvalidOutcome)?;
+    *outcome_shares = outcome_shares.checked_sub(amount).ok_or(MarketError::MathUnderflow)?;
+
+    let payout = cost_before
+        .checked_sub(lmsr_cost(&after, b)?)
+        .ok_or(MarketError::MathUnderflow)?
+        / FP_ONE;
+    u64::try_from(payout).map_err(|_| error!(MarketError::MathOverflow))
+}
+
+/// Shares of outcome `index` that `cost` collateral buys from the AMM, to within
+/// fixed-point error. Inverts the cost function: buying `s` for `c` means
+/// `exp((q_i + s - C - c) / b) = 1 - sum_{j != i} exp((q_j - C - c) / b)`.
+pub fn shares_for_cost(shares: &[u64], b: u64, index: usize, cost: u64) -> Result<u64> {
+    let outcome_shares = *shares.get(index).ok_or(MarketError::InvalidOutcome)?;
+    let target = lmsr_cost(shares, b)?
+        .checked_add((cost as u128).checked_mul(FP_ONE).ok_or(MarketError::MathOverflow)?)
+        .ok_or(MarketError::MathOverflow)?;
+    let Some(log_term) = remaining_log_term(shares, b, index, target)? else {
+        return Ok(0);
+    };
+
+    let estimate = target
+        .checked_sub(log_term)
+        .and_then(|rest| rest.checked_sub((outcome_shares as u128).checked_mul(FP_ONE)?))
+        .map_or(0, |rest| rest / FP_ONE);
+    Ok(u64::try_from(estimate).unwrap_or(u64::MAX))
+}
+
+/// Shares of outcome `index` the AMM has to take back to pay out `payout`, to
+/// within fixed-point error, or all of them if it can never pay that much.
+pub fn shares_for_payout(shares: &[u64], b: u64, index: usize, payout: u64) -> Result<u64> {
+    let outcome_shares = *shares.get(index).ok_or(MarketError::InvalidOutcome)?;
+    let Some(target) = lmsr_cost(shares, b)?.checked_sub((payout as u128).checked_mul(FP_ONE).ok_or(MarketError::MathOverflow)?) else {
+        return Ok(outcome_shares);
+    };
+    let Some(log_term) = remaining_log_term(shares, b, index, target)? else {
+        return Ok(outcome_shares);
+    };
+
+    let estimate = (outcome_shares as u128)
+        .checked_mul(FP_ONE)
+        .and_then(|base| base.checked_add(log_term))
+        .ok_or(MarketError::MathOverflow)?
+        .saturating_sub(target)
+        .div_ceil(FP_ONE);
+    Ok(u64::try_from(estimate).map_or(outcome_shares, |estimate| estimate.min(outcome_shares)))
+}
+
+/// `-b * ln(1 - sum_{j != index} exp((q_j - target) / b))` scaled by `FP_ONE`, or
+/// `None` when no trade of outcome `index` alone can bring the cost to `target`.
+fn remaining_log_term(shares: &[u64], b: u64, index: usize, target: u128) -> Result<Option<u128>> {
+    require!(b > 0, MarketError::NoLiquidity);
+    let mut others = 0u128;
+    for (_, &q) in shares.iter().enumerate().filter(|(j, _)| *j != index) {
+        let Some(distance) = target.checked_sub((q as u128).checked_mul(FP_ONE).ok_or(MarketError::MathOverflow)?) else {
+            return Ok(None);
+        };
+        others = others
+            .checked_add(fp_exp_neg(distance / b as u128)?)
+            .ok_or(MarketError::MathOverflow)?;
+    }
+    let Some(remaining) = FP_ONE.checked_sub(others).filter(|remaining| *remaining > 0) else {
+        return Ok(None);
+    };
+
+    let inverse = FP_ONE
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?
+        / remaining;
+    let log_term = (b as u128)
+        .checked_mul(fp_ln(inverse)?)
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(Some(log_term))
+}
+
+/// Smallest `x` in `[lo, hi]` for which `reached(x)` holds, or `None` if even `hi`
+/// falls short. `reached` must be monotone. The search gallops out from `guess`
+/// before bisecting, so a close guess settles in a handful of evaluations; one
+/// thousands away fails with `SearchLimitExceeded` after `MAX_SEARCH_EVALUATIONS`.
+pub fn first_reaching(lo: u64, hi: u64, guess: u64, mut predicate: impl FnMut(u64) -> Result<bool>) -> Result<Option<u64>> {
+    if lo > hi {
+        return Ok(None);
+    }
+    let mut evaluations = 0;
+    let mut reached = |x: u64| -> Result<bool> {
+        evaluations += 1;
+        require!(evaluations <= MAX_SEARCH_EVALUATIONS, MarketError::SearchLimitExceeded);
+        predicate(x)
+    };
+    let guess = guess.clamp(lo, hi);
+    let mut step = 1u64;
+
+    // Bracket the answer between a point that falls short and one that reaches.
+    let (mut short, mut reaching) = if reached(guess)? {
+        let mut reaching = guess;
+        loop {
+            if reaching == lo {
+                return Ok(Some(lo));
+            }
+            let probe = reaching.saturating_sub(step).max(lo);
+            if !reached(probe)? {
+                break (probe, reaching);
+            }
+            reaching = probe;
+            step = step.saturating_mul(2);
+        }
+    } else {
+        let mut short = guess;
+        loop {
+            if short == hi {
+                return Ok(None);
+            }
+            let probe = short.saturating_add(step).min(hi);
+            if reached(probe)? {
+                break (short, probe);
+            }
+            short = probe;
+            step = step.saturating_mul(2);
+        }
+    };
+
+    while reaching - short > 1 {
+        let mid = short + (reaching - short) / 2;
+        if reached(mid)? {
+            reaching = mid;
+        } else {
+            short = mid;
+        }
+    }
+    Ok(Some(reaching))
+}
+
+/// Per-token refund of every outcome at the current AMM prices, in
+/// `REFUND_RATE_PRECISION` units and rounded down.
+pub fn refund_rates(shares: &[u64], b: u64) -> Result<Vec<u64>> {
+    lmsr_prices(shares, b)?
+        .into_iter()
+        .map(|price| {
+            let rate = price
+                .checked_mul(REFUND_RATE_PRECISION as u128)
+                .ok_or(MarketError::MathOverflow)?
+                / FP_ONE;
+            u64::try_from(rate).map_err(|_| error!(MarketError::MathOverflow))
+        })
+        .collect()
+}
+
+/// Splits the trading fee on `amount` into `(creator_fee, protocol_fee)`.
+/// The total fee is rounded up so that tiny trades cannot dodge it.
+pub fn calculate_fees(amount: u64, fee_bps: u16) -> Result<(u64, u64)> {
+    let total_fee = (amount as u128)
+        .checked_mul(fee_bps as u128)
+        .ok_or(MarketError::MathOverflow)?
+        .div_ceil(BPS_DENOMINATOR as u128);
+    let protocol_fee = total_fee
+        .checked_mul(PROTOCOL_FEE_SHARE_BPS as u128)
+        .ok_or(MarketError::MathOverflow)?
+        / BPS_DENOMINATOR as u128;
+    let creator_fee = total_fee
+        .checked_sub(protocol_fee)
+        .ok_or(MarketError::MathUnderflow)?;
+
+    let creator_fee = u64::try_from(creator_fee).map_err(|_| error!(MarketError::MathOverflow))?;
+    let protocol_fee = u64::try_from(protocol_fee).map_err(|_| error!(MarketError::MathOverflow))?;
+    Ok((creator_fee, protocol_fee))
+}
+
+pub fn mul_div_floor(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    require!(denominator > 0, MarketError::NoLiquidity);
+    let result = (value as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(MarketError::MathOverflow)?
+        / denominator as u128;
+    u64::try_from(result).map_err(|_| error!(MarketError::MathOverflow))
+}
+
+pub fn mul_div_ceil(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    require!(denominator > 0, MarketError::NoLiquidity);
+    let result = (value as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(MarketError::MathOverflow)?
+        .div_ceil(denominator as u128);
+    u64::try_from(result).map_err(|_| error!(MarketError::MathOverflow))
+}
+
+/// LMSR cost function `C(q) = b * ln(sum(exp(q_i / b)))`, returned scaled by `FP_ONE`.
+///
+/// Evaluated as `max(q) + b * ln(sum(exp((q_i - max(q)) / b)))` so every exponent
+/// is non-positive and the sum stays within `[1, n]`.
+pub fn lmsr_cost(shares: &[u64], b: u64) -> Result<u128> {
+    require!(b > 0, MarketError::NoLiquidity);
+    let max_shares = shares.iter().copied().max().ok_or(MarketError::NoLiquidity)?;
+
+    let exp_sum = lmsr_exp_sum(shares, max_shares, b)?;
+    let log_term = (b as u128)
+        .checked_mul(fp_ln(exp_sum)?)
+        .ok_or(MarketError::MathOverflow)?;
+
+    let cost = (max_shares as u128)
+        .checked_mul(FP_ONE)
+        .and_then(|base| base.checked_add(log_term))
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(cost)
+}
+
+/// Instantaneous LMSR price of outcome `index`, scaled by `FP_ONE`.
+pub fn lmsr_price(shares: &[u64], b: u64, index: usize) -> Result<u128> {
+    require!(b > 0, MarketError::NoLiquidity);
+    let max_shares = shares.iter().copied().max().ok_or(MarketError::NoLiquidity)?;
+    let outcome_shares = *shares.get(index).ok_or(MarketError::InvalidOutcome)?;
+    let distance = max_shares
+        .checked_sub(outcome_shares)
+        .ok_or(MarketError::MathUnderflow)?;
+
+    let numerator = fp_exp_neg(lmsr_exponent(distance, b)?)?;
+    let price = numerator
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?
+        .checked_div(lmsr_exp_sum(shares, max_shares, b)?)
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(price)
+}
+
+/// `lmsr_price` of every outcome, in index order. The exponentials are shared
+/// across outcomes, so this costs one pass over the shares rather than one each.
+pub fn lmsr_prices(shares: &[u64], b: u64) -> Result<Vec<u128>> {
+    require!(b > 0, MarketError::NoLiquidity);
+    let max_shares = shares.iter().copied().max().ok_or(MarketError::NoLiquidity)?;
+    let terms = shares
+        .iter()
+        .map(|&q| {
+            let distance = max_shares.checked_sub(q).ok_or(MarketError::MathUnderflow)?;
+            fp_exp_neg(lmsr_exponent(distance, b)?)
+        })
+        .collect::<Result<Vec<u128>>>()?;
+    let sum = terms.iter().try_fold(0u128, |sum, &term| sum.checked_add(term).ok_or(error!(MarketError::MathOverflow)))?;
+
+    terms
+        .into_iter()
+        .map(|term| {
+            term.checked_mul(FP_ONE)
+                .ok_or(MarketError::MathOverflow)?
+                .checked_div(sum)
+                .ok_or(error!(MarketError::MathOverflow))
+        })
+        .collect()
+}
+
+fn lmsr_exp_sum(shares: &[u64], max_shares: u64, b: u64) -> Result<u128> {
+    shares.iter().try_fold(0u128, |sum, &q| {
+        let distance = max_shares.checked_sub(q).ok_or(MarketError::MathUnderflow)?;
+        let term = fp_exp_neg(lmsr_exponent(distance, b)?)?;
+        sum.checked_add(term).ok_or(error!(MarketError::MathOverflow))
+    })
+}
+
+fn lmsr_exponent(distance: u64, b: u64) -> Result<u128> {
+    require!(b > 0, MarketError::NoLiquidity);
+    let scaled = (distance as u128)
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?
+        / b as u128;
+    Ok(scaled)
+}
+
+/// `exp(-x)` for a non-negative fixed-point `x`, via `exp(-x) = exp(-r) / 2^k`
+/// with `x = k * ln2 + r` and a Taylor series for `exp(r)` on `[0, ln2)`.
+fn fp_exp_neg(x: u128) -> Result<u128> {
+    // exp(-42) is already below 1e-18, i.e. zero at this precision.
+    if x >= 42 * FP_ONE {
+        return Ok(0);
+    }
+
+    let halvings = x / FP_LN2;
+    let remainder = x % FP_LN2;
+
+    let mut term = FP_ONE;
+    let mut exp_r = FP_ONE;
+    for i in 1..=FP_SERIES_TERMS {
+        term = term
+            .checked_mul(remainder)
+            .ok_or(MarketError::MathOverflow)?
+            / FP_ONE
+            / i;
+        if term == 0 {
+            break;
+        }
+        exp_r = exp_r.checked_add(term).ok_or(MarketError::MathOverflow)?;
+    }
+
+    let exp_neg_r = FP_ONE
+        .checked_mul(FP_ONE)
+        .ok_or(MarketError::MathOverflow)?
+        / exp_r;
+    let halvings = u32::try_from(halvings).map_err(|_| error!(MarketError::MathOverflow))?;
+    exp_neg_r.checked_shr(halvings).ok_or(error!(MarketError::MathOverflow))
+}
+
+/// Natural log of a fixed-point `x >= 1`, via `ln(x) = k * ln2 + ln(m)` with
+/// `m = x / 2^k` in `[1, 2)` and `ln(m) = 2 * atanh((m - 1) / (m + 1))`.
+fn fp_ln(x: u128) -> Result<u128> {
+    require!(x >= FP_ONE, MarketError::MathUnderflow);
+
+    let doublings = (x / FP_ONE).ilog2();
+    let mantissa = x >> doublings;
+
+    let t = mantissa
+        .checked_sub(FP_ONE)
+        .and_then(|numerator| numerator.checked_mul(FP_ONE))
+        .ok_or(MarketError::MathOverflow)?
+        / mantissa.checked_add(FP_ONE).ok_or(MarketError::MathOverflow)?;
+    let t_squared = t.checked_mul(t).ok_or(MarketError::MathOverflow)? / FP_ONE;
+
+    let mut power = t;
+    let mut series = t;
+    for n in 1..=FP_SERIES_TERMS {
+        power = power.checked_mul(t_squared).ok_or(MarketError::MathOverflow)? / FP_ONE;
+        if power == 0 {
+            break;
+        }
+        series = series
+            .checked_add(power / (2 * n + 1))
+            .ok_or(MarketError::MathOverflow)?;
+    }
+
+    let ln = (doublings as u128)
+        .checked_mul(FP_LN2)
+        .and_then(|whole| whole.checked_add(series.checked_mul(2)?))
+        .ok_or(MarketError::MathOverflow)?;
+    Ok(ln)
+}
@@ -0,0 +1,85 @@
+//! The heaviest trades against the default 200k compute-unit budget. Natively
+//! only the token CPIs are metered; `cargo test-sbf` runs the compiled program,
+//! so the fixed-point pricing math is counted too.
+
+use anchor_spl::token_interface::spl_token_2022;
+use prediction_market::{instruction, ShareOutcome, MAX_CATEGORICAL_OUTCOMES};
+use prediction_market_test_utils::*;
+
+const DEFAULT_COMPUTE_UNITS: u64 = 200_000;
+
+fn assert_fits(label: &str, units: u64) {
+    println!("{label}: {units} CU{}", if runs_sbf() { "" } else { " (CPIs only)" });
+    assert!(units <= DEFAULT_COMPUTE_UNITS, "{label} used {units} CU");
+}
+
+#[tokio::test]
+async fn binary_trades_fit_the_default_budget() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    let units = env.compute_units(keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 500_000), &[&trader.keypair]).await;
+    assert_fits("buy_shares", units);
+    let units = env.compute_units(keys.sell_ix(&trader, ShareOutcome::Yes, 250_000, 0), &[&trader.keypair]).await;
+    assert_fits("sell_shares", units);
+}
+
+#[tokio::test]
+async fn exact_collateral_trades_fit_the_default_budget() {
+    let mut env = TestEnv::new().await;
+    // 1% transfer fee capped at 1_000, so both fee regimes show up
+    let mint = env.create_transfer_fee_mint(100, 1_000).await;
+    let creator = env.new_user_with(&mint, &spl_token_2022::ID, 100_000_000).await;
+    let trader = env.new_user_with(&mint, &spl_token_2022::ID, 100_000_000).await;
+    let now = env.now().await;
+    let keys = MarketKeys { token_program: spl_token_2022::ID, ..MarketKeys::new(&creator.pubkey(), 0, mint) };
+    env.send(vec![keys.create_ix(&creator, manual_market_args(0, now, DAY, creator.pubkey()))], &[&creator.keypair]).await.unwrap();
+    env.open_positions(&keys, &trader).await;
+
+    // Sizing the trade is a search over the curve, so cover a balanced market
+    // and one pushed far out, where a share of the cheap side costs next to nothing.
+    for (label, skew) in [("balanced", 0), ("skewed", 20_000_000)] {
+        if skew > 0 {
+            env.send(vec![keys.buy_ix(&trader, ShareOutcome::No, u64::MAX, skew)], &[&trader.keypair]).await.unwrap();
+        }
+        let units = env.compute_units(keys.buy_with_collateral_ix(&trader, ShareOutcome::Yes, 50_000, 1), &[&trader.keypair]).await;
+        assert_fits(&format!("buy_with_collateral ({label})"), units);
+        let units = env.compute_units(keys.sell_for_collateral_ix(&trader, ShareOutcome::Yes, 10_000, u64::MAX), &[&trader.keypair]).await;
+        assert_fits(&format!("sell_for_collateral ({label})"), units);
+    }
+}
+
+#[tokio::test]
+async fn largest_categorical_trades_fit_the_default_budget() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(100_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateCategoricalMarket {
+        market_id: 0,
+        question: "Who wins the league?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: creator.pubkey(),
+        resolver: None,
+        outcome_count: MAX_CATEGORICAL_OUTCOMES,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+    };
+    env.send(vec![keys.create_ix(&creator, args)], &[&creator.keypair]).await.unwrap();
+    for index in 0..MAX_CATEGORICAL_OUTCOMES {
+        env.send(vec![keys.init_outcome_mint_ix(&creator.pubkey(), index)], &[&creator.keypair]).await.unwrap();
+    }
+
+    let last = MAX_CATEGORICAL_OUTCOMES - 1;
+    env.create_atas(&trader.pubkey(), &[outcome_mint_pda(&keys.market, last)]).await;
+    let units = env.compute_units(keys.buy_ix(&trader, last, u64::MAX, 500_000), &[&trader.keypair]).await;
+    assert_fits("buy_outcome_shares", units);
+    let units = env.compute_units(keys.sell_ix(&trader, last, 250_000, 0), &[&trader.keypair]).await;
+    assert_fits("sell_outcome_shares", units);
+}
@@ -0,0 +1,661 @@
+//! One test per reachable `MarketError`. `DisputeAlreadyConfigured`,
+//! `DisputeNotConfigured` and `MathUnderflow` are shadowed by account
+//! constraints (the bond vault is `init`ed once and pinned to the market) or by
+//! share bookkeeping that always covers the tokens in circulation.
+
+use anchor_lang::InstructionData;
+use prediction_market::{instruction, FeeRecipient, MarketError, OrderSide, ResolverConfig, ShareOutcome, WinningOutcome};
+use prediction_market_test_utils::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+
+/// A fresh manual market ending in a day, plus a funded trader holding share accounts.
+async fn setup() -> (TestEnv, User, User, MarketKeys) {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(100_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+    (env, creator, trader, keys)
+}
+
+async fn resolve_yes(env: &mut TestEnv, creator: &User, keys: &MarketKeys) {
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair]).await.unwrap();
+}
+
+async fn create_with(env: &mut TestEnv, creator: &User, args: instruction::CreateMarket) -> TxResult {
+    env.create_market(creator, args).await.map(|_| ())
+}
+
+async fn oracle_setup(env: &mut TestEnv, creator: &User, feed: Pubkey) -> MarketKeys {
+    let now = env.now().await;
+    env.create_market(creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap()
+}
+
+async fn optimistic_setup(env: &mut TestEnv, creator: &User, keys: &MarketKeys, arbiter: Pubkey) {
+    env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), arbiter, 100_000, 3_600)], &[&creator.keypair]).await.unwrap();
+}
+
+fn update_config_ix(admin: Pubkey, data: impl InstructionData) -> solana_sdk::instruction::Instruction {
+    solana_sdk::instruction::Instruction {
+        program_id: prediction_market::ID,
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&prediction_market::accounts::UpdateConfig { config: config_pda(), admin }, None),
+        data: data.data(),
+    }
+}
+
+#[tokio::test]
+async fn market_resolved() {
+    let (mut env, creator, trader, keys) = setup().await;
+    resolve_yes(&mut env, &creator, &keys).await;
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketResolved);
+    let result = env.send(vec![keys.mint_set_ix(&trader, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketResolved);
+}
+
+#[tokio::test]
+async fn market_not_resolved() {
+    let (mut env, _creator, trader, keys) = setup().await;
+    env.send(vec![keys.mint_set_ix(&trader, 1_000)], &[&trader.keypair]).await.unwrap();
+    let result = env.send(vec![keys.redeem_ix(&trader, keys.yes_mint, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketNotResolved);
+}
+
+#[tokio::test]
+async fn market_not_end() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::MarketNotEnd);
+}
+
+#[tokio::test]
+async fn market_already_resolved() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    resolve_yes(&mut env, &creator, &keys).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::No))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::MarketAlreadyResolved);
+}
+
+#[tokio::test]
+async fn invalid_oracle_feed() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let keys = oracle_setup(&mut env, &creator, feed).await;
+    env.warp(DAY + 10).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidOracleFeed);
+}
+
+#[tokio::test]
+async fn oracle_price_stale() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let keys = oracle_setup(&mut env, &creator, feed).await;
+    env.warp(DAY + 1_000).await;
+    let now = env.now().await;
+    env.set_pyth_price(&feed, 101, 1, 0, now - 900);
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::OraclePriceStale);
+}
+
+#[tokio::test]
+async fn oracle_not_available() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let keys = oracle_setup(&mut env, &creator, feed).await;
+    env.warp(DAY + 10).await;
+    let end = env.market(&keys.market).await.end_timestamp;
+    env.set_pyth_price(&feed, 101, 1, 0, end);
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::OracleNotAvailable);
+}
+
+#[tokio::test]
+async fn oracle_confidence_too_wide() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let keys = oracle_setup(&mut env, &creator, feed).await;
+    env.warp(DAY + 10).await;
+    let now = env.now().await;
+    env.set_pyth_price(&feed, 101, 10, 0, now - 5);
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::OracleConfidenceTooWide);
+}
+
+#[tokio::test]
+async fn emergency_period_not_reached() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.emergency_ix(&creator.pubkey(), WinningOutcome::Yes)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::EmergencyPeriodNotReached);
+}
+
+#[tokio::test]
+async fn zero_amount() {
+    let (mut env, _creator, trader, keys) = setup().await;
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 0)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::ZeroAmount);
+}
+
+#[tokio::test]
+async fn invalid_token_mint() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.send(vec![keys.mint_set_ix(&trader, 1_000)], &[&trader.keypair]).await.unwrap();
+    resolve_yes(&mut env, &creator, &keys).await;
+    let result = env.send(vec![keys.redeem_ix(&trader, keys.no_mint, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::InvalidTokenMint);
+}
+
+#[tokio::test]
+async fn invalid_outcome() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Outcome(3)))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidOutcome);
+}
+
+#[tokio::test]
+async fn slippage_exceeded() {
+    let (mut env, _creator, trader, keys) = setup().await;
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, 1, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::SlippageExceeded);
+}
+
+#[tokio::test]
+async fn math_overflow() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        initial_liquidity: u64::MAX,
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::MathOverflow);
+}
+
+#[tokio::test]
+async fn no_liquidity() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000_000)], &[&trader.keypair]).await.unwrap();
+    resolve_yes(&mut env, &creator, &keys).await;
+    // The excess over the winners' claims is below the LP supply, so one LP token is worth nothing.
+    let result = env.send(vec![keys.withdraw_remaining_ix(&creator, 1)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::NoLiquidity);
+}
+
+#[tokio::test]
+async fn unauthorized_resolver() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.resolve_ix(&trader.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::UnauthorizedResolver);
+}
+
+#[tokio::test]
+async fn outcome_required() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), None)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::OutcomeRequired);
+}
+
+#[tokio::test]
+async fn oracle_threshold_required() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        oracle_threashold: None,
+        ..oracle_market_args(0, now, DAY, Pubkey::new_unique(), 100)
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::OracleThresholdRequired);
+}
+
+#[tokio::test]
+async fn invalid_token_account() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await.unwrap();
+    // Protocol fees may only go to an account owned by the treasury.
+    let admin = env.admin.insecure_clone();
+    let result = env.send(vec![keys.claim_fees_ix(&admin.pubkey(), FeeRecipient::Protocol, creator.collateral)], &[]).await;
+    assert_market_error(result, MarketError::InvalidTokenAccount);
+}
+
+#[tokio::test]
+async fn invalid_oracle_config() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        oracle_max_age: 0,
+        ..oracle_market_args(0, now, DAY, Pubkey::new_unique(), 100)
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidOracleConfig);
+}
+
+#[tokio::test]
+async fn invalid_liquidity_parameter() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        liquidity_parameter: 0,
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidLiquidityParameter);
+}
+
+#[tokio::test]
+async fn insufficient_liquidity() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        liquidity_parameter: 10 * LIQUIDITY_PARAMETER,
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InsufficientLiquidity);
+}
+
+#[tokio::test]
+async fn fee_too_high() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        fee_bps: Some(prediction_market::MAX_FEE_BPS + 1),
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::FeeTooHigh);
+}
+
+#[tokio::test]
+async fn unauthorized_fee_claim() {
+    let (mut env, _creator, trader, keys) = setup().await;
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await.unwrap();
+    let result = env.send(vec![keys.claim_fees_ix(&trader.pubkey(), FeeRecipient::Creator, trader.collateral)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::UnauthorizedFeeClaim);
+}
+
+#[tokio::test]
+async fn liquidity_locked() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    env.open_positions(&keys, &creator).await;
+    let result = env.send(vec![keys.remove_liquidity_ix(&creator, 2 * INITIAL_LIQUIDITY, 0)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::LiquidityLocked);
+}
+
+#[tokio::test]
+async fn trading_closed() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.open_positions(&keys, &creator).await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::TradingClosed);
+    let result = env.send(vec![keys.add_liquidity_ix(&trader, 1_000_000, 0)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::TradingClosed);
+    let result = env.send(vec![keys.remove_liquidity_ix(&creator, 1_000, 0)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::TradingClosed);
+    let result = env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::CancelMarket {})], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::TradingClosed);
+}
+
+#[tokio::test]
+async fn market_canceled() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.send(vec![keys.mint_set_ix(&trader, 1_000)], &[&trader.keypair]).await.unwrap();
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::CancelMarket {})], &[&creator.keypair]).await.unwrap();
+    let result = env.send(vec![keys.merge_set_ix(&trader, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketCanceled);
+}
+
+#[tokio::test]
+async fn invalid_end_timestamp() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = manual_market_args(0, now, -1, creator.pubkey());
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidEndTimestamp);
+}
+
+#[tokio::test]
+async fn invalid_trading_close_timestamp() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        trading_close_timestamp: Some(now + 2 * DAY),
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidTradingCloseTimestamp);
+}
+
+#[tokio::test]
+async fn unsupported_market_kind() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let keys = oracle_setup(&mut env, &creator, Pubkey::new_unique()).await;
+    let result = env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), creator.pubkey(), 100_000, 3_600)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::UnsupportedMarketKind);
+}
+
+fn categorical_args(market_id: u64, now: i64, creator: Pubkey, outcome_count: u8) -> instruction::CreateCategoricalMarket {
+    instruction::CreateCategoricalMarket {
+        market_id,
+        question: "Who wins the league?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: creator,
+        resolver: None,
+        outcome_count,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+    }
+}
+
+#[tokio::test]
+async fn invalid_outcome_count() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let result = env.send(vec![keys.create_ix(&creator, categorical_args(0, now, creator.pubkey(), 2))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidOutcomeCount);
+}
+
+#[tokio::test]
+async fn invalid_outcome_index() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    env.send(vec![keys.create_ix(&creator, categorical_args(0, now, creator.pubkey(), 3))], &[&creator.keypair]).await.unwrap();
+    let result = env.send(vec![keys.init_outcome_mint_ix(&creator.pubkey(), 1)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidOutcomeIndex);
+}
+
+#[tokio::test]
+async fn outcome_mints_pending() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let instructions = vec![keys.create_ix(&creator, categorical_args(0, now, creator.pubkey(), 3)), keys.init_outcome_mint_ix(&creator.pubkey(), 0)];
+    env.send(instructions, &[&creator.keypair]).await.unwrap();
+    env.create_atas(&trader.pubkey(), &[outcome_mint_pda(&keys.market, 0)]).await;
+    let result = env.send(vec![keys.buy_ix(&trader, 0, u64::MAX, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::OutcomeMintsPending);
+}
+
+#[tokio::test]
+async fn invalid_scalar_bounds() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = MarketKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateScalarMarket {
+        market_id: 0,
+        question: "Where will the index close?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: Pubkey::new_unique(),
+        lower_bound: 200,
+        upper_bound: 200,
+        bound_expo: 0,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+        oracle_max_age: 120,
+        oracle_max_conf_bps: 500,
+    };
+    let result = env.send(vec![keys.create_scalar_ix(&creator, args)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidScalarBounds);
+}
+
+async fn book_setup() -> (TestEnv, User, User, MarketKeys) {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.send(vec![keys.init_order_book_ix(&creator.pubkey())], &[&creator.keypair]).await.unwrap();
+    (env, creator, trader, keys)
+}
+
+#[tokio::test]
+async fn invalid_order_price() {
+    let (mut env, _creator, trader, keys) = book_setup().await;
+    let result = env.send(vec![keys.place_order_ix(&trader, ShareOutcome::Yes, OrderSide::Bid, 0, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::InvalidOrderPrice);
+}
+
+#[tokio::test]
+async fn order_too_small() {
+    let (mut env, _creator, trader, keys) = book_setup().await;
+    // 9_999_999 shares at 0.100 are worth just under one whole unit of collateral.
+    let result = env.send(vec![keys.place_order_ix(&trader, ShareOutcome::Yes, OrderSide::Bid, 100, 9_999_999)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::OrderTooSmall);
+}
+
+#[tokio::test]
+async fn order_book_full() {
+    let (mut env, _creator, _trader, keys) = book_setup().await;
+    let maker = env.new_user(40_000_000).await;
+    for _ in 0..prediction_market::MAX_ORDERS / 8 {
+        let orders = (0..8).map(|_| keys.place_order_ix(&maker, ShareOutcome::Yes, OrderSide::Bid, 100, 10_000_000)).collect();
+        env.send(orders, &[&maker.keypair]).await.unwrap();
+    }
+    let result = env.send(vec![keys.place_order_ix(&maker, ShareOutcome::Yes, OrderSide::Bid, 100, 10_000_000)], &[&maker.keypair]).await;
+    assert_market_error(result, MarketError::OrderBookFull);
+}
+
+#[tokio::test]
+async fn order_not_found() {
+    let (mut env, _creator, trader, keys) = book_setup().await;
+    let result = env.send(vec![keys.cancel_order_ix(&trader, ShareOutcome::Yes, 99)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::OrderNotFound);
+}
+
+#[tokio::test]
+async fn unauthorized_order_owner() {
+    let (mut env, creator, trader, keys) = book_setup().await;
+    env.send(vec![keys.place_order_ix(&trader, ShareOutcome::Yes, OrderSide::Bid, 100, 10_000_000)], &[&trader.keypair]).await.unwrap();
+    env.open_positions(&keys, &creator).await;
+    let result = env.send(vec![keys.cancel_order_ix(&creator, ShareOutcome::Yes, 0)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::UnauthorizedOrderOwner);
+}
+
+#[tokio::test]
+async fn optimistic_resolution_required() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::OptimisticResolutionRequired);
+}
+
+#[tokio::test]
+async fn invalid_dispute_config() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    let result = env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), creator.pubkey(), 0, 3_600)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::InvalidDisputeConfig);
+}
+
+#[tokio::test]
+async fn resolution_pending() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    let result = env.send(vec![keys.emergency_ix(&env.admin.pubkey(), WinningOutcome::No)], &[]).await;
+    assert_market_error(result, MarketError::ResolutionPending);
+}
+
+#[tokio::test]
+async fn no_resolution_proposed() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.bond_ix(&trader, instruction::DisputeResolution {})], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::NoResolutionProposed);
+}
+
+#[tokio::test]
+async fn already_disputed() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    env.send(vec![keys.bond_ix(&creator, instruction::DisputeResolution {})], &[&creator.keypair]).await.unwrap();
+    let result = env.send(vec![keys.bond_ix(&trader, instruction::DisputeResolution {})], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::AlreadyDisputed);
+}
+
+#[tokio::test]
+async fn not_disputed() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    let arbitrate = keys.settle_ix(&creator.pubkey(), &trader, instruction::ArbitrateResolution { outcome: WinningOutcome::Yes });
+    assert_market_error(env.send(vec![arbitrate], &[&creator.keypair]).await, MarketError::NotDisputed);
+}
+
+#[tokio::test]
+async fn dispute_window_closed() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    env.warp(3_601).await;
+    let result = env.send(vec![keys.bond_ix(&creator, instruction::DisputeResolution {})], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::DisputeWindowClosed);
+}
+
+#[tokio::test]
+async fn dispute_window_open() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    let finalize = keys.settle_ix(&trader.pubkey(), &trader, instruction::FinalizeResolution {});
+    assert_market_error(env.send(vec![finalize], &[&trader.keypair]).await, MarketError::DisputeWindowOpen);
+}
+
+#[tokio::test]
+async fn invalid_resolver_config() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        resolver: Some(ResolverConfig::Committee { members: vec![creator.pubkey()], threshold: 2 }),
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidResolverConfig);
+}
+
+#[tokio::test]
+async fn committee_resolution_required() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        resolver: Some(ResolverConfig::Committee { members: vec![creator.pubkey()], threshold: 1 }),
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    let keys = env.create_market(&creator, args).await.unwrap();
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::CommitteeResolutionRequired);
+}
+
+#[tokio::test]
+async fn not_a_committee_market() {
+    let (mut env, creator, _trader, keys) = setup().await;
+    env.warp(DAY + 1).await;
+    let result = env.send(vec![keys.vote_ix(&creator.pubkey(), WinningOutcome::Yes)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::NotACommitteeMarket);
+}
+
+#[tokio::test]
+async fn protocol_paused() {
+    let (mut env, _creator, trader, keys) = setup().await;
+    let admin = env.admin.pubkey();
+    env.send(vec![update_config_ix(admin, instruction::SetPaused { paused: true })], &[]).await.unwrap();
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::ProtocolPaused);
+}
+
+#[tokio::test]
+async fn unauthorized_admin() {
+    let (mut env, _creator, trader, _keys) = setup().await;
+    let result = env.send(vec![update_config_ix(trader.pubkey(), instruction::SetPaused { paused: true })], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::UnauthorizedAdmin);
+}
+
+#[tokio::test]
+async fn invalid_config() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    let mint = env.collateral_mint;
+    let result = env.send(vec![update_config_ix(admin, instruction::AddCollateralMint { mint })], &[]).await;
+    assert_market_error(result, MarketError::InvalidConfig);
+}
+
+#[tokio::test]
+async fn collateral_not_allowed() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let other_mint = env.create_mint().await;
+    env.create_atas(&creator.pubkey(), &[other_mint]).await;
+    let now = env.now().await;
+    let keys = MarketKeys::new(&creator.pubkey(), 0, other_mint);
+    let creator_on_other_mint = User {
+        keypair: creator.keypair.insecure_clone(),
+        collateral: creator.ata(&other_mint),
+        token_program: creator.token_program,
+    };
+    let create = keys.create_ix(&creator_on_other_mint, manual_market_args(0, now, DAY, creator.pubkey()));
+    let result = env.send(vec![create], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::CollateralNotAllowed);
+}
+
+#[tokio::test]
+async fn invalid_market_duration() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let args = manual_market_args(0, now, 30, creator.pubkey());
+    assert_market_error(create_with(&mut env, &creator, args).await, MarketError::InvalidMarketDuration);
+}
+
+#[tokio::test]
+async fn market_paused() {
+    let (mut env, creator, trader, keys) = setup().await;
+    env.open_positions(&keys, &creator).await;
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::PauseMarket {})], &[&creator.keypair]).await.unwrap();
+    let result = env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketPaused);
+    let result = env.send(vec![keys.add_liquidity_ix(&trader, 1_000_000, 0)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketPaused);
+    let result = env.send(vec![keys.remove_liquidity_ix(&creator, 1_000, 0)], &[&creator.keypair]).await;
+    assert_market_error(result, MarketError::MarketPaused);
+    let result = env.send(vec![keys.mint_set_ix(&trader, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::MarketPaused);
+}
+
+#[tokio::test]
+async fn arbitration_period_not_reached() {
+    let (mut env, creator, trader, keys) = setup().await;
+    optimistic_setup(&mut env, &creator, &keys, creator.pubkey()).await;
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&trader, instruction::ProposeResolution { outcome: WinningOutcome::Yes })], &[&trader.keypair]).await.unwrap();
+    env.send(vec![keys.bond_ix(&creator, instruction::DisputeResolution {})], &[&creator.keypair]).await.unwrap();
+    env.warp(prediction_market::ARBITRATION_PERIOD - 60).await;
+    let result = env.send(vec![keys.expire_dispute_ix(&trader.pubkey(), &trader, &creator)], &[&trader.keypair]).await;
+    assert_market_error(result, MarketError::ArbitrationPeriodNotReached);
+}
@@ -0,0 +1,90 @@
+//! Events decoded from the program logs carry the state change they announce.
+
+use prediction_market::{MarketResolved, SharesBought, SharesSold, ShareOutcome, WinningOutcome, WinningsRedeemed, PRICE_PRECISION};
+use prediction_market_test_utils::*;
+use solana_sdk::pubkey::Pubkey;
+
+#[tokio::test]
+async fn trades_log_amounts_fees_and_prices() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    let before = env.balance(&trader.collateral).await;
+    let logs = env.send_with_logs(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 300_000)], &[&trader.keypair]).await;
+    let [bought]: [SharesBought; 1] = events(&logs).try_into().ok().unwrap();
+    let market = env.market(&keys.market).await;
+    assert_eq!(bought.market, keys.market);
+    assert_eq!(bought.user, trader.pubkey());
+    assert!(bought.outcome == ShareOutcome::Yes);
+    assert_eq!(bought.shares, 300_000);
+    assert_eq!(bought.book_shares, 0);
+    assert_eq!(bought.cost + bought.fee, before - env.balance(&trader.collateral).await);
+    assert_eq!(bought.fee, market.creator_fees_accrued + market.protocol_fees_accrued);
+    assert!(bought.yes_price > PRICE_PRECISION / 2);
+    assert!(bought.yes_price + bought.no_price >= PRICE_PRECISION - 1 && bought.yes_price + bought.no_price <= PRICE_PRECISION + 1);
+    assert_eq!(bought.timestamp, env.now().await);
+
+    let before = env.balance(&trader.collateral).await;
+    let logs = env.send_with_logs(vec![keys.sell_ix(&trader, ShareOutcome::Yes, 100_000, 0)], &[&trader.keypair]).await;
+    let [sold]: [SharesSold; 1] = events(&logs).try_into().ok().unwrap();
+    assert_eq!(sold.market, keys.market);
+    assert_eq!(sold.user, trader.pubkey());
+    assert!(sold.outcome == ShareOutcome::Yes);
+    assert_eq!(sold.shares, 100_000);
+    assert_eq!(sold.book_shares, 0);
+    assert_eq!(sold.payout, env.balance(&trader.collateral).await - before);
+    assert!(sold.fee > 0);
+    assert!(sold.yes_price < bought.yes_price);
+    assert!(sold.no_price > bought.no_price);
+}
+
+#[tokio::test]
+async fn resolution_and_redemption_log_the_outcome_and_payout() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 300_000)], &[&trader.keypair]).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    let logs = env
+        .send_with_logs(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair])
+        .await;
+    let [resolved]: [MarketResolved; 1] = events(&logs).try_into().ok().unwrap();
+    assert_eq!(resolved.market, keys.market);
+    assert_eq!(resolved.resolver, creator.pubkey());
+    assert!(resolved.outcome == WinningOutcome::Yes);
+    assert_eq!(resolved.oracle_price, None);
+    assert_eq!(resolved.oracle_expo, None);
+    assert_eq!(resolved.timestamp, env.now().await);
+
+    let logs = env.send_with_logs(vec![keys.redeem_ix(&trader, keys.yes_mint, 300_000)], &[&trader.keypair]).await;
+    let [redeemed]: [WinningsRedeemed; 1] = events(&logs).try_into().ok().unwrap();
+    assert_eq!(redeemed.market, keys.market);
+    assert_eq!(redeemed.user, trader.pubkey());
+    assert!(redeemed.outcome == WinningOutcome::Yes);
+    assert_eq!(redeemed.tokens_burned, 300_000);
+    assert_eq!(redeemed.payout, 300_000);
+}
+
+#[tokio::test]
+async fn oracle_resolution_logs_the_feed_price() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = env.create_market(&creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap();
+
+    env.warp(DAY + 10).await;
+    let now = env.now().await;
+    env.set_pyth_price(&feed, 101, 1, -2, now - 5);
+    let logs = env.send_with_logs(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await;
+    let [resolved]: [MarketResolved; 1] = events(&logs).try_into().ok().unwrap();
+    assert_eq!(resolved.resolver, creator.pubkey());
+    assert_eq!(resolved.oracle_price, Some(101));
+    assert_eq!(resolved.oracle_expo, Some(-2));
+}
@@ -0,0 +1,117 @@
+//! Randomized trade sequences against a single market, checking after every step
+//! that the vaults hold exactly what the market accounts for and that the pool
+//! can always pay out the worst-case outcome.
+
+use prediction_market::{ShareOutcome, WinningOutcome};
+use prediction_market_test_utils::*;
+
+const TRADERS: usize = 3;
+const STEPS: usize = 40;
+
+async fn assert_solvent(env: &mut TestEnv, keys: &MarketKeys, step: &str) {
+    let market = env.market(&keys.market).await;
+    let vault = env.balance(&keys.collateral_vault).await;
+    assert_eq!(vault, market.total_liquidity, "{step}: vault drifted from total_liquidity");
+    assert_eq!(env.balance(&keys.creator_fee_vault).await, market.creator_fees_accrued, "{step}: creator fee vault drifted");
+    assert_eq!(env.balance(&keys.protocol_fee_vault).await, market.protocol_fees_accrued, "{step}: protocol fee vault drifted");
+    assert_eq!(env.supply(&keys.yes_mint).await, market.yes_shares_outstanding, "{step}: YES supply drifted");
+    assert_eq!(env.supply(&keys.no_mint).await, market.no_shares_outstanding, "{step}: NO supply drifted");
+    let worst_case = market.yes_shares_outstanding.max(market.no_shares_outstanding);
+    assert!(vault >= worst_case, "{step}: vault {vault} cannot cover {worst_case} winning shares");
+}
+
+async fn run(seed: u64, winner: WinningOutcome) {
+    let mut rng = XorShift(seed);
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(100_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &creator).await;
+
+    let mut traders = Vec::new();
+    for _ in 0..TRADERS {
+        let trader = env.new_user(100_000_000).await;
+        env.open_positions(&keys, &trader).await;
+        traders.push(trader);
+    }
+
+    for step in 0..STEPS {
+        let trader = &traders[rng.below(TRADERS as u64) as usize];
+        let outcome = if rng.below(2) == 0 { ShareOutcome::Yes } else { ShareOutcome::No };
+        let held = env.balance(&trader.ata(&keys.share_mint(&outcome))).await;
+        let label = format!("seed {seed} step {step}");
+
+        let instruction = match rng.below(6) {
+            0 | 1 => keys.buy_ix(trader, outcome, u64::MAX, 1 + rng.below(800_000)),
+            2 if held > 0 => keys.sell_ix(trader, outcome, 1 + rng.below(held), 0),
+            3 => keys.mint_set_ix(trader, 1 + rng.below(500_000)),
+            4 => {
+                let yes = env.balance(&trader.ata(&keys.yes_mint)).await;
+                let no = env.balance(&trader.ata(&keys.no_mint)).await;
+                let sets = yes.min(no);
+                if sets == 0 {
+                    continue;
+                }
+                keys.merge_set_ix(trader, 1 + rng.below(sets))
+            },
+            _ => {
+                let lp = env.balance(&trader.ata(&keys.lp_mint)).await;
+                if lp > 0 && rng.below(2) == 0 {
+                    keys.remove_liquidity_ix(trader, 1 + rng.below(lp), 0)
+                } else {
+                    keys.add_liquidity_ix(trader, 1_000 + rng.below(1_000_000), 0)
+                }
+            },
+        };
+        env.send(vec![instruction], &[&trader.keypair]).await.unwrap_or_else(|err| panic!("{label}: {err}"));
+        assert_solvent(&mut env, &keys, &label).await;
+    }
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(winner.clone()))], &[&creator.keypair]).await.unwrap();
+
+    // Every holder can redeem in full, whatever the outcome.
+    let mut holders: Vec<&User> = traders.iter().collect();
+    holders.push(&creator);
+    for holder in &holders {
+        for mint in [keys.yes_mint, keys.no_mint] {
+            let amount = env.balance(&holder.ata(&mint)).await;
+            let redeemable = match winner {
+                WinningOutcome::Yes => mint == keys.yes_mint,
+                WinningOutcome::No => mint == keys.no_mint,
+                _ => true,
+            };
+            if amount > 0 && redeemable {
+                env.send(vec![keys.redeem_ix(holder, mint, amount)], &[&holder.keypair]).await.unwrap_or_else(|err| panic!("seed {seed}: redeem failed: {err}"));
+            }
+        }
+    }
+
+    // What is left belongs to the LPs, and every one of them can take their share.
+    for holder in &holders {
+        let lp = env.balance(&holder.ata(&keys.lp_mint)).await;
+        if lp > 0 {
+            let _ = env.send(vec![keys.withdraw_remaining_ix(holder, lp)], &[&holder.keypair]).await;
+        }
+    }
+    let vault = env.balance(&keys.collateral_vault).await;
+    assert!(vault <= holders.len() as u64, "seed {seed}: {vault} left in the vault after every LP withdrew");
+}
+
+#[tokio::test]
+async fn random_trades_stay_solvent_when_yes_wins() {
+    for seed in [0x9E37_79B9_7F4A_7C15, 0xD1B5_4A32_D192_ED03] {
+        run(seed, WinningOutcome::Yes).await;
+    }
+}
+
+#[tokio::test]
+async fn random_trades_stay_solvent_when_no_wins() {
+    for seed in [0x2545_F491_4F6C_DD1D, 0x94D0_49BB_1331_11EB] {
+        run(seed, WinningOutcome::No).await;
+    }
+}
+
+#[tokio::test]
+async fn random_trades_stay_solvent_when_canceled() {
+    run(0xBF58_476D_1CE4_E5B9, WinningOutcome::Canceled).await;
+}
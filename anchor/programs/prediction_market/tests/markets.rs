@@ -0,0 +1,769 @@
+//! Happy paths through every instruction of the program.
+
+use anchor_lang::InstructionData;
+use anchor_spl::token_interface::spl_token_2022::{self, extension::transfer_fee::TransferFee};
+use prediction_market::{instruction, math, FeeRecipient, MarketError, MarketKind, OrderSide, ResolverConfig, ShareOutcome, TradeQuote, WinningOutcome, PRICE_PRECISION};
+use prediction_market_test_utils::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+
+#[tokio::test]
+async fn create_market_derives_every_account_from_creator_and_id() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 7, DAY).await;
+
+    assert_eq!(keys.market, market_pda(&creator.pubkey(), 7));
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.creator, creator.pubkey());
+    assert_eq!(market.market_id, 7);
+    assert_eq!(market.yes_token_mint, keys.yes_mint);
+    assert_eq!(market.no_token_mint, keys.no_mint);
+    assert_eq!(market.collateral_vault, keys.collateral_vault);
+    assert_eq!(market.fee_bps, DEFAULT_FEE_BPS);
+    assert_eq!(market.total_liquidity, 2 * INITIAL_LIQUIDITY);
+    assert_eq!(env.balance(&keys.collateral_vault).await, 2 * INITIAL_LIQUIDITY);
+    assert_eq!(env.balance(&creator.ata(&keys.lp_mint)).await, 2 * INITIAL_LIQUIDITY);
+
+    // The same creator can open a second market under a new id.
+    let second = env.create_manual_market(&creator, 8, DAY).await;
+    assert_ne!(second.market, keys.market);
+}
+
+#[tokio::test]
+async fn buy_and_sell_move_the_price_and_accrue_fees() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    let opening_price = env.view_u64(keys.price_ix(ShareOutcome::Yes)).await;
+    assert!(opening_price.abs_diff(PRICE_PRECISION / 2) <= 1);
+
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 500_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.ata(&keys.yes_mint)).await, 500_000);
+    assert!(env.view_u64(keys.price_ix(ShareOutcome::Yes)).await > opening_price);
+
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.yes_shares_outstanding, 500_000);
+    assert!(market.creator_fees_accrued > 0 && market.protocol_fees_accrued > 0);
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+    assert_eq!(env.balance(&keys.creator_fee_vault).await, market.creator_fees_accrued);
+    assert_eq!(env.balance(&keys.protocol_fee_vault).await, market.protocol_fees_accrued);
+
+    env.send(vec![keys.sell_ix(&trader, ShareOutcome::Yes, 500_000, 0)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.ata(&keys.yes_mint)).await, 0);
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.yes_shares_outstanding, 0);
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+    // The round trip costs the trader the fees on both legs and nothing more.
+    assert!(env.balance(&trader.collateral).await < 10_000_000);
+    assert_eq!(env.view_u64(keys.price_ix(ShareOutcome::Yes)).await, opening_price);
+}
+
+#[tokio::test]
+async fn quote_trade_previews_what_a_trade_costs_and_pays() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    let buy: TradeQuote = env.view(keys.quote_ix(ShareOutcome::Yes, OrderSide::Bid, 500_000)).await;
+    assert_eq!(buy.total, buy.amount + buy.fee);
+    assert!(buy.average_price > PRICE_PRECISION / 2 && buy.average_price < buy.yes_price_after);
+    assert!(buy.price_impact_bps > 0);
+
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, buy.total, 500_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, 10_000_000 - buy.total);
+    assert_eq!(env.view_u64(keys.price_ix(ShareOutcome::Yes)).await, buy.yes_price_after);
+    assert_eq!(env.view_u64(keys.price_ix(ShareOutcome::No)).await, buy.no_price_after);
+
+    let sell: TradeQuote = env.view(keys.quote_ix(ShareOutcome::Yes, OrderSide::Ask, 500_000)).await;
+    assert_eq!(sell.total, sell.amount - sell.fee);
+    assert!(sell.amount <= buy.amount);
+    env.send(vec![keys.sell_ix(&trader, ShareOutcome::Yes, 500_000, sell.total)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, 10_000_000 - buy.total + sell.total);
+    assert_eq!(env.view_u64(keys.price_ix(ShareOutcome::Yes)).await, sell.yes_price_after);
+}
+
+#[tokio::test]
+async fn quote_trade_handles_a_price_that_rounds_to_zero() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(100_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    // 43 liquidity parameters of YES push NO's exact price below 1e-18.
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 43 * LIQUIDITY_PARAMETER)], &[&trader.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    assert_eq!(math::lmsr_price(&market.shares(), market.liquidity_parameter, ShareOutcome::No.index()).unwrap(), 0);
+
+    let nudge: TradeQuote = env.view(keys.quote_ix(ShareOutcome::No, OrderSide::Bid, 1_000)).await;
+    assert_eq!(nudge.price_impact_bps, 0);
+    let lift: TradeQuote = env.view(keys.quote_ix(ShareOutcome::No, OrderSide::Bid, 5 * LIQUIDITY_PARAMETER)).await;
+    assert_eq!(lift.price_impact_bps, u64::MAX);
+    assert_eq!(lift.no_price_after, prediction_market::MIN_PRICE);
+}
+
+#[tokio::test]
+async fn exact_collateral_trades_solve_for_the_share_count() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    // The buy takes the largest share count the budget covers, fees included.
+    let market = env.market(&keys.market).await;
+    env.send(vec![keys.buy_with_collateral_ix(&trader, ShareOutcome::Yes, 300_000, 1)], &[&trader.keypair]).await.unwrap();
+    let bought = env.balance(&trader.ata(&keys.yes_mint)).await;
+    let spent = 10_000_000 - env.balance(&trader.collateral).await;
+    assert!(bought > 0 && spent <= 300_000);
+    let one_more = math::buy_cost(&market.shares(), market.liquidity_parameter, 0, bought + 1).unwrap();
+    let (creator_fee, protocol_fee) = math::calculate_fees(one_more, market.fee_bps).unwrap();
+    assert!(one_more + creator_fee + protocol_fee > 300_000);
+
+    let err = env.send(vec![keys.buy_with_collateral_ix(&trader, ShareOutcome::Yes, 100_000, 1_000_000)], &[&trader.keypair]).await;
+    assert_market_error(err, MarketError::SlippageExceeded);
+
+    // The sell gives up the fewest shares that net the requested collateral.
+    let market = env.market(&keys.market).await;
+    let before = env.balance(&trader.collateral).await;
+    env.send(vec![keys.sell_for_collateral_ix(&trader, ShareOutcome::Yes, 100_000, bought)], &[&trader.keypair]).await.unwrap();
+    let sold = bought - env.balance(&trader.ata(&keys.yes_mint)).await;
+    assert!(env.balance(&trader.collateral).await - before >= 100_000);
+    let one_less = math::sell_payout(&market.shares(), market.liquidity_parameter, 0, sold - 1).unwrap();
+    let (creator_fee, protocol_fee) = math::calculate_fees(one_less, market.fee_bps).unwrap();
+    assert!(one_less - creator_fee - protocol_fee < 100_000);
+
+    let err = env.send(vec![keys.sell_for_collateral_ix(&trader, ShareOutcome::Yes, 100_000, 1_000)], &[&trader.keypair]).await;
+    assert_market_error(err, MarketError::SlippageExceeded);
+
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.yes_shares_outstanding, bought - sold);
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+}
+
+#[tokio::test]
+async fn exact_collateral_trades_leave_the_order_book_alone() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let maker = env.new_user(20_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &maker).await;
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.init_order_book_ix(&creator.pubkey())], &[&creator.keypair]).await.unwrap();
+
+    // An ask below the AMM quote and a bid above it, either of which a book
+    // trade would take first.
+    env.send(vec![keys.mint_set_ix(&maker, 5_000_000)], &[&maker.keypair]).await.unwrap();
+    env.send(vec![keys.place_order_ix(&maker, ShareOutcome::Yes, OrderSide::Ask, 400, 5_000_000)], &[&maker.keypair]).await.unwrap();
+    env.send(vec![keys.place_order_ix(&maker, ShareOutcome::Yes, OrderSide::Bid, 800, 1_250_000)], &[&maker.keypair]).await.unwrap();
+    let book = env.order_book(&keys.market).await;
+
+    // The book accounts are passed, but the budget is still spent on the AMM.
+    let market = env.market(&keys.market).await;
+    let mut buy = keys.buy_with_book_ix(&trader, ShareOutcome::Yes, 0, 0, &[]);
+    buy.data = instruction::BuyWithCollateral { outcome: ShareOutcome::Yes, collateral_in: 300_000, min_shares_out: 1 }.data();
+    env.send(vec![buy], &[&trader.keypair]).await.unwrap();
+    let bought = env.balance(&trader.ata(&keys.yes_mint)).await;
+    let spent = 10_000_000 - env.balance(&trader.collateral).await;
+    assert!(bought > 0 && spent <= 300_000);
+    let one_more = math::buy_cost(&market.shares(), market.liquidity_parameter, 0, bought + 1).unwrap();
+    let (creator_fee, protocol_fee) = math::calculate_fees(one_more, market.fee_bps).unwrap();
+    assert!(one_more + creator_fee + protocol_fee > 300_000);
+
+    let mut sell = keys.sell_with_book_ix(&trader, ShareOutcome::Yes, 0, 0, &[]);
+    sell.data = instruction::SellForCollateral { outcome: ShareOutcome::Yes, collateral_out: 100_000, max_shares_in: bought }.data();
+    env.send(vec![sell], &[&trader.keypair]).await.unwrap();
+
+    assert!(env.order_book(&keys.market).await.orders == book.orders);
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.yes_shares_outstanding, 5_000_000 + env.balance(&trader.ata(&keys.yes_mint)).await);
+}
+
+#[tokio::test]
+async fn exact_collateral_buys_absorb_the_collateral_transfer_fee() {
+    let mut env = TestEnv::new().await;
+    // 1% transfer fee, capped well above anything this test moves
+    let transfer_fee = TransferFee { epoch: 0.into(), maximum_fee: 1_000_000.into(), transfer_fee_basis_points: 100.into() };
+    let mint = env.create_transfer_fee_mint(100, 1_000_000).await;
+    let creator = env.new_user_with(&mint, &spl_token_2022::ID, 10_000_000).await;
+    let trader = env.new_user_with(&mint, &spl_token_2022::ID, 10_000_000).await;
+    let now = env.now().await;
+    let keys = MarketKeys { token_program: spl_token_2022::ID, ..MarketKeys::new(&creator.pubkey(), 0, mint) };
+    env.send(vec![keys.create_ix(&creator, manual_market_args(0, now, DAY, creator.pubkey()))], &[&creator.keypair]).await.unwrap();
+    env.open_positions(&keys, &trader).await;
+
+    // Every leg of the buy is grossed up for the transfer fee, and together they
+    // still fit the budget; one more share would not.
+    let market = env.market(&keys.market).await;
+    env.send(vec![keys.buy_with_collateral_ix(&trader, ShareOutcome::Yes, 300_000, 1)], &[&trader.keypair]).await.unwrap();
+    let bought = env.balance(&trader.ata(&keys.yes_mint)).await;
+    let spent = 10_000_000 - env.balance(&trader.collateral).await;
+    assert!(bought > 0 && spent <= 300_000);
+    let one_more = math::buy_cost(&market.shares(), market.liquidity_parameter, 0, bought + 1).unwrap();
+    let (creator_fee, protocol_fee) = math::calculate_fees(one_more, market.fee_bps).unwrap();
+    let debit: u64 = [one_more, creator_fee, protocol_fee]
+        .iter()
+        .map(|&amount| amount + transfer_fee.calculate_inverse_fee(amount).unwrap())
+        .sum();
+    assert!(debit > 300_000);
+
+    // The vaults are still credited exactly what the market accounts for.
+    let market = env.market(&keys.market).await;
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+    assert_eq!(env.balance(&keys.creator_fee_vault).await, market.creator_fees_accrued);
+    assert_eq!(env.balance(&keys.protocol_fee_vault).await, market.protocol_fees_accrued);
+}
+
+#[tokio::test]
+async fn complete_sets_mint_and_merge_at_par() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(1_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    env.send(vec![keys.mint_set_ix(&trader, 400_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, 600_000);
+    assert_eq!(env.balance(&trader.ata(&keys.yes_mint)).await, 400_000);
+    assert_eq!(env.balance(&trader.ata(&keys.no_mint)).await, 400_000);
+
+    env.send(vec![keys.merge_set_ix(&trader, 400_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, 1_000_000);
+    assert_eq!(env.supply(&keys.yes_mint).await, 0);
+    let market = env.market(&keys.market).await;
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+}
+
+#[tokio::test]
+async fn liquidity_providers_join_and_leave_pro_rata() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let provider = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &provider).await;
+
+    env.send(vec![keys.add_liquidity_ix(&provider, 1_000_000, 1)], &[&provider.keypair]).await.unwrap();
+    let lp_tokens = env.balance(&provider.ata(&keys.lp_mint)).await;
+    assert_eq!(lp_tokens, 1_000_000);
+    assert_eq!(env.market(&keys.market).await.liquidity_parameter, LIQUIDITY_PARAMETER * 3 / 2);
+
+    env.send(vec![keys.remove_liquidity_ix(&provider, lp_tokens, 1)], &[&provider.keypair]).await.unwrap();
+    assert_eq!(env.balance(&provider.ata(&keys.lp_mint)).await, 0);
+    assert_eq!(env.balance(&provider.collateral).await, 10_000_000);
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.liquidity_parameter, LIQUIDITY_PARAMETER);
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+}
+
+#[tokio::test]
+async fn manual_resolution_pays_winners_liquidity_and_fees() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::Yes, u64::MAX, 300_000)], &[&trader.keypair]).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Yes))], &[&creator.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    assert!(market.resolved);
+    assert!(market.winning_outcome == WinningOutcome::Yes);
+
+    let before = env.balance(&trader.collateral).await;
+    env.send(vec![keys.redeem_ix(&trader, keys.yes_mint, 300_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, before + 300_000);
+
+    let vault = env.balance(&keys.collateral_vault).await;
+    let creator_before = env.balance(&creator.collateral).await;
+    env.send(vec![keys.withdraw_remaining_ix(&creator, 2 * INITIAL_LIQUIDITY)], &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&keys.collateral_vault).await, 0);
+    assert_eq!(env.balance(&creator.collateral).await, creator_before + vault);
+
+    let creator_fees = market.creator_fees_accrued;
+    env.send(vec![keys.claim_fees_ix(&creator.pubkey(), FeeRecipient::Creator, creator.collateral)], &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&creator.collateral).await, creator_before + vault + creator_fees);
+
+    let admin = env.admin.insecure_clone();
+    let treasury = anchor_spl::associated_token::get_associated_token_address(&admin.pubkey(), &env.collateral_mint);
+    env.create_atas(&admin.pubkey(), &[env.collateral_mint]).await;
+    env.send(vec![keys.claim_fees_ix(&admin.pubkey(), FeeRecipient::Protocol, treasury)], &[]).await.unwrap();
+    assert_eq!(env.balance(&treasury).await, market.protocol_fees_accrued);
+    assert_eq!(env.balance(&keys.protocol_fee_vault).await, 0);
+}
+
+#[tokio::test]
+async fn oracle_markets_resolve_from_the_feed() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = env.create_market(&creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap();
+
+    env.warp(DAY + 10).await;
+    let now = env.now().await;
+    env.set_pyth_price(&feed, 101, 1, 0, now - 5);
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::Yes);
+}
+
+#[tokio::test]
+async fn oracle_markets_cancel_after_the_grace_period_without_a_price() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = env.create_market(&creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap();
+
+    env.warp(DAY + prediction_market::ORACLE_GRACE_PERIOD + 10).await;
+    env.set_pyth_price(&feed, 101, 1, 0, now);
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::Canceled);
+}
+
+#[tokio::test]
+async fn scalar_markets_split_each_unit_between_long_and_short() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = MarketKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateScalarMarket {
+        market_id: 0,
+        question: "Where will the index close?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: feed,
+        lower_bound: 100,
+        upper_bound: 200,
+        bound_expo: 0,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+        oracle_max_age: 120,
+        oracle_max_conf_bps: 500,
+    };
+    env.send(vec![keys.create_scalar_ix(&creator, args)], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.market_kind == MarketKind::Scalar);
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.mint_set_ix(&trader, 1_000_000)], &[&trader.keypair]).await.unwrap();
+
+    env.warp(DAY + 10).await;
+    let now = env.now().await;
+    env.set_pyth_price(&feed, 125, 1, 0, now - 5);
+    env.send(vec![keys.resolve_ix(&creator.pubkey(), feed, None)], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::Scalar);
+
+    env.send(
+        vec![keys.redeem_ix(&trader, keys.yes_mint, 1_000_000), keys.redeem_ix(&trader, keys.no_mint, 1_000_000)],
+        &[&trader.keypair],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, 10_000_000);
+}
+
+#[tokio::test]
+async fn categorical_markets_trade_and_redeem_per_outcome() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateCategoricalMarket {
+        market_id: 0,
+        question: "Who wins the league?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: creator.pubkey(),
+        resolver: None,
+        outcome_count: 3,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+    };
+    let init_mints = (0..3).map(|index| keys.init_outcome_mint_ix(&creator.pubkey(), index));
+    let mut instructions = vec![keys.create_ix(&creator, args)];
+    instructions.extend(init_mints);
+    env.send(instructions, &[&creator.keypair]).await.unwrap();
+
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.outcome_mints.len(), 3);
+    assert_eq!(env.balance(&keys.collateral_vault).await, 3 * INITIAL_LIQUIDITY);
+    assert!(env.view_u64(keys.price_ix(1)).await.abs_diff(PRICE_PRECISION / 3) <= 1);
+
+    let winning_mint = outcome_mint_pda(&keys.market, 1);
+    env.create_atas(&trader.pubkey(), &[winning_mint]).await;
+    env.send(vec![keys.buy_ix(&trader, 1, u64::MAX, 200_000)], &[&trader.keypair]).await.unwrap();
+    env.send(vec![keys.sell_ix(&trader, 1, 50_000, 0)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.ata(&winning_mint)).await, 150_000);
+    let market = env.market(&keys.market).await;
+    assert_eq!(market.outcome_shares[1], 150_000);
+    assert_eq!(env.balance(&keys.collateral_vault).await, market.total_liquidity);
+
+    env.warp(DAY + 1).await;
+    let resolve = MarketKeys::new(&creator.pubkey(), 0, env.collateral_mint).resolve_ix(&creator.pubkey(), creator.pubkey(), Some(WinningOutcome::Outcome(1)));
+    env.send(vec![resolve], &[&creator.keypair]).await.unwrap();
+
+    let before = env.balance(&trader.collateral).await;
+    env.send(vec![keys.redeem_ix(&trader, 1, 150_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, before + 150_000);
+}
+
+#[tokio::test]
+async fn canceled_markets_refund_at_the_snapshotted_prices() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.mint_set_ix(&trader, 100_000)], &[&trader.keypair]).await.unwrap();
+
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::CancelMarket {})], &[&creator.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    assert!(market.winning_outcome == WinningOutcome::Canceled);
+    // Rates are rounded down, so a complete set refunds at most a unit of dust less than par.
+    assert!(market.canceled_yes_refund_rate + market.canceled_no_refund_rate <= prediction_market::REFUND_RATE_PRECISION);
+    assert!(market.canceled_yes_refund_rate + market.canceled_no_refund_rate >= prediction_market::REFUND_RATE_PRECISION - 2);
+
+    env.send(
+        vec![keys.redeem_ix(&trader, keys.yes_mint, 100_000), keys.redeem_ix(&trader, keys.no_mint, 100_000)],
+        &[&trader.keypair],
+    )
+    .await
+    .unwrap();
+    assert!(env.balance(&trader.collateral).await >= 10_000_000 - 2);
+}
+
+#[tokio::test]
+async fn canceled_refunds_do_not_depend_on_redemption_order() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let bull = env.new_user(10_000_000).await;
+    let bear = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &bull).await;
+    env.open_positions(&keys, &bear).await;
+    env.send(vec![keys.buy_ix(&bull, ShareOutcome::Yes, u64::MAX, 600_000)], &[&bull.keypair]).await.unwrap();
+    env.send(vec![keys.buy_ix(&bear, ShareOutcome::No, u64::MAX, 100_000)], &[&bear.keypair]).await.unwrap();
+
+    let shares = env.market(&keys.market).await.shares();
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::CancelMarket {})], &[&creator.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    let rates = math::refund_rates(&shares, market.liquidity_parameter).unwrap();
+    assert_eq!([market.canceled_yes_refund_rate, market.canceled_no_refund_rate], [rates[0], rates[1]]);
+    assert!(market.canceled_yes_refund_rate > market.canceled_no_refund_rate);
+    let refund = |amount: u64, rate: u64| amount * rate / prediction_market::REFUND_RATE_PRECISION;
+
+    // Interleave the two holders: every redemption pays the same snapshotted rate.
+    let redemptions = [(&bear, keys.no_mint, 50_000), (&bull, keys.yes_mint, 600_000), (&bear, keys.no_mint, 50_000)];
+    for (holder, mint, amount) in redemptions {
+        let rate = if mint == keys.yes_mint { rates[0] } else { rates[1] };
+        let before = env.balance(&holder.collateral).await;
+        env.send(vec![keys.redeem_ix(holder, mint, amount)], &[&holder.keypair]).await.unwrap();
+        assert_eq!(env.balance(&holder.collateral).await, before + refund(amount, rate));
+        let market = env.market(&keys.market).await;
+        assert_eq!([market.canceled_yes_refund_rate, market.canceled_no_refund_rate], [rates[0], rates[1]]);
+    }
+
+    // What is left after every holder redeemed still covers the liquidity withdrawal.
+    let vault = env.balance(&keys.collateral_vault).await;
+    env.send(vec![keys.withdraw_remaining_ix(&creator, 2 * INITIAL_LIQUIDITY)], &[&creator.keypair]).await.unwrap();
+    assert_eq!(env.balance(&keys.collateral_vault).await, 0);
+    assert!(vault > 0);
+}
+
+#[tokio::test]
+async fn canceled_categorical_markets_snapshot_every_outcome_price() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let now = env.now().await;
+    let keys = CategoricalKeys::new(&creator.pubkey(), 0, env.collateral_mint);
+    let args = instruction::CreateCategoricalMarket {
+        market_id: 0,
+        question: "Who wins the league?".to_string(),
+        end_timestamp: now + DAY,
+        trading_close_timestamp: None,
+        resolution_source: creator.pubkey(),
+        resolver: None,
+        outcome_count: 3,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+    };
+    let mut instructions = vec![keys.create_ix(&creator, args)];
+    instructions.extend((0..3).map(|index| keys.init_outcome_mint_ix(&creator.pubkey(), index)));
+    env.send(instructions, &[&creator.keypair]).await.unwrap();
+
+    let mint = outcome_mint_pda(&keys.market, 2);
+    env.create_atas(&trader.pubkey(), &[mint]).await;
+    env.send(vec![keys.buy_ix(&trader, 2, u64::MAX, 400_000)], &[&trader.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    let snapshot = math::refund_rates(&market.shares(), market.liquidity_parameter).unwrap();
+
+    let cancel = MarketKeys::new(&creator.pubkey(), 0, env.collateral_mint).manage_ix(&creator.pubkey(), instruction::CancelMarket {});
+    env.send(vec![cancel], &[&creator.keypair]).await.unwrap();
+    let rates = env.market(&keys.market).await.canceled_outcome_refund_rates;
+    assert_eq!(rates, snapshot);
+    assert!(rates[2] > rates[0] && rates[0] == rates[1]);
+    assert!(rates.iter().sum::<u64>() <= prediction_market::REFUND_RATE_PRECISION);
+
+    let before = env.balance(&trader.collateral).await;
+    env.send(vec![keys.redeem_ix(&trader, 2, 400_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.collateral).await, before + 400_000 * rates[2] / prediction_market::REFUND_RATE_PRECISION);
+}
+
+#[tokio::test]
+async fn paused_markets_resume_trading_once_unpaused() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::PauseMarket {})], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.paused);
+    env.send(vec![keys.manage_ix(&creator.pubkey(), instruction::UnpauseMarket {})], &[&creator.keypair]).await.unwrap();
+    env.send(vec![keys.buy_ix(&trader, ShareOutcome::No, u64::MAX, 1_000)], &[&trader.keypair]).await.unwrap();
+    assert_eq!(env.balance(&trader.ata(&keys.no_mint)).await, 1_000);
+}
+
+#[tokio::test]
+async fn emergency_resolution_opens_after_the_emergency_period() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let feed = Pubkey::new_unique();
+    let now = env.now().await;
+    let keys = env.create_market(&creator, oracle_market_args(0, now, DAY, feed, 100)).await.unwrap();
+
+    env.warp(DAY + prediction_market::EMERGENCY_PERIOD + 1).await;
+    env.send(vec![keys.emergency_ix(&creator.pubkey(), WinningOutcome::No)], &[&creator.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::No);
+}
+
+#[tokio::test]
+async fn undisputed_proposals_finalize_and_return_the_bond() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let proposer = env.new_user(1_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    let arbiter = Pubkey::new_unique();
+    env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), arbiter, 100_000, 3_600)], &[&creator.keypair]).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&proposer, instruction::ProposeResolution { outcome: WinningOutcome::No })], &[&proposer.keypair]).await.unwrap();
+    assert_eq!(env.balance(&proposer.collateral).await, 900_000);
+
+    env.warp(3_601).await;
+    env.send(vec![keys.settle_ix(&proposer.pubkey(), &proposer, instruction::FinalizeResolution {})], &[&proposer.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::No);
+    assert_eq!(env.balance(&proposer.collateral).await, 1_000_000);
+}
+
+#[tokio::test]
+async fn disputed_proposals_are_settled_by_the_arbiter() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let proposer = env.new_user(1_000_000).await;
+    let disputer = env.new_user(1_000_000).await;
+    let arbiter = env.new_user(0).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), arbiter.pubkey(), 100_000, 3_600)], &[&creator.keypair]).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&proposer, instruction::ProposeResolution { outcome: WinningOutcome::No })], &[&proposer.keypair]).await.unwrap();
+    env.send(vec![keys.bond_ix(&disputer, instruction::DisputeResolution {})], &[&disputer.keypair]).await.unwrap();
+
+    let arbitrate = keys.settle_ix(&arbiter.pubkey(), &disputer, instruction::ArbitrateResolution { outcome: WinningOutcome::Yes });
+    env.send(vec![arbitrate], &[&arbiter.keypair]).await.unwrap();
+    assert!(env.market(&keys.market).await.winning_outcome == WinningOutcome::Yes);
+    assert_eq!(env.balance(&disputer.collateral).await, 1_100_000);
+    assert_eq!(env.balance(&proposer.collateral).await, 900_000);
+}
+
+#[tokio::test]
+async fn disputes_the_arbiter_never_settles_cancel_and_return_both_bonds() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let proposer = env.new_user(1_000_000).await;
+    let disputer = env.new_user(1_000_000).await;
+    let trader = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &trader).await;
+    env.send(vec![keys.mint_set_ix(&trader, 100_000)], &[&trader.keypair]).await.unwrap();
+    env.send(vec![keys.configure_dispute_ix(&creator.pubkey(), Pubkey::new_unique(), 100_000, 3_600)], &[&creator.keypair]).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.bond_ix(&proposer, instruction::ProposeResolution { outcome: WinningOutcome::No })], &[&proposer.keypair]).await.unwrap();
+    env.send(vec![keys.bond_ix(&disputer, instruction::DisputeResolution {})], &[&disputer.keypair]).await.unwrap();
+
+    // The arbiter never shows up; once its deadline passes anyone can call the market off.
+    env.warp(prediction_market::ARBITRATION_PERIOD + 1).await;
+    env.send(vec![keys.expire_dispute_ix(&trader.pubkey(), &proposer, &disputer)], &[&trader.keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    assert!(market.resolved);
+    assert!(market.winning_outcome == WinningOutcome::Canceled);
+    assert_eq!(env.balance(&proposer.collateral).await, 1_000_000);
+    assert_eq!(env.balance(&disputer.collateral).await, 1_000_000);
+    assert_eq!(env.balance(&seeded(b"bond_vault", &keys.market)).await, 0);
+
+    env.send(
+        vec![keys.redeem_ix(&trader, keys.yes_mint, 100_000), keys.redeem_ix(&trader, keys.no_mint, 100_000)],
+        &[&trader.keypair],
+    )
+    .await
+    .unwrap();
+    assert!(env.balance(&trader.collateral).await >= 10_000_000 - 2);
+}
+
+#[tokio::test]
+async fn committees_resolve_once_the_threshold_agrees() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let members: Vec<User> = vec![env.new_user(0).await, env.new_user(0).await, env.new_user(0).await];
+    let now = env.now().await;
+    let args = instruction::CreateMarket {
+        resolver: Some(ResolverConfig::Committee {
+            members: members.iter().map(User::pubkey).collect(),
+            threshold: 2,
+        }),
+        ..manual_market_args(0, now, DAY, creator.pubkey())
+    };
+    let keys = env.create_market(&creator, args).await.unwrap();
+
+    env.warp(DAY + 1).await;
+    env.send(vec![keys.vote_ix(&members[0].pubkey(), WinningOutcome::Yes)], &[&members[0].keypair]).await.unwrap();
+    env.send(vec![keys.vote_ix(&members[1].pubkey(), WinningOutcome::No)], &[&members[1].keypair]).await.unwrap();
+    assert!(!env.market(&keys.market).await.resolved);
+
+    env.send(vec![keys.vote_ix(&members[2].pubkey(), WinningOutcome::No)], &[&members[2].keypair]).await.unwrap();
+    let market = env.market(&keys.market).await;
+    assert!(market.resolved);
+    assert!(market.winning_outcome == WinningOutcome::No);
+}
+
+#[tokio::test]
+async fn resting_orders_fill_ahead_of_the_amm_and_cancel_with_refunds() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let maker = env.new_user(20_000_000).await;
+    let taker = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &maker).await;
+    env.open_positions(&keys, &taker).await;
+    env.send(vec![keys.init_order_book_ix(&creator.pubkey())], &[&creator.keypair]).await.unwrap();
+
+    env.send(vec![keys.mint_set_ix(&maker, 5_000_000)], &[&maker.keypair]).await.unwrap();
+    env.send(vec![keys.place_order_ix(&maker, ShareOutcome::Yes, OrderSide::Ask, 400, 5_000_000)], &[&maker.keypair]).await.unwrap();
+    env.send(vec![keys.place_order_ix(&maker, ShareOutcome::No, OrderSide::Bid, 300, 4_000_000)], &[&maker.keypair]).await.unwrap();
+    assert_eq!(env.order_book(&keys.market).await.orders.len(), 2);
+
+    env.send(vec![keys.buy_with_book_ix(&taker, ShareOutcome::Yes, u64::MAX, 3_000_000, &[])], &[&taker.keypair]).await.unwrap();
+    assert_eq!(env.balance(&taker.ata(&keys.yes_mint)).await, 3_000_000);
+    assert_eq!(env.balance(&taker.collateral).await, 10_000_000 - 1_200_000);
+    // The whole fill came from the book, so only the maker's complete sets are outstanding.
+    assert_eq!(env.market(&keys.market).await.yes_shares_outstanding, 5_000_000);
+
+    let maker_collateral = env.balance(&maker.collateral).await;
+    env.send(vec![keys.cancel_order_ix(&maker, ShareOutcome::Yes, 0)], &[&maker.keypair]).await.unwrap();
+    assert_eq!(env.balance(&maker.collateral).await, maker_collateral + 1_200_000);
+    assert_eq!(env.balance(&maker.ata(&keys.yes_mint)).await, 2_000_000);
+
+    env.send(vec![keys.cancel_order_ix(&maker, ShareOutcome::No, 1)], &[&maker.keypair]).await.unwrap();
+    assert_eq!(env.balance(&maker.collateral).await, maker_collateral + 1_200_000 + 1_200_000);
+    assert!(env.order_book(&keys.market).await.orders.is_empty());
+}
+
+#[tokio::test]
+async fn filled_orders_pay_out_and_free_their_book_slots() {
+    let mut env = TestEnv::new().await;
+    let creator = env.new_user(10_000_000).await;
+    let maker = env.new_user(40_000_000).await;
+    let taker = env.new_user(10_000_000).await;
+    let keys = env.create_manual_market(&creator, 0, DAY).await;
+    env.open_positions(&keys, &maker).await;
+    env.open_positions(&keys, &taker).await;
+    env.send(vec![keys.init_order_book_ix(&creator.pubkey())], &[&creator.keypair]).await.unwrap();
+
+    for _ in 0..prediction_market::MAX_ORDERS / 8 {
+        let bids = (0..8).map(|_| keys.place_order_ix(&maker, ShareOutcome::No, OrderSide::Bid, 500, 2_000_000)).collect();
+        env.send(bids, &[&maker.keypair]).await.unwrap();
+    }
+    let result = env.send(vec![keys.place_order_ix(&maker, ShareOutcome::No, OrderSide::Bid, 500, 2_000_000)], &[&maker.keypair]).await;
+    assert_market_error(result, MarketError::OrderBookFull);
+
+    // Filling the oldest bid completely needs its owner's payout accounts.
+    env.send(vec![keys.mint_set_ix(&taker, 2_000_000)], &[&taker.keypair]).await.unwrap();
+    let result = env.send(vec![keys.sell_with_book_ix(&taker, ShareOutcome::No, 2_000_000, 0, &[])], &[&taker.keypair]).await;
+    assert_market_error(result, MarketError::InvalidTokenAccount);
+
+    let maker_collateral = env.balance(&maker.collateral).await;
+    env.send(vec![keys.sell_with_book_ix(&taker, ShareOutcome::No, 2_000_000, 0, &[&maker])], &[&taker.keypair]).await.unwrap();
+    assert_eq!(env.balance(&taker.collateral).await, 10_000_000 - 2_000_000 + 1_000_000);
+    assert_eq!(env.balance(&maker.ata(&keys.no_mint)).await, 2_000_000);
+    assert_eq!(env.balance(&maker.collateral).await, maker_collateral);
+    let book = env.order_book(&keys.market).await;
+    assert_eq!(book.orders.len(), prediction_market::MAX_ORDERS - 1);
+    assert!(book.orders.iter().all(|order| order.id != 0));
+
+    env.send(vec![keys.place_order_ix(&maker, ShareOutcome::No, OrderSide::Bid, 500, 2_000_000)], &[&maker.keypair]).await.unwrap();
+    assert_eq!(env.order_book(&keys.market).await.orders.len(), prediction_market::MAX_ORDERS);
+}
+
+#[tokio::test]
+async fn config_admin_can_tune_pause_and_hand_over() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.insecure_clone();
+    let new_admin = env.new_user(0).await;
+    let update = |data: Vec<u8>, signer: Pubkey| solana_sdk::instruction::Instruction {
+        program_id: prediction_market::ID,
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&prediction_market::accounts::UpdateConfig { config: config_pda(), admin: signer }, None),
+        data,
+    };
+
+    let extra_mint = env.create_mint().await;
+    env.send(vec![update(instruction::AddCollateralMint { mint: extra_mint }.data(), admin.pubkey())], &[]).await.unwrap();
+    assert!(env.config().await.allowed_collateral_mints.contains(&extra_mint));
+    env.send(vec![update(instruction::RemoveCollateralMint { mint: extra_mint }.data(), admin.pubkey())], &[]).await.unwrap();
+    assert!(!env.config().await.allowed_collateral_mints.contains(&extra_mint));
+
+    let collateral_mint = env.collateral_mint;
+    let tune = instruction::UpdateConfig {
+        treasury: new_admin.pubkey(),
+        default_fee_bps: 50,
+        allowed_collateral_mints: vec![collateral_mint],
+        min_market_duration: 60,
+        max_market_duration: 30 * DAY,
+    };
+    env.send(vec![update(tune.data(), admin.pubkey())], &[]).await.unwrap();
+    let config = env.config().await;
+    assert_eq!(config.default_fee_bps, 50);
+    assert_eq!(config.treasury, new_admin.pubkey());
+
+    env.send(vec![update(instruction::SetPaused { paused: true }.data(), admin.pubkey())], &[]).await.unwrap();
+    assert!(env.config().await.paused);
+    env.send(vec![update(instruction::SetPaused { paused: false }.data(), admin.pubkey())], &[]).await.unwrap();
+
+    env.send(vec![update(instruction::ProposeAdmin { new_admin: new_admin.pubkey() }.data(), admin.pubkey())], &[]).await.unwrap();
+    let accept = solana_sdk::instruction::Instruction {
+        program_id: prediction_market::ID,
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(
+            &prediction_market::accounts::AcceptAdmin { config: config_pda(), new_admin: new_admin.pubkey() },
+            None,
+        ),
+        data: instruction::AcceptAdmin {}.data(),
+    };
+    env.send(vec![accept], &[&new_admin.keypair]).await.unwrap();
+    let config = env.config().await;
+    assert_eq!(config.admin, new_admin.pubkey());
+    assert_eq!(config.pending_admin, None);
+}
@@ -0,0 +1,236 @@
+//! Property tests for the pricing math in `prediction_market::math`, run on the
+//! host against arbitrary share vectors, depths and trade sizes.
+
+use prediction_market::math::{buy_cost, calculate_fees, first_reaching, lmsr_cost, lmsr_price, lmsr_prices, price, prices, refund_rates, sell_payout, shares_for_cost, shares_for_payout};
+use prediction_market::{MarketError, FP_ONE, MAX_PRICE, MAX_SEARCH_EVALUATIONS, MIN_PRICE, PRICE_PRECISION, REFUND_RATE_PRECISION};
+use proptest::prelude::*;
+
+/// Slack for comparing fixed-point prices, far below anything a quote can show.
+const PRICE_TOLERANCE: u128 = 1_000_000;
+
+fn depth() -> impl Strategy<Value = u64> {
+    1_000u64..1_000_000_000_000
+}
+
+fn book(max_shares: u64) -> impl Strategy<Value = Vec<u64>> {
+    prop::collection::vec(0..max_shares, 2..=16)
+}
+
+#[derive(Debug, Clone)]
+enum Trade {
+    Buy { index: usize, amount: u64 },
+    Sell { index: usize, amount: u64 },
+}
+
+fn trade() -> impl Strategy<Value = Trade> {
+    prop_oneof![
+        (0usize..16, 1u64..10_000_000_000).prop_map(|(index, amount)| Trade::Buy { index, amount }),
+        (0usize..16, 1u64..10_000_000_000).prop_map(|(index, amount)| Trade::Sell { index, amount }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn never_panics_on_arbitrary_input(
+        shares in prop::collection::vec(any::<u64>(), 0..=16),
+        b in any::<u64>(),
+        index in 0usize..20,
+        amount in any::<u64>(),
+        fee_bps in any::<u16>(),
+    ) {
+        let _ = lmsr_cost(&shares, b);
+        let _ = lmsr_price(&shares, b, index);
+        let _ = price(&shares, b, index);
+        let _ = prices(&shares, b);
+        let _ = buy_cost(&shares, b, index, amount);
+        let _ = sell_payout(&shares, b, index, amount);
+        let _ = refund_rates(&shares, b);
+        let _ = calculate_fees(amount, fee_bps);
+        let _ = shares_for_cost(&shares, b, index, amount);
+        let _ = shares_for_payout(&shares, b, index, amount);
+    }
+
+    #[test]
+    fn quoted_prices_stay_in_bounds(shares in book(u64::MAX / 2), b in depth(), index in 0usize..16) {
+        prop_assume!(index < shares.len());
+        let quote = price(&shares, b, index).unwrap();
+        prop_assert!((MIN_PRICE..=MAX_PRICE).contains(&quote));
+    }
+
+    #[test]
+    fn all_prices_match_the_per_outcome_quotes(shares in book(1_000_000_000_000), b in depth()) {
+        let each: Vec<u128> = (0..shares.len()).map(|index| lmsr_price(&shares, b, index).unwrap()).collect();
+        prop_assert_eq!(lmsr_prices(&shares, b).unwrap(), each);
+        let quotes: Vec<u64> = (0..shares.len()).map(|index| price(&shares, b, index).unwrap()).collect();
+        prop_assert_eq!(prices(&shares, b).unwrap(), quotes);
+    }
+
+    #[test]
+    fn prices_sum_to_at_most_one(shares in book(1_000_000_000_000), b in depth()) {
+        let total: u128 = (0..shares.len()).map(|index| lmsr_price(&shares, b, index).unwrap()).sum();
+        prop_assert!(total <= FP_ONE);
+        prop_assert!(total + PRICE_TOLERANCE * shares.len() as u128 >= FP_ONE);
+
+        let rates: u64 = refund_rates(&shares, b).unwrap().iter().sum();
+        prop_assert!(rates <= REFUND_RATE_PRECISION);
+    }
+
+    #[test]
+    fn buying_raises_the_price_and_lowers_the_rest(
+        shares in book(1_000_000_000_000),
+        b in depth(),
+        index in 0usize..16,
+        amount in 1u64..1_000_000_000_000,
+    ) {
+        prop_assume!(index < shares.len());
+        let mut after = shares.clone();
+        after[index] += amount;
+
+        for outcome in 0..shares.len() {
+            let before_price = lmsr_price(&shares, b, outcome).unwrap();
+            let after_price = lmsr_price(&after, b, outcome).unwrap();
+            if outcome == index {
+                prop_assert!(after_price + PRICE_TOLERANCE >= before_price, "price of {outcome} fell after buying it");
+            } else {
+                prop_assert!(after_price <= before_price + PRICE_TOLERANCE, "price of {outcome} rose after buying {index}");
+            }
+        }
+    }
+
+    #[test]
+    fn cost_grows_with_trade_size(
+        shares in book(1_000_000_000_000),
+        b in depth(),
+        index in 0usize..16,
+        small in 1u64..1_000_000_000,
+        extra in 0u64..1_000_000_000,
+    ) {
+        prop_assume!(index < shares.len());
+        let small_cost = buy_cost(&shares, b, index, small).unwrap();
+        let large_cost = buy_cost(&shares, b, index, small + extra).unwrap();
+        prop_assert!(small_cost <= large_cost);
+        // No share ever costs more than a full unit of collateral.
+        prop_assert!(large_cost <= small + extra + 1);
+    }
+
+    #[test]
+    fn buy_then_sell_never_profits(
+        shares in book(1_000_000_000_000),
+        b in depth(),
+        index in 0usize..16,
+        amount in 1u64..1_000_000_000_000,
+        fee_bps in 0u16..=1_000,
+    ) {
+        prop_assume!(index < shares.len());
+        let cost = buy_cost(&shares, b, index, amount).unwrap();
+        let (buy_creator_fee, buy_protocol_fee) = calculate_fees(cost, fee_bps).unwrap();
+
+        let mut after = shares.clone();
+        after[index] += amount;
+        let payout = sell_payout(&after, b, index, amount).unwrap();
+        let (sell_creator_fee, sell_protocol_fee) = calculate_fees(payout, fee_bps).unwrap();
+
+        prop_assert!(payout <= cost);
+        let paid = cost + buy_creator_fee + buy_protocol_fee;
+        let received = payout - sell_creator_fee - sell_protocol_fee;
+        prop_assert!(received <= paid);
+    }
+
+    #[test]
+    fn share_estimates_invert_the_cost_curve(
+        shares in book(1_000_000_000_000),
+        b in depth(),
+        index in 0usize..16,
+        amount in 1u64..1_000_000_000_000,
+    ) {
+        prop_assume!(index < shares.len());
+        let mut after = shares.clone();
+        after[index] += amount;
+        // Outside the quotable range a share costs next to nothing and the fixed-point
+        // estimate degrades; the instructions' search absorbs that error.
+        let quotable = |price: u128| (MIN_PRICE as u128 * FP_ONE / PRICE_PRECISION as u128..=MAX_PRICE as u128 * FP_ONE / PRICE_PRECISION as u128).contains(&price);
+        prop_assume!(quotable(lmsr_price(&shares, b, index).unwrap()) && quotable(lmsr_price(&after, b, index).unwrap()));
+
+        let cost = buy_cost(&shares, b, index, amount).unwrap();
+        let bought = shares_for_cost(&shares, b, index, cost).unwrap();
+        // Within a share of the largest count that `cost` pays for.
+        prop_assert!(buy_cost(&shares, b, index, bought.saturating_sub(1)).unwrap() <= cost);
+        prop_assert!(buy_cost(&shares, b, index, bought + 2).unwrap() > cost);
+
+        let payout = sell_payout(&after, b, index, amount).unwrap();
+        let sold = shares_for_payout(&after, b, index, payout).unwrap();
+        // Within a share of the smallest count that pays `payout`.
+        prop_assert!(sell_payout(&after, b, index, (sold + 1).min(after[index])).unwrap() >= payout);
+        prop_assert!(sold < 2 || sell_payout(&after, b, index, sold - 2).unwrap() < payout);
+    }
+
+    #[test]
+    fn search_finds_the_first_point_reached(lo in 0u64..1_000, span in 0u64..1_000_000, threshold in 0u64..1_002_000, guess in any::<u64>()) {
+        let hi = lo + span;
+        let mut evaluations = 0;
+        let found = first_reaching(lo, hi, guess, |x| {
+            evaluations += 1;
+            Ok(x >= threshold)
+        });
+        let expected = (threshold <= hi).then(|| threshold.max(lo));
+        // A far-off guess may run out of evaluations, but never answers wrongly.
+        prop_assert!(evaluations <= MAX_SEARCH_EVALUATIONS);
+        match found {
+            Ok(found) => prop_assert_eq!(found, expected),
+            Err(err) => prop_assert_eq!(err, MarketError::SearchLimitExceeded.into()),
+        }
+    }
+
+    #[test]
+    fn search_from_a_close_guess_stays_within_the_limit(
+        lo in 0u64..1_000,
+        span in 0u64..1_000_000,
+        threshold in 0u64..1_002_000,
+        offset in 0u64..2_048,
+        above in any::<bool>(),
+    ) {
+        let hi = lo + span;
+        let expected = (threshold <= hi).then(|| threshold.max(lo));
+        let answer = expected.unwrap_or(hi);
+        let guess = if above { answer + offset } else { answer.saturating_sub(offset) };
+        let found = first_reaching(lo, hi, guess, |x| Ok(x >= threshold)).unwrap();
+        prop_assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn cost_covers_the_largest_position(shares in book(u64::MAX / 2), b in depth()) {
+        let max_shares = *shares.iter().max().unwrap();
+        prop_assert!(lmsr_cost(&shares, b).unwrap() >= max_shares as u128 * FP_ONE);
+    }
+
+    #[test]
+    fn vault_covers_worst_case_payout(
+        b in depth(),
+        outcomes in 2usize..=16,
+        trades in prop::collection::vec(trade(), 1..64),
+    ) {
+        let mut shares = vec![0u64; outcomes];
+        let mut vault = lmsr_cost(&shares, b).unwrap().div_ceil(FP_ONE) as u64;
+
+        for trade in trades {
+            match trade {
+                Trade::Buy { index, amount } => {
+                    let index = index % outcomes;
+                    vault += buy_cost(&shares, b, index, amount).unwrap();
+                    shares[index] += amount;
+                },
+                Trade::Sell { index, amount } => {
+                    let index = index % outcomes;
+                    let amount = amount.min(shares[index]);
+                    if amount == 0 {
+                        continue;
+                    }
+                    vault -= sell_payout(&shares, b, index, amount).unwrap();
+                    shares[index] -= amount;
+                },
+            }
+            let worst_case = *shares.iter().max().unwrap();
+            prop_assert!(vault >= worst_case, "vault {vault} cannot cover {worst_case} winning shares");
+        }
+    }
+}
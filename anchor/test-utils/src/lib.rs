@@ -0,0 +1,1180 @@
+//! In-process test harness: runs the program natively inside a
+//! `solana-program-test` bank next to the real SPL token programs. Shared by
+//! the program's and the client's test suites.
+
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Event, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::spl_token_2022;
+use anchor_spl::token_interface::spl_token_2022::extension::{transfer_fee::instruction as transfer_fee_instruction, ExtensionType, StateWithExtensions};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use prediction_market::{
+    accounts, instruction, FeeRecipient, Market, MarketError, MarketType, OracleComparison, OrderBook,
+    ResolverConfig, ShareOutcome, WinningOutcome,
+};
+use pyth_sdk_solana::state::{AccountType, PriceStatus, SolanaPriceAccount, MAGIC, VERSION_2};
+use solana_program_test::{processor, BanksClientError, BanksTransactionResultWithMetadata, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::clock::Clock;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::program_stubs;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::sync::{Once, OnceLock};
+
+/// Decodes every `E` the program emitted into `logs`. Anchor logs an event as
+/// `Program data: <base64>` of its discriminator followed by the borsh body;
+/// natively the line goes through `sol_log` and so also gets its prefix.
+pub fn events<E: Event + AnchorDeserialize>(logs: &[String]) -> Vec<E> {
+    logs.iter()
+        .map(|line| line.strip_prefix("Program log: ").unwrap_or(line))
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .filter_map(|data| BASE64_STANDARD.decode(data).ok())
+        .filter_map(|bytes| bytes.strip_prefix(E::DISCRIMINATOR).map(|body| E::deserialize(&mut &body[..]).unwrap()))
+        .collect()
+}
+
+/// `solana-program-test` prints `sol_log_data` of native programs to stdout
+/// instead of the transaction logs. Wrap its syscall stubs once so events are
+/// logged as `Program data: ...`, the line they produce on chain.
+fn log_event_data() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let inner = program_stubs::set_syscall_stubs(Box::new(LogDataStubs));
+        let _ = PROGRAM_TEST_STUBS.set(inner);
+    });
+}
+
+static PROGRAM_TEST_STUBS: OnceLock<Box<dyn program_stubs::SyscallStubs>> = OnceLock::new();
+
+/// Forwards to `solana-program-test`'s stubs, waiting out the swap if a
+/// parallel test calls in while they are being replaced.
+struct LogDataStubs;
+
+impl LogDataStubs {
+    fn inner(&self) -> &dyn program_stubs::SyscallStubs {
+        PROGRAM_TEST_STUBS.wait().as_ref()
+    }
+}
+
+impl program_stubs::SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.inner().sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.inner().sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.inner().sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        self.inner().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_sysvar(&self, sysvar_id_addr: *const u8, var_addr: *mut u8, offset: u64, length: u64) -> u64 {
+        self.inner().sol_get_sysvar(sysvar_id_addr, var_addr, offset, length)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_epoch_stake(&self, vote_address: *const u8) -> u64 {
+        self.inner().sol_get_epoch_stake(vote_address)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.inner().sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.inner().sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.inner().sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.inner().sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.inner().sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.inner().sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| BASE64_STANDARD.encode(field)).collect();
+        self.inner().sol_log(&format!("Program data: {}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.inner().sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.inner().sol_get_stack_height()
+    }
+}
+
+pub type TxResult = Result<(), BanksClientError>;
+
+pub const DECIMALS: u8 = 6;
+pub const DEFAULT_FEE_BPS: u16 = 100;
+pub const DAY: i64 = 86_400;
+pub const INITIAL_LIQUIDITY: u64 = 1_000_000;
+pub const LIQUIDITY_PARAMETER: u64 = 1_000_000;
+
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // Anchor's entrypoint ties the slice and the accounts to one lifetime.
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    prediction_market::entry(program_id, accounts, data)
+}
+
+pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &prediction_market::ID).0
+}
+
+pub fn market_pda(creator: &Pubkey, market_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"market", creator.as_ref(), &market_id.to_le_bytes()], &prediction_market::ID).0
+}
+
+pub fn seeded(seed: &[u8], market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seed, market.as_ref()], &prediction_market::ID).0
+}
+
+pub fn outcome_mint_pda(market: &Pubkey, index: u8) -> Pubkey {
+    Pubkey::find_program_address(&[b"outcome_mint", market.as_ref(), &[index]], &prediction_market::ID).0
+}
+
+/// Asserts that a transaction failed with the given program error.
+pub fn assert_market_error(result: TxResult, error: MarketError) {
+    let expected = u32::from(error);
+    match result {
+        Err(err) => match err.unwrap() {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                assert_eq!(code, expected, "expected error {expected}, got {code}")
+            },
+            other => panic!("expected error {expected}, got {other:?}"),
+        },
+        Ok(()) => panic!("expected error {expected}, transaction succeeded"),
+    }
+}
+
+pub struct User {
+    pub keypair: Keypair,
+    pub collateral: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl User {
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn ata(&self, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(&self.pubkey(), mint, &self.token_program)
+    }
+}
+
+/// Every address of a two-outcome market, all derived from creator and id.
+/// Markets use the SPL token program unless `token_program` is overridden.
+#[derive(Clone, Copy)]
+pub struct MarketKeys {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub yes_mint: Pubkey,
+    pub no_mint: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub creator_fee_vault: Pubkey,
+    pub protocol_fee_vault: Pubkey,
+    pub lp_mint: Pubkey,
+}
+
+impl MarketKeys {
+    pub fn new(creator: &Pubkey, market_id: u64, collateral_mint: Pubkey) -> Self {
+        let market = market_pda(creator, market_id);
+        Self {
+            market,
+            authority: seeded(b"authority", &market),
+            collateral_mint,
+            token_program: spl_token::ID,
+            yes_mint: seeded(b"yes_mint", &market),
+            no_mint: seeded(b"no_mint", &market),
+            collateral_vault: seeded(b"collateral_vault", &market),
+            creator_fee_vault: seeded(b"creator_fees", &market),
+            protocol_fee_vault: seeded(b"protocol_fees", &market),
+            lp_mint: seeded(b"lp_mint", &market),
+        }
+    }
+
+    pub fn share_mint(&self, outcome: &ShareOutcome) -> Pubkey {
+        match outcome {
+            ShareOutcome::Yes => self.yes_mint,
+            ShareOutcome::No => self.no_mint,
+        }
+    }
+
+    fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: prediction_market::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    pub fn create_ix(&self, creator: &User, args: instruction::CreateMarket) -> Instruction {
+        Self::ix(self.create_accounts(creator), args)
+    }
+
+    pub fn create_scalar_ix(&self, creator: &User, args: instruction::CreateScalarMarket) -> Instruction {
+        Self::ix(self.create_accounts(creator), args)
+    }
+
+    fn create_accounts(&self, creator: &User) -> accounts::CreateMarket {
+        accounts::CreateMarket {
+            config: config_pda(),
+            creator: creator.pubkey(),
+            market: self.market,
+            collateral_mint: self.collateral_mint,
+            yes_token_mint: self.yes_mint,
+            no_token_mint: self.no_mint,
+            market_authority: self.authority,
+            collateral_vault: self.collateral_vault,
+            creator_fee_vault: self.creator_fee_vault,
+            protocol_fee_vault: self.protocol_fee_vault,
+            lp_token_mint: self.lp_mint,
+            creator_lp_token_account: creator.ata(&self.lp_mint),
+            creator_collateral_account: creator.collateral,
+            system_program: solana_sdk::system_program::ID,
+            token_program: self.token_program,
+            associated_token_program: spl_associated_token_account::ID,
+            rent: solana_sdk::sysvar::rent::ID,
+        }
+    }
+
+    pub fn buy_ix(&self, user: &User, outcome: ShareOutcome, max_cost: u64, shares_desired: u64) -> Instruction {
+        self.buy_through_ix(user, outcome, max_cost, shares_desired, false)
+    }
+
+    /// `makers` own the resting orders the buy fills completely, in fill order.
+    pub fn buy_with_book_ix(&self, user: &User, outcome: ShareOutcome, max_cost: u64, shares_desired: u64, makers: &[&User]) -> Instruction {
+        let mut ix = self.buy_through_ix(user, outcome.clone(), max_cost, shares_desired, true);
+        ix.accounts.extend(self.maker_accounts(&outcome, makers));
+        ix
+    }
+
+    fn buy_through_ix(&self, user: &User, outcome: ShareOutcome, max_cost: u64, shares_desired: u64, book: bool) -> Instruction {
+        let (order_book, book_collateral_vault, book_share_vault) = self.book_accounts(&outcome, book);
+        Self::ix(
+            accounts::BuyShares {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: Some(user.ata(&self.yes_mint)),
+                user_no_token_account: Some(user.ata(&self.no_mint)),
+                collateral_vault: self.collateral_vault,
+                creator_fee_vault: self.creator_fee_vault,
+                protocol_fee_vault: self.protocol_fee_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                order_book,
+                book_collateral_vault,
+                book_share_vault,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::BuyShares { outcome, max_cost, shares_desired },
+        )
+    }
+
+    pub fn sell_ix(&self, user: &User, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64) -> Instruction {
+        self.sell_through_ix(user, outcome, shares_to_sell, min_payout, false)
+    }
+
+    /// `makers` own the resting orders the sale fills completely, in fill order.
+    pub fn sell_with_book_ix(&self, user: &User, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64, makers: &[&User]) -> Instruction {
+        let mut ix = self.sell_through_ix(user, outcome.clone(), shares_to_sell, min_payout, true);
+        ix.accounts.extend(self.maker_accounts(&outcome, makers));
+        ix
+    }
+
+    fn sell_through_ix(&self, user: &User, outcome: ShareOutcome, shares_to_sell: u64, min_payout: u64, book: bool) -> Instruction {
+        let (order_book, book_collateral_vault, book_share_vault) = self.book_accounts(&outcome, book);
+        Self::ix(
+            accounts::SellShares {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: user.ata(&self.yes_mint),
+                user_no_token_account: user.ata(&self.no_mint),
+                collateral_vault: self.collateral_vault,
+                creator_fee_vault: self.creator_fee_vault,
+                protocol_fee_vault: self.protocol_fee_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                order_book,
+                book_collateral_vault,
+                book_share_vault,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::SellShares { outcome, shares_to_sell, min_payout },
+        )
+    }
+
+    /// The payout accounts a book trade passes for each order it fills completely.
+    fn maker_accounts(&self, outcome: &ShareOutcome, makers: &[&User]) -> Vec<AccountMeta> {
+        let share_mint = self.share_mint(outcome);
+        makers
+            .iter()
+            .flat_map(|maker| [AccountMeta::new(maker.collateral, false), AccountMeta::new(maker.ata(&share_mint), false)])
+            .collect()
+    }
+
+    fn book_accounts(&self, outcome: &ShareOutcome, book: bool) -> (Option<Pubkey>, Option<Pubkey>, Option<Pubkey>) {
+        if !book {
+            return (None, None, None);
+        }
+        let share_vault = match outcome {
+            ShareOutcome::Yes => seeded(b"book_yes", &self.market),
+            ShareOutcome::No => seeded(b"book_no", &self.market),
+        };
+        (Some(seeded(b"order_book", &self.market)), Some(seeded(b"book_collateral", &self.market)), Some(share_vault))
+    }
+
+    pub fn mint_set_ix(&self, user: &User, amount: u64) -> Instruction {
+        Self::ix(
+            accounts::MintCompleteSet {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: user.ata(&self.yes_mint),
+                user_no_token_account: user.ata(&self.no_mint),
+                collateral_vault: self.collateral_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::MintCompleteSet { amount },
+        )
+    }
+
+    pub fn merge_set_ix(&self, user: &User, amount: u64) -> Instruction {
+        Self::ix(
+            accounts::MergeCompleteSet {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: user.ata(&self.yes_mint),
+                user_no_token_account: user.ata(&self.no_mint),
+                collateral_vault: self.collateral_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::MergeCompleteSet { amount },
+        )
+    }
+
+    pub fn add_liquidity_ix(&self, user: &User, amount: u64, min_lp_tokens: u64) -> Instruction {
+        Self::ix(
+            accounts::AddLiquidity {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: user.ata(&self.yes_mint),
+                user_no_token_account: user.ata(&self.no_mint),
+                user_lp_token_account: user.ata(&self.lp_mint),
+                collateral_vault: self.collateral_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                lp_token_mint: self.lp_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::AddLiquidity { amount, min_lp_tokens },
+        )
+    }
+
+    pub fn remove_liquidity_ix(&self, user: &User, lp_tokens: u64, min_collateral: u64) -> Instruction {
+        Self::ix(
+            accounts::RemoveLiquidity {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_yes_token_account: user.ata(&self.yes_mint),
+                user_no_token_account: user.ata(&self.no_mint),
+                user_lp_token_account: user.ata(&self.lp_mint),
+                collateral_vault: self.collateral_vault,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                lp_token_mint: self.lp_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::RemoveLiquidity { lp_tokens, min_collateral },
+        )
+    }
+
+    pub fn resolve_ix(&self, resolver: &Pubkey, resolution_source: Pubkey, manual_outcome: Option<WinningOutcome>) -> Instruction {
+        Self::ix(
+            accounts::ResolveMarket {
+                config: config_pda(),
+                market: self.market,
+                resolution_source,
+                resolver: *resolver,
+            },
+            instruction::ResolveMarket { manual_outcome },
+        )
+    }
+
+    pub fn emergency_ix(&self, resolver: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        Self::ix(
+            accounts::EmergencyResolveMarket {
+                config: config_pda(),
+                market: self.market,
+                resolver: *resolver,
+            },
+            instruction::EmergencyResolveMarket { outcome },
+        )
+    }
+
+    pub fn redeem_ix(&self, user: &User, winning_mint: Pubkey, amount: u64) -> Instruction {
+        Self::ix(
+            accounts::RedeemWinnings {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_winning_token_account: user.ata(&winning_mint),
+                collateral_vault: self.collateral_vault,
+                winning_token_mint: winning_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::RedeemWinnings { amount },
+        )
+    }
+
+    pub fn withdraw_remaining_ix(&self, user: &User, lp_tokens: u64) -> Instruction {
+        Self::ix(
+            accounts::WithdrawRemainingLiquidity {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_lp_token_account: user.ata(&self.lp_mint),
+                collateral_vault: self.collateral_vault,
+                yes_token_mint: Some(self.yes_mint),
+                no_token_mint: Some(self.no_mint),
+                lp_token_mint: self.lp_mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::WithdrawRemainingLiquidity { lp_tokens },
+        )
+    }
+
+    pub fn claim_fees_ix(&self, claimer: &Pubkey, recipient: FeeRecipient, destination: Pubkey) -> Instruction {
+        let fee_vault = match recipient {
+            FeeRecipient::Creator => self.creator_fee_vault,
+            FeeRecipient::Protocol => self.protocol_fee_vault,
+        };
+        Self::ix(
+            accounts::ClaimFees {
+                config: config_pda(),
+                market: self.market,
+                claimer: *claimer,
+                fee_vault,
+                destination,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::ClaimFees { recipient },
+        )
+    }
+
+    pub fn manage_ix(&self, creator: &Pubkey, data: impl InstructionData) -> Instruction {
+        Self::ix(
+            accounts::ManageMarket {
+                config: config_pda(),
+                market: self.market,
+                creator: *creator,
+            },
+            data,
+        )
+    }
+
+    pub fn vote_ix(&self, voter: &Pubkey, outcome: WinningOutcome) -> Instruction {
+        Self::ix(
+            accounts::VoteResolution {
+                config: config_pda(),
+                market: self.market,
+                voter: *voter,
+            },
+            instruction::VoteResolution { outcome },
+        )
+    }
+
+    pub fn price_ix(&self, outcome: ShareOutcome) -> Instruction {
+        Self::ix(accounts::GetMarketPrice { market: self.market }, instruction::GetMarketPrice { outcome })
+    }
+
+    pub fn buy_with_collateral_ix(&self, user: &User, outcome: ShareOutcome, collateral_in: u64, min_shares_out: u64) -> Instruction {
+        let accounts = self.buy_ix(user, outcome.clone(), 0, 0);
+        Instruction { data: instruction::BuyWithCollateral { outcome, collateral_in, min_shares_out }.data(), ..accounts }
+    }
+
+    pub fn sell_for_collateral_ix(&self, user: &User, outcome: ShareOutcome, collateral_out: u64, max_shares_in: u64) -> Instruction {
+        let accounts = self.sell_ix(user, outcome.clone(), 0, 0);
+        Instruction { data: instruction::SellForCollateral { outcome, collateral_out, max_shares_in }.data(), ..accounts }
+    }
+
+    pub fn quote_ix(&self, outcome: ShareOutcome, side: prediction_market::OrderSide, shares: u64) -> Instruction {
+        Self::ix(accounts::GetMarketPrice { market: self.market }, instruction::QuoteTrade { outcome, side, shares })
+    }
+
+    pub fn configure_dispute_ix(&self, creator: &Pubkey, arbiter: Pubkey, bond_amount: u64, dispute_window: i64) -> Instruction {
+        Self::ix(
+            accounts::ConfigureDispute {
+                config: config_pda(),
+                market: self.market,
+                creator: *creator,
+                collateral_mint: self.collateral_mint,
+                bond_vault: seeded(b"bond_vault", &self.market),
+                market_authority: self.authority,
+                system_program: solana_sdk::system_program::ID,
+                token_program: self.token_program,
+                rent: solana_sdk::sysvar::rent::ID,
+            },
+            instruction::ConfigureDispute { arbiter, bond_amount, dispute_window },
+        )
+    }
+
+    pub fn bond_ix(&self, bonder: &User, data: impl InstructionData) -> Instruction {
+        Self::ix(
+            accounts::PostBond {
+                config: config_pda(),
+                market: self.market,
+                bonder: bonder.pubkey(),
+                bonder_collateral_account: bonder.collateral,
+                bond_vault: seeded(b"bond_vault", &self.market),
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            data,
+        )
+    }
+
+    pub fn settle_ix(&self, resolver: &Pubkey, winner: &User, data: impl InstructionData) -> Instruction {
+        Self::ix(
+            accounts::SettleResolution {
+                config: config_pda(),
+                market: self.market,
+                resolver: *resolver,
+                winner_collateral_account: winner.collateral,
+                bond_vault: seeded(b"bond_vault", &self.market),
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            data,
+        )
+    }
+
+    pub fn expire_dispute_ix(&self, caller: &Pubkey, proposer: &User, disputer: &User) -> Instruction {
+        Self::ix(
+            accounts::ExpireDispute {
+                config: config_pda(),
+                market: self.market,
+                caller: *caller,
+                proposer_collateral_account: proposer.collateral,
+                disputer_collateral_account: disputer.collateral,
+                bond_vault: seeded(b"bond_vault", &self.market),
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: self.token_program,
+            },
+            instruction::ExpireDispute {},
+        )
+    }
+
+    pub fn init_order_book_ix(&self, payer: &Pubkey) -> Instruction {
+        Self::ix(
+            accounts::InitOrderBook {
+                config: config_pda(),
+                market: self.market,
+                payer: *payer,
+                order_book: seeded(b"order_book", &self.market),
+                collateral_mint: self.collateral_mint,
+                yes_token_mint: self.yes_mint,
+                no_token_mint: self.no_mint,
+                book_collateral_vault: seeded(b"book_collateral", &self.market),
+                book_yes_vault: seeded(b"book_yes", &self.market),
+                book_no_vault: seeded(b"book_no", &self.market),
+                market_authority: self.authority,
+                system_program: solana_sdk::system_program::ID,
+                token_program: self.token_program,
+                rent: solana_sdk::sysvar::rent::ID,
+            },
+            instruction::InitOrderBook {},
+        )
+    }
+
+    pub fn place_order_ix(&self, owner: &User, outcome: ShareOutcome, side: prediction_market::OrderSide, price: u64, shares: u64) -> Instruction {
+        let (owner_token_account, escrow_vault, mint) = match side {
+            prediction_market::OrderSide::Bid => (owner.collateral, seeded(b"book_collateral", &self.market), self.collateral_mint),
+            prediction_market::OrderSide::Ask => {
+                let mint = self.share_mint(&outcome);
+                let vault = match outcome {
+                    ShareOutcome::Yes => seeded(b"book_yes", &self.market),
+                    ShareOutcome::No => seeded(b"book_no", &self.market),
+                };
+                (owner.ata(&mint), vault, mint)
+            },
+        };
+        Self::ix(
+            accounts::PlaceOrder {
+                config: config_pda(),
+                market: self.market,
+                order_book: seeded(b"order_book", &self.market),
+                owner: owner.pubkey(),
+                owner_token_account,
+                escrow_vault,
+                mint,
+                token_program: self.token_program,
+            },
+            instruction::PlaceOrder { outcome, side, price, shares },
+        )
+    }
+
+    pub fn cancel_order_ix(&self, owner: &User, outcome: ShareOutcome, order_id: u64) -> Instruction {
+        let share_mint = self.share_mint(&outcome);
+        let book_share_vault = match outcome {
+            ShareOutcome::Yes => seeded(b"book_yes", &self.market),
+            ShareOutcome::No => seeded(b"book_no", &self.market),
+        };
+        Self::ix(
+            accounts::CancelOrder {
+                config: config_pda(),
+                market: self.market,
+                order_book: seeded(b"order_book", &self.market),
+                owner: owner.pubkey(),
+                owner_collateral_account: owner.collateral,
+                owner_share_account: owner.ata(&share_mint),
+                book_collateral_vault: seeded(b"book_collateral", &self.market),
+                book_share_vault,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                share_mint,
+                token_program: self.token_program,
+            },
+            instruction::CancelOrder { order_id },
+        )
+    }
+}
+
+/// Addresses of a categorical market.
+#[derive(Clone, Copy)]
+pub struct CategoricalKeys {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub creator_fee_vault: Pubkey,
+    pub protocol_fee_vault: Pubkey,
+    pub lp_mint: Pubkey,
+}
+
+impl CategoricalKeys {
+    pub fn new(creator: &Pubkey, market_id: u64, collateral_mint: Pubkey) -> Self {
+        let keys = MarketKeys::new(creator, market_id, collateral_mint);
+        Self {
+            market: keys.market,
+            authority: keys.authority,
+            collateral_mint,
+            collateral_vault: keys.collateral_vault,
+            creator_fee_vault: keys.creator_fee_vault,
+            protocol_fee_vault: keys.protocol_fee_vault,
+            lp_mint: keys.lp_mint,
+        }
+    }
+
+    pub fn create_ix(&self, creator: &User, args: instruction::CreateCategoricalMarket) -> Instruction {
+        MarketKeys::ix(
+            accounts::CreateCategoricalMarket {
+                config: config_pda(),
+                creator: creator.pubkey(),
+                market: self.market,
+                collateral_mint: self.collateral_mint,
+                market_authority: self.authority,
+                collateral_vault: self.collateral_vault,
+                creator_fee_vault: self.creator_fee_vault,
+                protocol_fee_vault: self.protocol_fee_vault,
+                lp_token_mint: self.lp_mint,
+                creator_lp_token_account: creator.ata(&self.lp_mint),
+                creator_collateral_account: creator.collateral,
+                system_program: solana_sdk::system_program::ID,
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                rent: solana_sdk::sysvar::rent::ID,
+            },
+            args,
+        )
+    }
+
+    pub fn init_outcome_mint_ix(&self, creator: &Pubkey, outcome_index: u8) -> Instruction {
+        MarketKeys::ix(
+            accounts::InitOutcomeMint {
+                config: config_pda(),
+                market: self.market,
+                creator: *creator,
+                collateral_mint: self.collateral_mint,
+                outcome_mint: outcome_mint_pda(&self.market, outcome_index),
+                market_authority: self.authority,
+                system_program: solana_sdk::system_program::ID,
+                token_program: spl_token::ID,
+                rent: solana_sdk::sysvar::rent::ID,
+            },
+            instruction::InitOutcomeMint { outcome_index },
+        )
+    }
+
+    fn trade_accounts(&self, user: &User, outcome_index: u8) -> accounts::TradeOutcomeShares {
+        let outcome_mint = outcome_mint_pda(&self.market, outcome_index);
+        accounts::TradeOutcomeShares {
+            config: config_pda(),
+            market: self.market,
+            user: user.pubkey(),
+            user_collateral_account: user.collateral,
+            user_outcome_token_account: user.ata(&outcome_mint),
+            collateral_vault: self.collateral_vault,
+            creator_fee_vault: self.creator_fee_vault,
+            protocol_fee_vault: self.protocol_fee_vault,
+            outcome_mint,
+            market_authority: self.authority,
+            collateral_mint: self.collateral_mint,
+            token_program: spl_token::ID,
+        }
+    }
+
+    pub fn buy_ix(&self, user: &User, outcome_index: u8, max_cost: u64, shares_desired: u64) -> Instruction {
+        MarketKeys::ix(self.trade_accounts(user, outcome_index), instruction::BuyOutcomeShares { outcome_index, max_cost, shares_desired })
+    }
+
+    pub fn sell_ix(&self, user: &User, outcome_index: u8, shares_to_sell: u64, min_payout: u64) -> Instruction {
+        MarketKeys::ix(self.trade_accounts(user, outcome_index), instruction::SellOutcomeShares { outcome_index, shares_to_sell, min_payout })
+    }
+
+    pub fn price_ix(&self, outcome_index: u8) -> Instruction {
+        MarketKeys::ix(accounts::GetMarketPrice { market: self.market }, instruction::GetOutcomePrice { outcome_index })
+    }
+
+    pub fn redeem_ix(&self, user: &User, outcome_index: u8, amount: u64) -> Instruction {
+        let mint = outcome_mint_pda(&self.market, outcome_index);
+        MarketKeys::ix(
+            accounts::RedeemWinnings {
+                config: config_pda(),
+                market: self.market,
+                user: user.pubkey(),
+                user_collateral_account: user.collateral,
+                user_winning_token_account: user.ata(&mint),
+                collateral_vault: self.collateral_vault,
+                winning_token_mint: mint,
+                market_authority: self.authority,
+                collateral_mint: self.collateral_mint,
+                token_program: spl_token::ID,
+            },
+            instruction::RedeemWinnings { amount },
+        )
+    }
+}
+
+/// Arguments for a manual binary market ending in `end_in` seconds.
+pub fn manual_market_args(market_id: u64, now: i64, end_in: i64, resolver: Pubkey) -> instruction::CreateMarket {
+    instruction::CreateMarket {
+        market_id,
+        question: "Will it rain tomorrow?".to_string(),
+        end_timestamp: now + end_in,
+        trading_close_timestamp: None,
+        market_type: MarketType::Manual,
+        resolution_source: resolver,
+        resolver: None,
+        initial_liquidity: INITIAL_LIQUIDITY,
+        liquidity_parameter: LIQUIDITY_PARAMETER,
+        fee_bps: None,
+        oracle_threashold: None,
+        oracle_threshold_expo: 0,
+        oracle_comparison: OracleComparison::Above,
+        oracle_max_age: 0,
+        oracle_max_conf_bps: 0,
+    }
+}
+
+/// The config every `TestEnv` starts with: `admin` as treasury and
+/// `collateral_mint` as the only allowed collateral.
+pub fn config_args(admin: &Pubkey, collateral_mint: Pubkey) -> instruction::InitializeConfig {
+    instruction::InitializeConfig {
+        treasury: *admin,
+        default_fee_bps: DEFAULT_FEE_BPS,
+        allowed_collateral_mints: vec![collateral_mint],
+        min_market_duration: 60,
+        max_market_duration: 365 * DAY,
+    }
+}
+
+/// Arguments for a binary oracle market on `feed` asking whether the price ends
+/// at or above `threshold` (expo 0).
+pub fn oracle_market_args(market_id: u64, now: i64, end_in: i64, feed: Pubkey, threshold: i64) -> instruction::CreateMarket {
+    instruction::CreateMarket {
+        market_type: MarketType::Oracle,
+        resolution_source: feed,
+        oracle_threashold: Some(threshold),
+        oracle_comparison: OracleComparison::EqualOrAbove,
+        oracle_max_age: 120,
+        oracle_max_conf_bps: 500,
+        ..manual_market_args(market_id, now, end_in, feed)
+    }
+}
+
+pub struct TestEnv {
+    pub ctx: ProgramTestContext,
+    pub admin: Keypair,
+    pub mint_authority: Keypair,
+    pub collateral_mint: Pubkey,
+}
+
+impl TestEnv {
+    /// Starts a bank with the program, an initialized config and one allowlisted
+    /// collateral mint.
+    pub async fn new() -> Self {
+        let program_test = ProgramTest::new("prediction_market", prediction_market::ID, processor!(process_instruction));
+        let ctx = program_test.start_with_context().await;
+        log_event_data();
+        let admin = ctx.payer.insecure_clone();
+
+        let mut env = Self {
+            ctx,
+            admin,
+            mint_authority: Keypair::new(),
+            collateral_mint: Pubkey::default(),
+        };
+        env.set_program_data(&env.admin.pubkey());
+        env.collateral_mint = env.create_mint().await;
+
+        env.send(vec![env.initialize_config_ix()], &[]).await.unwrap();
+        env
+    }
+
+    pub fn initialize_config_ix(&self) -> Instruction {
+        Instruction {
+            program_id: prediction_market::ID,
+            accounts: accounts::InitializeConfig {
+                admin: self.admin.pubkey(),
+                config: config_pda(),
+                program_data: program_data_address(),
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: config_args(&self.admin.pubkey(), self.collateral_mint).data(),
+        }
+    }
+
+    /// Native programs have no ProgramData account, so fake the upgradeable
+    /// loader's record naming `authority` as the upgrade authority.
+    pub fn set_program_data(&mut self, authority: &Pubkey) {
+        let mut data = vec![3, 0, 0, 0];
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(authority.as_ref());
+        self.ctx.set_account(
+            &program_data_address(),
+            &AccountSharedData::from(Account {
+                lamports: 1_000_000_000,
+                data,
+                owner: bpf_loader_upgradeable::ID,
+                executable: false,
+                rent_epoch: 0,
+            }),
+        );
+    }
+
+    pub async fn send(&mut self, instructions: Vec<Instruction>, signers: &[&Keypair]) -> TxResult {
+        let tx = self.transaction(&instructions, signers).await;
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Sends one instruction on its own, asserts it succeeded and returns the
+    /// compute units it consumed.
+    pub async fn compute_units(&mut self, instruction: Instruction, signers: &[&Keypair]) -> u64 {
+        self.send_with_metadata(&[instruction], signers).await.metadata.unwrap().compute_units_consumed
+    }
+
+    /// Sends a transaction, asserts it succeeded and returns its program logs.
+    pub async fn send_with_logs(&mut self, instructions: Vec<Instruction>, signers: &[&Keypair]) -> Vec<String> {
+        self.send_with_metadata(&instructions, signers).await.metadata.unwrap().log_messages
+    }
+
+    /// Metadata comes from executing straight against the working bank, which
+    /// can race the send service still retrying the previous transaction, so
+    /// retry while its accounts are locked.
+    async fn send_with_metadata(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> BanksTransactionResultWithMetadata {
+        loop {
+            let tx = self.transaction(instructions, signers).await;
+            let processed = self.ctx.banks_client.process_transaction_with_metadata(tx).await.unwrap();
+            match &processed.result {
+                Err(TransactionError::AccountInUse) => continue,
+                result => result.clone().unwrap(),
+            }
+            return processed;
+        }
+    }
+
+    async fn transaction(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Transaction {
+        let blockhash = self.fresh_blockhash().await;
+        let mut keys: Vec<&Keypair> = vec![&self.ctx.payer];
+        keys.extend(signers.iter().copied().filter(|signer| signer.pubkey() != self.ctx.payer.pubkey()));
+        Transaction::new_signed_with_payer(instructions, Some(&self.ctx.payer.pubkey()), &keys, blockhash)
+    }
+
+    /// A blockhash newer than the last one used. The bank registers one every few
+    /// milliseconds, so a cached hash soon expires, and never reusing one keeps
+    /// otherwise identical transactions from being deduplicated.
+    async fn fresh_blockhash(&mut self) -> Hash {
+        self.ctx.last_blockhash = self.ctx.banks_client.get_new_latest_blockhash(&self.ctx.last_blockhash).await.unwrap();
+        self.ctx.last_blockhash
+    }
+
+    /// Runs a view instruction and decodes its return data.
+    pub async fn view<T: AnchorDeserialize>(&mut self, instruction: Instruction) -> T {
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(&[instruction], Some(&self.ctx.payer.pubkey()), &[&self.ctx.payer], blockhash);
+        let simulation = self.ctx.banks_client.simulate_transaction(tx).await.unwrap();
+        simulation.result.unwrap().unwrap();
+        let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+        T::deserialize(&mut return_data.data.as_slice()).unwrap()
+    }
+
+    pub async fn view_u64(&mut self, instruction: Instruction) -> u64 {
+        self.view(instruction).await
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    pub async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    pub async fn create_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let instructions = vec![
+            system_instruction::create_account(
+                &self.ctx.payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &self.mint_authority.pubkey(), None, DECIMALS).unwrap(),
+        ];
+        self.send(instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates and allowlists a Token-2022 collateral mint that withholds
+    /// `fee_bps` of every transfer, capped at `max_fee`.
+    pub async fn create_transfer_fee_mint(&mut self, fee_bps: u16, max_fee: u64) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+        let instructions = vec![
+            system_instruction::create_account(&self.ctx.payer.pubkey(), &mint.pubkey(), rent.minimum_balance(len), len as u64, &spl_token_2022::ID),
+            transfer_fee_instruction::initialize_transfer_fee_config(&spl_token_2022::ID, &mint.pubkey(), None, None, fee_bps, max_fee).unwrap(),
+            spl_token_2022::instruction::initialize_mint2(&spl_token_2022::ID, &mint.pubkey(), &self.mint_authority.pubkey(), None, DECIMALS).unwrap(),
+            Instruction {
+                program_id: prediction_market::ID,
+                accounts: accounts::UpdateConfig { config: config_pda(), admin: self.admin.pubkey() }.to_account_metas(None),
+                data: instruction::AddCollateralMint { mint: mint.pubkey() }.data(),
+            },
+        ];
+        self.send(instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// Funds a fresh wallet with SOL and `collateral` units of the default mint.
+    pub async fn new_user(&mut self, collateral: u64) -> User {
+        let mint = self.collateral_mint;
+        self.new_user_with(&mint, &spl_token::ID, collateral).await
+    }
+
+    /// Funds a fresh wallet with SOL and `collateral` units of `mint`, owned by
+    /// `token_program`.
+    pub async fn new_user_with(&mut self, mint: &Pubkey, token_program: &Pubkey, collateral: u64) -> User {
+        let keypair = Keypair::new();
+        let user = User {
+            collateral: get_associated_token_address_with_program_id(&keypair.pubkey(), mint, token_program),
+            token_program: *token_program,
+            keypair,
+        };
+        let mint_authority = self.mint_authority.insecure_clone();
+        let instructions = vec![
+            system_instruction::transfer(&self.ctx.payer.pubkey(), &user.pubkey(), 10_000_000_000),
+            spl_associated_token_account::instruction::create_associated_token_account(&self.ctx.payer.pubkey(), &user.pubkey(), mint, token_program),
+            spl_token_2022::instruction::mint_to(token_program, mint, &user.collateral, &mint_authority.pubkey(), &[], collateral).unwrap(),
+        ];
+        self.send(instructions, &[&mint_authority]).await.unwrap();
+        user
+    }
+
+    /// Creates `owner`'s associated token accounts for `mints` if missing.
+    pub async fn create_atas(&mut self, owner: &Pubkey, mints: &[Pubkey]) {
+        self.create_atas_with(owner, mints, &spl_token::ID).await
+    }
+
+    async fn create_atas_with(&mut self, owner: &Pubkey, mints: &[Pubkey], token_program: &Pubkey) {
+        let payer = self.ctx.payer.pubkey();
+        let instructions = mints
+            .iter()
+            .map(|mint| spl_associated_token_account::instruction::create_associated_token_account_idempotent(&payer, owner, mint, token_program))
+            .collect();
+        self.send(instructions, &[]).await.unwrap();
+    }
+
+    /// Creates a manual market resolved by `creator` and gives `traders` share accounts.
+    pub async fn create_manual_market(&mut self, creator: &User, market_id: u64, end_in: i64) -> MarketKeys {
+        let now = self.now().await;
+        let args = manual_market_args(market_id, now, end_in, creator.pubkey());
+        self.create_market(creator, args).await.unwrap()
+    }
+
+    pub async fn create_market(&mut self, creator: &User, args: instruction::CreateMarket) -> Result<MarketKeys, BanksClientError> {
+        let keys = MarketKeys::new(&creator.pubkey(), args.market_id, self.collateral_mint);
+        self.send(vec![keys.create_ix(creator, args)], &[&creator.keypair]).await?;
+        Ok(keys)
+    }
+
+    /// Gives `user` YES, NO and LP token accounts for the market.
+    pub async fn open_positions(&mut self, keys: &MarketKeys, user: &User) {
+        self.create_atas_with(&user.pubkey(), &[keys.yes_mint, keys.no_mint, keys.lp_mint], &keys.token_program).await;
+    }
+
+    pub async fn market(&mut self, market: &Pubkey) -> Market {
+        let account = self.ctx.banks_client.get_account(*market).await.unwrap().unwrap();
+        Market::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn order_book(&mut self, market: &Pubkey) -> OrderBook {
+        let account = self.ctx.banks_client.get_account(seeded(b"order_book", market)).await.unwrap().unwrap();
+        OrderBook::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn config(&mut self) -> prediction_market::Config {
+        let account = self.ctx.banks_client.get_account(config_pda()).await.unwrap().unwrap();
+        prediction_market::Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn balance(&mut self, token_account: &Pubkey) -> u64 {
+        let account = self.ctx.banks_client.get_account(*token_account).await.unwrap().unwrap();
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+    }
+
+    pub async fn supply(&mut self, mint: &Pubkey) -> u64 {
+        let account = self.ctx.banks_client.get_account(*mint).await.unwrap().unwrap();
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data).unwrap().base.supply
+    }
+
+    /// Writes a Pyth v2 price account at `feed`.
+    pub fn set_pyth_price(&mut self, feed: &Pubkey, price: i64, conf: u64, expo: i32, publish_time: i64) {
+        let mut account = SolanaPriceAccount {
+            magic: MAGIC,
+            ver: VERSION_2,
+            atype: AccountType::Price as u32,
+            expo,
+            timestamp: publish_time,
+            ..Default::default()
+        };
+        account.agg.price = price;
+        account.agg.conf = conf;
+        account.agg.status = PriceStatus::Trading;
+        self.ctx.set_account(
+            feed,
+            &AccountSharedData::from(Account {
+                lamports: 1_000_000_000,
+                data: bytemuck::bytes_of(&account).to_vec(),
+                owner: Pubkey::new_unique(),
+                executable: false,
+                rent_epoch: 0,
+            }),
+        );
+    }
+}
+
+/// Whether the program runs from its compiled `.so` rather than natively, as
+/// under `cargo test-sbf`. Only then are its own compute units metered.
+pub fn runs_sbf() -> bool {
+    std::env::var("BPF_OUT_DIR").is_ok() || std::env::var("SBF_OUT_DIR").is_ok()
+}
+
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[prediction_market::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+/// Tiny deterministic PRNG so randomized runs are reproducible from a seed.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+pub fn resolver_single(key: Pubkey) -> Option<ResolverConfig> {
+    Some(ResolverConfig::Single(key))
+}
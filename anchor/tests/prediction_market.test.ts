@@ -6,10 +6,16 @@ import {
   Keypair, 
   SystemProgram,
   SYSVAR_RENT_PUBKEY,
-  Transaction
+  Transaction,
+  sendAndConfirmTransaction,
 } from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeMintInstruction,
   createMint,
   createAccount,
   mintTo,
@@ -29,28 +35,48 @@ describe("prediction_market", () => {
   let creator: Keypair;
   let user: Keypair;
   let collateralMint: PublicKey;
-  let market: Keypair;
-  let yesTokenMint: Keypair;
-  let noTokenMint: Keypair;
+  let market: PublicKey;
+  let yesTokenMint: PublicKey;
+  let noTokenMint: PublicKey;
   let marketAuthority: PublicKey;
-  let collateralVault: Keypair; // Keep as Keypair for signing
-  let collateralVaultPubkey: PublicKey;
+  let collateralVault: PublicKey;
   let creatorCollateralAccount: PublicKey;
   let userCollateralAccount: PublicKey;
   let userYesTokenAccount: PublicKey;
   let userNoTokenAccount: PublicKey;
 
   const INITIAL_LIQUIDITY = 1000;
+  // Must satisfy b * ln(2) <= 2 * initial liquidity
+  const LIQUIDITY_PARAMETER = 5000;
+  const FEE_BPS = 100; // 1%
   const MINT_AMOUNT = 10000;
 
+  // Derives the per-market PDAs seeded with the market key (fee vaults, LP mint)
+  const marketPda = (seed: string, marketPubkey: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from(seed), marketPubkey.toBuffer()],
+      program.programId
+    )[0];
+
+  // Markets are PDAs of their creator and a creator-chosen id; the outcome mints
+  // and the collateral vault are PDAs of the market
+  const marketAddress = (creatorPubkey: PublicKey, marketId: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), creatorPubkey.toBuffer(), new anchor.BN(marketId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const configPda = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId)[0];
+  const BPF_LOADER_UPGRADEABLE_ID = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+
   beforeAll(async () => {
     // Initialize keypairs
     creator = Keypair.generate();
     user = Keypair.generate();
-    market = Keypair.generate();
-    yesTokenMint = Keypair.generate();
-    noTokenMint = Keypair.generate();
-    collateralVault = Keypair.generate(); // This needs to be a Keypair for signing
+    market = marketAddress(creator.publicKey, 0);
+    yesTokenMint = marketPda("yes_mint", market);
+    noTokenMint = marketPda("no_mint", market);
+    collateralVault = marketPda("collateral_vault", market);
 
     // Airdrop SOL to accounts
     await provider.connection.requestAirdrop(creator.publicKey, 3 * anchor.web3.LAMPORTS_PER_SOL);
@@ -98,18 +124,37 @@ describe("prediction_market", () => {
       MINT_AMOUNT
     );
 
+    // The config is a singleton, so only the first run against a validator creates it
+    if (!(await program.account.config.fetchNullable(configPda))) {
+      await program.methods
+        .initializeConfig(provider.wallet.publicKey, FEE_BPS, [collateralMint], new anchor.BN(1), new anchor.BN(86400 * 365))
+        .accountsStrict({
+          admin: provider.wallet.publicKey,
+          config: configPda,
+          programData: PublicKey.findProgramAddressSync([program.programId.toBuffer()], BPF_LOADER_UPGRADEABLE_ID)[0],
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    } else {
+      const config = await program.account.config.fetch(configPda);
+      await program.methods
+        .updateConfig(config.treasury, config.defaultFeeBps, [...config.allowedCollateralMints.slice(-14), collateralMint], config.minMarketDuration, config.maxMarketDuration)
+        .accountsStrict({ config: configPda, admin: provider.wallet.publicKey })
+        .rpc();
+    }
+
     [marketAuthority] = PublicKey.findProgramAddressSync(
-      [Buffer.from("authority"), market.publicKey.toBuffer()],
+      [Buffer.from("authority"), market.toBuffer()],
       program.programId
     );
 
     userYesTokenAccount = await getAssociatedTokenAddressSync(
-      yesTokenMint.publicKey,
+      yesTokenMint,
       user.publicKey
     );
 
     userNoTokenAccount = await getAssociatedTokenAddressSync(
-      noTokenMint.publicKey,
+      noTokenMint,
       user.publicKey
     );
   });
@@ -121,48 +166,63 @@ describe("prediction_market", () => {
     
     const tx = await program.methods
       .createMarket(
+        new anchor.BN(0),
         question,
         endTimestamp,
+        null, // Trade until the end timestamp
         { manual: {} }, // MarketType::Manual
         resolutionSource,
+        null, // Creator resolves
         new anchor.BN(INITIAL_LIQUIDITY * 2), // Total initial liquidity
-        null // No oracle threshold for manual markets
+        new anchor.BN(LIQUIDITY_PARAMETER),
+        FEE_BPS,
+        null, // No oracle threshold for manual markets
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
       )
       .accountsStrict({
+        config: configPda,
         creator: creator.publicKey,
-        market: market.publicKey,
+        market: market,
         collateralMint: collateralMint,
-        yesTokenMint: yesTokenMint.publicKey,
-        noTokenMint: noTokenMint.publicKey,
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
         marketAuthority: marketAuthority,
-        collateralVault: collateralVault.publicKey, 
+        collateralVault: collateralVault, 
+        creatorFeeVault: marketPda("creator_fees", market),
+        protocolFeeVault: marketPda("protocol_fees", market),
+        lpTokenMint: marketPda("lp_mint", market),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", market), creator.publicKey),
         creatorCollateralAccount: creatorCollateralAccount,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         rent: SYSVAR_RENT_PUBKEY,
       })
-      .signers([creator, market, yesTokenMint, noTokenMint, collateralVault]) // Include collateralVault in signers
+      .signers([creator])
       .rpc();
 
     console.log("Create market tx:", tx);
 
-    const marketAccount = await program.account.market.fetch(market.publicKey);
+    const marketAccount = await program.account.market.fetch(market);
     console.log("marketAccount collateralVault: ", marketAccount.collateralVault.toBase58());
     
     // expect(marketAccount.creator.toString()).toEqual(creator.publicKey.toString());
     // expect(marketAccount.question).toEqual(question);
     // expect(marketAccount.resolved).toBe(false);
     // expect(marketAccount.winningOutcome).toEqual({ undecided: {} });
-    // expect(marketAccount.yesSharesOutstanding.toNumber()).toEqual(INITIAL_LIQUIDITY);
-    // expect(marketAccount.noSharesOutstanding.toNumber()).toEqual(INITIAL_LIQUIDITY);
-    // expect(marketAccount.totalLiquidity.toNumber()).toEqual(INITIAL_LIQUIDITY * 2);
+    // expect(marketAccount.yesSharesOutstanding.toNumber()).toEqual(0);
+    // expect(marketAccount.noSharesOutstanding.toNumber()).toEqual(0);
+    // expect(marketAccount.totalLiquidity.toNumber()).toEqual(INITIAL_LIQUIDITY * 4);
 
     const createYesTokenAccountTx = new Transaction().add(
       createAssociatedTokenAccountInstruction(
         user.publicKey, // payer
         userYesTokenAccount, // ata
         user.publicKey, // owner
-        yesTokenMint.publicKey // mint
+        yesTokenMint // mint
       )
     );
 
@@ -171,7 +231,7 @@ describe("prediction_market", () => {
         user.publicKey, // payer
         userNoTokenAccount, // ata
         user.publicKey, // owner
-        noTokenMint.publicKey // mint
+        noTokenMint // mint
       )
     );
 
@@ -190,15 +250,22 @@ describe("prediction_market", () => {
         new anchor.BN(sharesToBuy)
       )
       .accountsStrict({
-        market: market.publicKey,
+        config: configPda,
+        market: market,
         user: user.publicKey,
         userCollateralAccount: userCollateralAccount,
         userYesTokenAccount: userYesTokenAccount,
         userNoTokenAccount: userNoTokenAccount,
-        collateralVault: collateralVault.publicKey, // Use PublicKey here too
-        yesTokenMint: yesTokenMint.publicKey,
-        noTokenMint: noTokenMint.publicKey,
+        collateralVault: collateralVault, // Use PublicKey here too
+        creatorFeeVault: marketPda("creator_fees", market),
+        protocolFeeVault: marketPda("protocol_fees", market),
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
+        orderBook: null,
+        bookCollateralVault: null,
+        bookShareVault: null,
         marketAuthority: marketAuthority,
+        collateralMint: collateralMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])
@@ -211,8 +278,8 @@ describe("prediction_market", () => {
     // expect(Number(yesTokenAccount.amount)).toEqual(sharesToBuy);
 
     // Verify market state updated
-    // const marketAccount = await program.account.market.fetch(market.publicKey);
-    // expect(marketAccount.yesSharesOutstanding.toNumber()).toEqual(INITIAL_LIQUIDITY + sharesToBuy);
+    // const marketAccount = await program.account.market.fetch(market);
+    // expect(marketAccount.yesSharesOutstanding.toNumber()).toEqual(sharesToBuy);
   });
 
   it("Allows users to buy NO shares", async () => {
@@ -226,15 +293,22 @@ describe("prediction_market", () => {
         new anchor.BN(sharesToBuy)
       )
       .accountsStrict({
-        market: market.publicKey,
+        config: configPda,
+        market: market,
         user: user.publicKey,
         userCollateralAccount: userCollateralAccount,
         userYesTokenAccount: userYesTokenAccount,
         userNoTokenAccount: userNoTokenAccount,
-        collateralVault: collateralVault.publicKey,
-        yesTokenMint: yesTokenMint.publicKey,
-        noTokenMint: noTokenMint.publicKey,
+        collateralVault: collateralVault,
+        creatorFeeVault: marketPda("creator_fees", market),
+        protocolFeeVault: marketPda("protocol_fees", market),
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
+        orderBook: null,
+        bookCollateralVault: null,
+        bookShareVault: null,
         marketAuthority: marketAuthority,
+        collateralMint: collateralMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])
@@ -251,28 +325,21 @@ describe("prediction_market", () => {
     const sharesToSell = 25;
     
     // Get current market state
-    const marketData = await program.account.market.fetch(market.publicKey);
+    const marketData = await program.account.market.fetch(market);
     console.log("Market state before sell:");
     console.log("YES shares outstanding:", marketData.yesSharesOutstanding.toNumber());
     console.log("NO shares outstanding:", marketData.noSharesOutstanding.toNumber());
     console.log("Total liquidity:", marketData.totalLiquidity.toNumber());
     
-    // Calculate expected payout manually to understand the math
-    const PRICE_PRECISION = 1000;
-    const LIQUIDITY_PARAMETER = 1000;
-    
+    // LMSR payout: C(q) - C(q') with C(q) = b * ln(e^(yes/b) + e^(no/b))
+    const b = marketData.liquidityParameter.toNumber();
     const yesShares = marketData.yesSharesOutstanding.toNumber();
     const noShares = marketData.noSharesOutstanding.toNumber();
-    const totalShares = yesShares + noShares;
-    
-    const currentYesPrice = Math.floor((noShares * PRICE_PRECISION) / totalShares);
-    const priceImpact = Math.floor((sharesToSell * PRICE_PRECISION) / LIQUIDITY_PARAMETER);
-    const adjustedPrice = Math.max(0, currentYesPrice - priceImpact);
-    const expectedPayout = Math.floor((sharesToSell * adjustedPrice) / PRICE_PRECISION);
-    
-    console.log("Current YES price:", currentYesPrice);
-    console.log("Price impact:", priceImpact);  
-    console.log("Adjusted price:", adjustedPrice);
+    const cost = (yes: number, no: number) => b * Math.log(Math.exp(yes / b) + Math.exp(no / b));
+    const grossPayout = Math.floor(cost(yesShares, noShares) - cost(yesShares - sharesToSell, noShares));
+    const expectedPayout = grossPayout - Math.ceil((grossPayout * FEE_BPS) / 10000);
+
+    console.log("Liquidity parameter:", b);
     console.log("Expected payout:", expectedPayout);
     
     // Use a conservative minimum (90% of expected, minimum 1)
@@ -286,15 +353,22 @@ describe("prediction_market", () => {
         new anchor.BN(minPayout) // Use calculated minimum
       )
       .accountsStrict({
-        market: market.publicKey,
+        config: configPda,
+        market: market,
         user: user.publicKey,
         userCollateralAccount: userCollateralAccount,
         userYesTokenAccount: userYesTokenAccount,
         userNoTokenAccount: userNoTokenAccount,
-        collateralVault: collateralVault.publicKey,
-        yesTokenMint: yesTokenMint.publicKey,
-        noTokenMint: noTokenMint.publicKey,
+        collateralVault: collateralVault,
+        creatorFeeVault: marketPda("creator_fees", market),
+        protocolFeeVault: marketPda("protocol_fees", market),
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
+        orderBook: null,
+        bookCollateralVault: null,
+        bookShareVault: null,
         marketAuthority: marketAuthority,
+        collateralMint: collateralMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])
@@ -303,7 +377,7 @@ describe("prediction_market", () => {
     console.log("Sell shares tx:", tx);
 
     // Verify the sale worked
-    const updatedMarketData = await program.account.market.fetch(market.publicKey);
+    const updatedMarketData = await program.account.market.fetch(market);
     console.log("Updated YES shares outstanding:", updatedMarketData.yesSharesOutstanding.toNumber());
     console.log("Updated total liquidity:", updatedMarketData.totalLiquidity.toNumber());
     
@@ -312,11 +386,127 @@ describe("prediction_market", () => {
     console.log("User YES token balance after sell:", Number(yesTokenAccount.amount));
   });
 
+  it("Lets the creator claim accrued trading fees", async () => {
+    const creatorFeeVault = marketPda("creator_fees", market);
+    const accrued = (await program.account.market.fetch(market)).creatorFeesAccrued.toNumber();
+    const balanceBefore = Number((await getAccount(provider.connection, creatorCollateralAccount)).amount);
+
+    await program.methods
+      .claimFees({ creator: {} })
+      .accountsStrict({
+        config: configPda,
+        market: market,
+        claimer: creator.publicKey,
+        feeVault: creatorFeeVault,
+        destination: creatorCollateralAccount,
+        marketAuthority: marketAuthority,
+        programData: null,
+        collateralMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([creator])
+      .rpc();
+
+    const balanceAfter = Number((await getAccount(provider.connection, creatorCollateralAccount)).amount);
+    expect(balanceAfter - balanceBefore).toEqual(accrued);
+  });
+
+  it("Mints and merges a complete set", async () => {
+    const amount = 40;
+    const accounts = {
+      config: configPda,
+      market: market,
+      user: user.publicKey,
+      userCollateralAccount: userCollateralAccount,
+      userYesTokenAccount: userYesTokenAccount,
+      userNoTokenAccount: userNoTokenAccount,
+      collateralVault: collateralVault,
+      yesTokenMint: yesTokenMint,
+      noTokenMint: noTokenMint,
+      marketAuthority: marketAuthority,
+      collateralMint: collateralMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    const yesBefore = Number((await getAccount(provider.connection, userYesTokenAccount)).amount);
+    const noBefore = Number((await getAccount(provider.connection, userNoTokenAccount)).amount);
+
+    await program.methods
+      .mintCompleteSet(new anchor.BN(amount))
+      .accountsStrict(accounts)
+      .signers([user])
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, userYesTokenAccount)).amount)).toEqual(yesBefore + amount);
+    expect(Number((await getAccount(provider.connection, userNoTokenAccount)).amount)).toEqual(noBefore + amount);
+
+    await program.methods
+      .mergeCompleteSet(new anchor.BN(amount))
+      .accountsStrict(accounts)
+      .signers([user])
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, userYesTokenAccount)).amount)).toEqual(yesBefore);
+    expect(Number((await getAccount(provider.connection, userNoTokenAccount)).amount)).toEqual(noBefore);
+  });
+
+  it("Adds and removes liquidity", async () => {
+    const lpTokenMint = marketPda("lp_mint", market);
+    const userLpTokenAccount = getAssociatedTokenAddressSync(lpTokenMint, user.publicKey);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        createAssociatedTokenAccountInstruction(user.publicKey, userLpTokenAccount, user.publicKey, lpTokenMint)
+      ),
+      [user]
+    );
+
+    const accounts = {
+      config: configPda,
+      market: market,
+      user: user.publicKey,
+      userCollateralAccount: userCollateralAccount,
+      userYesTokenAccount: userYesTokenAccount,
+      userNoTokenAccount: userNoTokenAccount,
+      userLpTokenAccount: userLpTokenAccount,
+      collateralVault: collateralVault,
+      yesTokenMint: yesTokenMint,
+      noTokenMint: noTokenMint,
+      lpTokenMint: lpTokenMint,
+      marketAuthority: marketAuthority,
+      collateralMint: collateralMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    const priceBefore = await program.methods.getMarketPrice({ yes: {} }).accountsStrict({ market: market }).view();
+
+    await program.methods
+      .addLiquidity(new anchor.BN(500), new anchor.BN(1))
+      .accountsStrict(accounts)
+      .signers([user])
+      .rpc();
+
+    const lpBalance = Number((await getAccount(provider.connection, userLpTokenAccount)).amount);
+    expect(lpBalance).toBeGreaterThan(0);
+
+    // Adding liquidity deepens the pool without moving the price
+    const priceAfter = await program.methods.getMarketPrice({ yes: {} }).accountsStrict({ market: market }).view();
+    expect(priceAfter.toNumber()).toEqual(priceBefore.toNumber());
+
+    await program.methods
+      .removeLiquidity(new anchor.BN(lpBalance), new anchor.BN(0))
+      .accountsStrict(accounts)
+      .signers([user])
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, userLpTokenAccount)).amount)).toEqual(0);
+  });
+
   it("Gets market price", async () => {
     const price = await program.methods
       .getMarketPrice({ yes: {} })
       .accountsStrict({
-        market: market.publicKey,
+        config: configPda,
+        market: market,
       })
       .view();
 
@@ -327,49 +517,66 @@ describe("prediction_market", () => {
 
   it("Resolves market manually", async () => {
     // Create a market that's already past its end time
-    const pastMarket = Keypair.generate();
-    const pastYesTokenMint = Keypair.generate();
-    const pastNoTokenMint = Keypair.generate();
-    const pastCollateralVault = Keypair.generate(); // Keep as Keypair for signing
+    const pastMarket = marketAddress(creator.publicKey, 1);
+    const pastYesTokenMint = marketPda("yes_mint", pastMarket);
+    const pastNoTokenMint = marketPda("no_mint", pastMarket);
+    const pastCollateralVault = marketPda("collateral_vault", pastMarket);
     
     const [pastMarketAuthority] = PublicKey.findProgramAddressSync(
-      [Buffer.from("authority"), pastMarket.publicKey.toBuffer()],
+      [Buffer.from("authority"), pastMarket.toBuffer()],
       program.programId
     );
 
-    // Create a market with past end time
-    const pastEndTime = new anchor.BN(Math.floor(Date.now() / 1000) - 100); // 100 seconds ago
+    // Create a market that ends almost immediately (end time must be in the future)
+    const pastEndTime = new anchor.BN(Math.floor(Date.now() / 1000) + 2);
     
     await program.methods
       .createMarket(
+        new anchor.BN(1),
         "Past market for resolution test",
         pastEndTime,
+        null, // Trade until the end timestamp
         { manual: {} },
         creator.publicKey,
+        null, // Creator resolves
         new anchor.BN(INITIAL_LIQUIDITY * 2),
-        null
+        new anchor.BN(LIQUIDITY_PARAMETER),
+        FEE_BPS,
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
       )
       .accountsStrict({
+        config: configPda,
         creator: creator.publicKey,
-        market: pastMarket.publicKey,
+        market: pastMarket,
         collateralMint: collateralMint,
-        yesTokenMint: pastYesTokenMint.publicKey,
-        noTokenMint: pastNoTokenMint.publicKey,
+        yesTokenMint: pastYesTokenMint,
+        noTokenMint: pastNoTokenMint,
         marketAuthority: pastMarketAuthority,
-        collateralVault: pastCollateralVault.publicKey, // Use PublicKey
+        collateralVault: pastCollateralVault, // Use PublicKey
+        creatorFeeVault: marketPda("creator_fees", pastMarket),
+        protocolFeeVault: marketPda("protocol_fees", pastMarket),
+        lpTokenMint: marketPda("lp_mint", pastMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", pastMarket), creator.publicKey),
         creatorCollateralAccount: creatorCollateralAccount,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         rent: SYSVAR_RENT_PUBKEY,
       })
-      .signers([creator, pastMarket, pastYesTokenMint, pastNoTokenMint, pastCollateralVault]) // Include vault in signers
+      .signers([creator])
       .rpc({ skipPreflight: true });
 
-    // Now resolve the past market
+    // Wait for the end time to pass, then resolve the market
+    await new Promise(resolve => setTimeout(resolve, 4000));
     const tx = await program.methods
       .resolveMarket({ yes: {} }) // Manual outcome: YES wins
       .accountsStrict({
-        market: pastMarket.publicKey,
+        config: configPda,
+        market: pastMarket,
         resolutionSource: creator.publicKey, // Manual resolution source
         resolver: creator.publicKey,
       })
@@ -379,57 +586,100 @@ describe("prediction_market", () => {
     console.log("Resolve market tx:", tx);
 
     // Verify market is resolved
-    // const marketAccount = await program.account.market.fetch(pastMarket.publicKey);
+    // const marketAccount = await program.account.market.fetch(pastMarket);
     // expect(marketAccount.resolved).toBe(true);
     // expect(marketAccount.winningOutcome).toEqual({ yes: {} });
+
+    // Nothing was traded, so the creator's LP position owns the whole vault
+    const pastLpTokenMint = marketPda("lp_mint", pastMarket);
+    const creatorLpTokenAccount = getAssociatedTokenAddressSync(pastLpTokenMint, creator.publicKey);
+    const lpBalance = Number((await getAccount(provider.connection, creatorLpTokenAccount)).amount);
+    const balanceBefore = Number((await getAccount(provider.connection, creatorCollateralAccount)).amount);
+
+    await program.methods
+      .withdrawRemainingLiquidity(new anchor.BN(lpBalance))
+      .accountsStrict({
+        config: configPda,
+        market: pastMarket,
+        user: creator.publicKey,
+        userCollateralAccount: creatorCollateralAccount,
+        userLpTokenAccount: creatorLpTokenAccount,
+        collateralVault: pastCollateralVault,
+        yesTokenMint: pastYesTokenMint,
+        noTokenMint: pastNoTokenMint,
+        lpTokenMint: pastLpTokenMint,
+        marketAuthority: pastMarketAuthority,
+        collateralMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([creator])
+      .rpc();
+
+    const balanceAfter = Number((await getAccount(provider.connection, creatorCollateralAccount)).amount);
+    expect(balanceAfter - balanceBefore).toEqual(INITIAL_LIQUIDITY * 4);
   });
 
   it("Fails to buy shares with zero amount", async () => {
     // Create a new unresolved market for this test
-    const newMarket = Keypair.generate();
-    const newYesTokenMint = Keypair.generate();
-    const newNoTokenMint = Keypair.generate();
-    const newCollateralVault = Keypair.generate(); // Keep as Keypair for signing
+    const newMarket = marketAddress(creator.publicKey, 2);
+    const newYesTokenMint = marketPda("yes_mint", newMarket);
+    const newNoTokenMint = marketPda("no_mint", newMarket);
+    const newCollateralVault = marketPda("collateral_vault", newMarket);
     
     const [newMarketAuthority] = PublicKey.findProgramAddressSync(
-      [Buffer.from("authority"), newMarket.publicKey.toBuffer()],
+      [Buffer.from("authority"), newMarket.toBuffer()],
       program.programId
     );
 
     // Create the new market
     await program.methods
       .createMarket(
+        new anchor.BN(2),
         "Test market for zero amount",
         new anchor.BN(Date.now() / 1000 + 86400),
+        null, // Trade until the end timestamp
         { manual: {} },
         creator.publicKey,
+        null, // Creator resolves
         new anchor.BN(INITIAL_LIQUIDITY * 2),
-        null
+        new anchor.BN(LIQUIDITY_PARAMETER),
+        FEE_BPS,
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
       )
       .accountsStrict({
+        config: configPda,
         creator: creator.publicKey,
-        market: newMarket.publicKey,
+        market: newMarket,
         collateralMint: collateralMint,
-        yesTokenMint: newYesTokenMint.publicKey,
-        noTokenMint: newNoTokenMint.publicKey,
+        yesTokenMint: newYesTokenMint,
+        noTokenMint: newNoTokenMint,
         marketAuthority: newMarketAuthority,
-        collateralVault: newCollateralVault.publicKey, // Use PublicKey
+        collateralVault: newCollateralVault, // Use PublicKey
+        creatorFeeVault: marketPda("creator_fees", newMarket),
+        protocolFeeVault: marketPda("protocol_fees", newMarket),
+        lpTokenMint: marketPda("lp_mint", newMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", newMarket), creator.publicKey),
         creatorCollateralAccount: creatorCollateralAccount,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         rent: SYSVAR_RENT_PUBKEY,
       })
-      .signers([creator, newMarket, newYesTokenMint, newNoTokenMint, newCollateralVault]) // Include vault in signers
+      .signers([creator])
       .rpc();
 
     // Create token accounts for the new mints
     const newUserYesTokenAccount = await getAssociatedTokenAddress(
-      newYesTokenMint.publicKey,
+      newYesTokenMint,
       user.publicKey
     );
 
     const newUserNoTokenAccount = await getAssociatedTokenAddress(
-      newNoTokenMint.publicKey,
+      newNoTokenMint,
       user.publicKey
     );
 
@@ -438,7 +688,7 @@ describe("prediction_market", () => {
         user.publicKey,
         newUserYesTokenAccount,
         user.publicKey,
-        newYesTokenMint.publicKey
+        newYesTokenMint
       )
     );
 
@@ -447,7 +697,7 @@ describe("prediction_market", () => {
         user.publicKey,
         newUserNoTokenAccount,
         user.publicKey,
-        newNoTokenMint.publicKey
+        newNoTokenMint
       )
     );
 
@@ -463,15 +713,22 @@ describe("prediction_market", () => {
           new anchor.BN(0) // Zero shares
         )
         .accountsStrict({
-          market: newMarket.publicKey,
+          config: configPda,
+          market: newMarket,
           user: user.publicKey,
           userCollateralAccount: userCollateralAccount,
           userYesTokenAccount: newUserYesTokenAccount,
           userNoTokenAccount: newUserNoTokenAccount,
-          collateralVault: newCollateralVault.publicKey,
-          yesTokenMint: newYesTokenMint.publicKey,
-          noTokenMint: newNoTokenMint.publicKey,
+          collateralVault: newCollateralVault,
+          creatorFeeVault: marketPda("creator_fees", newMarket),
+          protocolFeeVault: marketPda("protocol_fees", newMarket),
+          yesTokenMint: newYesTokenMint,
+          noTokenMint: newNoTokenMint,
+          orderBook: null,
+          bookCollateralVault: null,
+          bookShareVault: null,
           marketAuthority: newMarketAuthority,
+          collateralMint: collateralMint,
           tokenProgram: TOKEN_PROGRAM_ID
         })
         .signers([user])
@@ -483,4 +740,655 @@ describe("prediction_market", () => {
       expect(error.error.errorCode.code).toContain("ZeroAmount");
     }
   });
-});
\ No newline at end of file
+
+  it("Creates and trades a categorical market", async () => {
+    const catMarket = marketAddress(creator.publicKey, 3);
+    const catCollateralVault = marketPda("collateral_vault", catMarket);
+    const outcomeCount = 3;
+    const [catMarketAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), catMarket.toBuffer()],
+      program.programId
+    );
+    const outcomeMint = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("outcome_mint"), catMarket.toBuffer(), Buffer.from([index])],
+        program.programId
+      )[0];
+
+    // Funding is 500 per outcome, which covers b * ln(3) for b = 1000
+    await program.methods
+      .createCategoricalMarket(
+        new anchor.BN(3),
+        "Which team wins the league?",
+        new anchor.BN(Date.now() / 1000 + 86400),
+        null,
+        creator.publicKey,
+        null,
+        outcomeCount,
+        new anchor.BN(500),
+        new anchor.BN(1000),
+        FEE_BPS
+      )
+      .accountsStrict({
+        config: configPda,
+        creator: creator.publicKey,
+        market: catMarket,
+        collateralMint: collateralMint,
+        marketAuthority: catMarketAuthority,
+        collateralVault: catCollateralVault,
+        creatorFeeVault: marketPda("creator_fees", catMarket),
+        protocolFeeVault: marketPda("protocol_fees", catMarket),
+        lpTokenMint: marketPda("lp_mint", catMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", catMarket), creator.publicKey),
+        creatorCollateralAccount: creatorCollateralAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([creator])
+      .rpc();
+
+    for (let index = 0; index < outcomeCount; index++) {
+      await program.methods
+        .initOutcomeMint(index)
+        .accountsStrict({
+          config: configPda,
+          market: catMarket,
+          creator: creator.publicKey,
+          collateralMint: collateralMint,
+          outcomeMint: outcomeMint(index),
+          marketAuthority: catMarketAuthority,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .signers([creator])
+        .rpc();
+    }
+
+    const userOutcomeAccount = getAssociatedTokenAddressSync(outcomeMint(1), user.publicKey);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        createAssociatedTokenAccountInstruction(user.publicKey, userOutcomeAccount, user.publicKey, outcomeMint(1))
+      ),
+      [user]
+    );
+
+    await program.methods
+      .buyOutcomeShares(1, new anchor.BN(100), new anchor.BN(60))
+      .accountsStrict({
+        config: configPda,
+        market: catMarket,
+        user: user.publicKey,
+        userCollateralAccount: userCollateralAccount,
+        userOutcomeTokenAccount: userOutcomeAccount,
+        collateralVault: catCollateralVault,
+        creatorFeeVault: marketPda("creator_fees", catMarket),
+        protocolFeeVault: marketPda("protocol_fees", catMarket),
+        outcomeMint: outcomeMint(1),
+        marketAuthority: catMarketAuthority,
+        collateralMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, userOutcomeAccount)).amount)).toEqual(60);
+    const prices = await Promise.all(
+      [0, 1, 2].map(index =>
+        program.methods.getOutcomePrice(index).accountsStrict({ market: catMarket }).view()
+      )
+    );
+    expect(prices[1].toNumber()).toBeGreaterThan(prices[0].toNumber());
+    expect(prices[0].toNumber()).toEqual(prices[2].toNumber());
+  });
+
+  it("Creates a scalar market with LONG/SHORT mints", async () => {
+    const createScalar = async (lowerBound: number, upperBound: number) => {
+      const scalarMarket = marketAddress(creator.publicKey, 4);
+      const longMint = marketPda("yes_mint", scalarMarket);
+      const shortMint = marketPda("no_mint", scalarMarket);
+      const scalarVault = marketPda("collateral_vault", scalarMarket);
+      const [scalarAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), scalarMarket.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createScalarMarket(
+          new anchor.BN(4),
+          "Where will SOL/USD settle?",
+          new anchor.BN(Date.now() / 1000 + 86400),
+          null,
+          Keypair.generate().publicKey, // Pyth feed, never read by this test
+          new anchor.BN(lowerBound),
+          new anchor.BN(upperBound),
+          0,
+          new anchor.BN(200),
+          new anchor.BN(500),
+          FEE_BPS,
+          new anchor.BN(60),
+          500
+        )
+        .accountsStrict({
+          config: configPda,
+          creator: creator.publicKey,
+          market: scalarMarket,
+          collateralMint: collateralMint,
+          yesTokenMint: longMint,
+          noTokenMint: shortMint,
+          marketAuthority: scalarAuthority,
+          collateralVault: scalarVault,
+          creatorFeeVault: marketPda("creator_fees", scalarMarket),
+          protocolFeeVault: marketPda("protocol_fees", scalarMarket),
+          lpTokenMint: marketPda("lp_mint", scalarMarket),
+          creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", scalarMarket), creator.publicKey),
+          creatorCollateralAccount: creatorCollateralAccount,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .signers([creator])
+        .rpc();
+      return scalarMarket;
+    };
+
+    try {
+      await createScalar(200, 100);
+      expect("Should have failed with inverted bounds");
+    } catch (error: any) {
+      expect(error.error.errorCode.code).toContain("InvalidScalarBounds");
+    }
+
+    const scalarMarket = await createScalar(100, 200);
+    const marketAccount = await program.account.market.fetch(scalarMarket);
+    expect(marketAccount.marketKind).toEqual({ scalar: {} });
+    expect(marketAccount.scalarLowerBound.toNumber()).toEqual(100);
+    expect(marketAccount.scalarUpperBound.toNumber()).toEqual(200);
+  });
+
+  it("Fills sells against resting bids before the AMM", async () => {
+    const orderBook = marketPda("order_book", market);
+    const bookCollateralVault = marketPda("book_collateral", market);
+    const bookYesVault = marketPda("book_yes", market);
+
+    await program.methods
+      .initOrderBook()
+      .accountsStrict({
+        config: configPda,
+        market: market,
+        payer: user.publicKey,
+        orderBook,
+        collateralMint: collateralMint,
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
+        bookCollateralVault,
+        bookYesVault,
+        bookNoVault: marketPda("book_no", market),
+        marketAuthority: marketAuthority,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    // A bid at the top of the price range always beats the AMM quote
+    await program.methods
+      .placeOrder({ yes: {} }, { bid: {} }, new anchor.BN(990), new anchor.BN(10))
+      .accountsStrict({
+        config: configPda,
+        market: market,
+        orderBook,
+        owner: user.publicKey,
+        ownerTokenAccount: userCollateralAccount,
+        escrowVault: bookCollateralVault,
+        mint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    const sharesBefore = (await program.account.market.fetch(market)).yesSharesOutstanding.toNumber();
+    await program.methods
+      .sellShares({ yes: {} }, new anchor.BN(10), new anchor.BN(9))
+      .accountsStrict({
+        config: configPda,
+        market: market,
+        user: user.publicKey,
+        userCollateralAccount: userCollateralAccount,
+        userYesTokenAccount: userYesTokenAccount,
+        userNoTokenAccount: userNoTokenAccount,
+        collateralVault: collateralVault,
+        creatorFeeVault: marketPda("creator_fees", market),
+        protocolFeeVault: marketPda("protocol_fees", market),
+        yesTokenMint: yesTokenMint,
+        noTokenMint: noTokenMint,
+        orderBook,
+        bookCollateralVault,
+        bookShareVault: bookYesVault,
+        marketAuthority: marketAuthority,
+        collateralMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    // The whole sell went to the book, so the AMM state is untouched
+    const sharesAfter = (await program.account.market.fetch(market)).yesSharesOutstanding.toNumber();
+    expect(sharesAfter).toEqual(sharesBefore);
+    expect(Number((await getAccount(provider.connection, bookYesVault)).amount)).toEqual(10);
+
+    const book = await program.account.orderBook.fetch(orderBook);
+    await program.methods
+      .cancelOrder(book.orders[0].id)
+      .accountsStrict({
+        config: configPda,
+        market: market,
+        orderBook,
+        owner: user.publicKey,
+        ownerCollateralAccount: userCollateralAccount,
+        ownerShareAccount: userYesTokenAccount,
+        bookCollateralVault,
+        bookShareVault: bookYesVault,
+        marketAuthority: marketAuthority,
+        collateralMint: collateralMint,
+        shareMint: yesTokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    expect((await program.account.orderBook.fetch(orderBook)).orders).toHaveLength(0);
+    expect(Number((await getAccount(provider.connection, bookYesVault)).amount)).toEqual(0);
+  });
+
+  it("Resolves optimistically through a disputed proposal", async () => {
+    const optimisticMarket = marketAddress(user.publicKey, 0);
+    const optimisticYesMint = marketPda("yes_mint", optimisticMarket);
+    const optimisticNoMint = marketPda("no_mint", optimisticMarket);
+    const optimisticVault = marketPda("collateral_vault", optimisticMarket);
+    const [optimisticAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), optimisticMarket.toBuffer()],
+      program.programId
+    );
+    const bondVault = marketPda("bond_vault", optimisticMarket);
+    const BOND = 50;
+
+    await program.methods
+      .createMarket(
+        new anchor.BN(0),
+        "Optimistic market",
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3),
+        null,
+        { manual: {} },
+        user.publicKey,
+        null, // Creator resolves
+        new anchor.BN(100),
+        new anchor.BN(200),
+        FEE_BPS,
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
+      )
+      .accountsStrict({
+        config: configPda,
+        creator: user.publicKey,
+        market: optimisticMarket,
+        collateralMint: collateralMint,
+        yesTokenMint: optimisticYesMint,
+        noTokenMint: optimisticNoMint,
+        marketAuthority: optimisticAuthority,
+        collateralVault: optimisticVault,
+        creatorFeeVault: marketPda("creator_fees", optimisticMarket),
+        protocolFeeVault: marketPda("protocol_fees", optimisticMarket),
+        lpTokenMint: marketPda("lp_mint", optimisticMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", optimisticMarket), user.publicKey),
+        creatorCollateralAccount: userCollateralAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    // The creator's key arbitrates disputes on this market
+    await program.methods
+      .configureDispute(creator.publicKey, new anchor.BN(BOND), new anchor.BN(60))
+      .accountsStrict({
+        config: configPda,
+        market: optimisticMarket,
+        creator: user.publicKey,
+        collateralMint: collateralMint,
+        bondVault,
+        marketAuthority: optimisticAuthority,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    try {
+      await program.methods
+        .resolveMarket({ yes: {} })
+        .accountsStrict({
+          config: configPda,
+          market: optimisticMarket,
+          resolutionSource: user.publicKey,
+          resolver: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+      expect("Should have required optimistic resolution");
+    } catch (error: any) {
+      expect(error.error.errorCode.code).toContain("OptimisticResolutionRequired");
+    }
+
+    const bondAccounts = {
+      config: configPda,
+      market: optimisticMarket,
+      bonder: user.publicKey,
+      bonderCollateralAccount: userCollateralAccount,
+      bondVault,
+      collateralMint: collateralMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.methods.proposeResolution({ yes: {} }).accountsStrict(bondAccounts).signers([user]).rpc();
+    await program.methods.disputeResolution().accountsStrict(bondAccounts).signers([user]).rpc();
+    expect(Number((await getAccount(provider.connection, bondVault)).amount)).toEqual(BOND * 2);
+
+    const balanceBefore = Number((await getAccount(provider.connection, userCollateralAccount)).amount);
+    await program.methods
+      .arbitrateResolution({ no: {} })
+      .accountsStrict({
+        config: configPda,
+        market: optimisticMarket,
+        resolver: creator.publicKey,
+        winnerCollateralAccount: userCollateralAccount,
+        bondVault,
+        marketAuthority: optimisticAuthority,
+        collateralMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([creator])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(optimisticMarket);
+    expect(marketAccount.resolved).toBe(true);
+    expect(marketAccount.winningOutcome).toEqual({ no: {} });
+    const balanceAfter = Number((await getAccount(provider.connection, userCollateralAccount)).amount);
+    expect(balanceAfter - balanceBefore).toEqual(BOND * 2);
+  });
+
+  it("Resolves a committee market once the threshold agrees", async () => {
+    const committeeMarket = marketAddress(user.publicKey, 1);
+    const committeeYesMint = marketPda("yes_mint", committeeMarket);
+    const committeeNoMint = marketPda("no_mint", committeeMarket);
+    const committeeVault = marketPda("collateral_vault", committeeMarket);
+    const [committeeAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), committeeMarket.toBuffer()],
+      program.programId
+    );
+    const members = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+
+    await program.methods
+      .createMarket(
+        new anchor.BN(1),
+        "Committee market",
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3),
+        null,
+        { manual: {} },
+        user.publicKey,
+        { committee: { members: members.map(member => member.publicKey), threshold: 2 } },
+        new anchor.BN(100),
+        new anchor.BN(200),
+        FEE_BPS,
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
+      )
+      .accountsStrict({
+        config: configPda,
+        creator: user.publicKey,
+        market: committeeMarket,
+        collateralMint: collateralMint,
+        yesTokenMint: committeeYesMint,
+        noTokenMint: committeeNoMint,
+        marketAuthority: committeeAuthority,
+        collateralVault: committeeVault,
+        creatorFeeVault: marketPda("creator_fees", committeeMarket),
+        protocolFeeVault: marketPda("protocol_fees", committeeMarket),
+        lpTokenMint: marketPda("lp_mint", committeeMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", committeeMarket), user.publicKey),
+        creatorCollateralAccount: userCollateralAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    const vote = (member: Keypair, outcome: any) =>
+      program.methods
+        .voteResolution(outcome)
+        .accountsStrict({ config: configPda, market: committeeMarket, voter: member.publicKey })
+        .signers([member])
+        .rpc();
+
+    await vote(members[0], { yes: {} });
+    await vote(members[1], { no: {} });
+    expect((await program.account.market.fetch(committeeMarket)).resolved).toBe(false);
+
+    await vote(members[2], { no: {} });
+    const marketAccount = await program.account.market.fetch(committeeMarket);
+    expect(marketAccount.resolved).toBe(true);
+    expect(marketAccount.winningOutcome).toEqual({ no: {} });
+  });
+
+  it("Rejects trading while the protocol is paused", async () => {
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setPaused(paused)
+        .accountsStrict({ config: configPda, admin: provider.wallet.publicKey })
+        .rpc();
+
+    await setPaused(true);
+    try {
+      await program.methods
+        .buyShares({ yes: {} }, new anchor.BN(100), new anchor.BN(10))
+        .accountsStrict({
+          config: configPda,
+          market: market,
+          user: user.publicKey,
+          userCollateralAccount: userCollateralAccount,
+          userYesTokenAccount: userYesTokenAccount,
+          userNoTokenAccount: userNoTokenAccount,
+          collateralVault: collateralVault,
+          creatorFeeVault: marketPda("creator_fees", market),
+          protocolFeeVault: marketPda("protocol_fees", market),
+          yesTokenMint: yesTokenMint,
+          noTokenMint: noTokenMint,
+          orderBook: null,
+          bookCollateralVault: null,
+          bookShareVault: null,
+          marketAuthority: marketAuthority,
+          collateralMint: collateralMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      expect("Should have failed while paused");
+    } catch (error: any) {
+      expect(error.error.errorCode.code).toContain("ProtocolPaused");
+    } finally {
+      await setPaused(false);
+    }
+  });
+
+  it("Pauses and cancels a market before it ends", async () => {
+    const cancelMarket = marketAddress(user.publicKey, 2);
+    const cancelYesMint = marketPda("yes_mint", cancelMarket);
+    const cancelNoMint = marketPda("no_mint", cancelMarket);
+    const cancelVault = marketPda("collateral_vault", cancelMarket);
+    const [cancelAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), cancelMarket.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createMarket(
+        new anchor.BN(2),
+        "Will the called-off match be won by the home team?",
+        new anchor.BN(Date.now() / 1000 + 86400),
+        null,
+        { manual: {} },
+        user.publicKey,
+        null,
+        new anchor.BN(100),
+        new anchor.BN(200),
+        null, // Protocol default fee
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
+      )
+      .accountsStrict({
+        config: configPda,
+        creator: user.publicKey,
+        market: cancelMarket,
+        collateralMint: collateralMint,
+        yesTokenMint: cancelYesMint,
+        noTokenMint: cancelNoMint,
+        marketAuthority: cancelAuthority,
+        collateralVault: cancelVault,
+        creatorFeeVault: marketPda("creator_fees", cancelMarket),
+        protocolFeeVault: marketPda("protocol_fees", cancelMarket),
+        lpTokenMint: marketPda("lp_mint", cancelMarket),
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(marketPda("lp_mint", cancelMarket), user.publicKey),
+        creatorCollateralAccount: userCollateralAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([user])
+      .rpc();
+
+    const manageAccounts = { config: configPda, market: cancelMarket, creator: user.publicKey };
+    await program.methods.pauseMarket().accountsStrict(manageAccounts).signers([user]).rpc();
+    expect((await program.account.market.fetch(cancelMarket)).paused).toBe(true);
+    await program.methods.unpauseMarket().accountsStrict(manageAccounts).signers([user]).rpc();
+    expect((await program.account.market.fetch(cancelMarket)).paused).toBe(false);
+
+    await program.methods.cancelMarket().accountsStrict(manageAccounts).signers([user]).rpc();
+    const marketAccount = await program.account.market.fetch(cancelMarket);
+    expect(marketAccount.resolved).toBe(true);
+    expect(marketAccount.winningOutcome).toEqual({ canceled: {} });
+    // Nothing traded, so both sides refund at half a unit
+    expect(marketAccount.canceledYesRefundRate.toNumber()).toEqual(500_000_000);
+  });
+
+  it("Credits vaults the full amount for Token-2022 collateral with a transfer fee", async () => {
+    // 1% transfer fee, capped well above anything this test moves
+    const feeMint = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: creator.publicKey,
+          newAccountPubkey: feeMint.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(feeMint.publicKey, null, null, 100, BigInt(1_000_000), TOKEN_2022_PROGRAM_ID),
+        createInitializeMintInstruction(feeMint.publicKey, 6, creator.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [creator, feeMint]
+    );
+    const feeCollateralAccount = await createAccount(provider.connection, creator, feeMint.publicKey, creator.publicKey, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    await mintTo(provider.connection, creator, feeMint.publicKey, feeCollateralAccount, creator, MINT_AMOUNT, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    await program.methods
+      .addCollateralMint(feeMint.publicKey)
+      .accountsStrict({ config: configPda, admin: provider.wallet.publicKey })
+      .rpc();
+
+    const feeMarket = marketAddress(creator.publicKey, 5);
+    const feeYesMint = marketPda("yes_mint", feeMarket);
+    const feeNoMint = marketPda("no_mint", feeMarket);
+    const feeVault = marketPda("collateral_vault", feeMarket);
+    const [feeAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), feeMarket.toBuffer()],
+      program.programId
+    );
+    const lpMint = marketPda("lp_mint", feeMarket);
+
+    await program.methods
+      .createMarket(
+        new anchor.BN(5),
+        "Will the fee-bearing stablecoin hold its peg?",
+        new anchor.BN(Date.now() / 1000 + 86400),
+        null,
+        { manual: {} },
+        creator.publicKey,
+        null,
+        new anchor.BN(1000),
+        new anchor.BN(1000),
+        null,
+        null,
+        0,
+        { above: {} },
+        new anchor.BN(0),
+        0
+      )
+      .accountsStrict({
+        config: configPda,
+        creator: creator.publicKey,
+        market: feeMarket,
+        collateralMint: feeMint.publicKey,
+        yesTokenMint: feeYesMint,
+        noTokenMint: feeNoMint,
+        marketAuthority: feeAuthority,
+        collateralVault: feeVault,
+        creatorFeeVault: marketPda("creator_fees", feeMarket),
+        protocolFeeVault: marketPda("protocol_fees", feeMarket),
+        lpTokenMint: lpMint,
+        creatorLpTokenAccount: getAssociatedTokenAddressSync(lpMint, creator.publicKey, false, TOKEN_2022_PROGRAM_ID),
+        creatorCollateralAccount: feeCollateralAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([creator])
+      .rpc();
+
+    // The creator pays the transfer fee on top, so the vault holds exactly the funding
+    const vault = await getAccount(provider.connection, feeVault, undefined, TOKEN_2022_PROGRAM_ID);
+    expect(Number(vault.amount)).toEqual(2000);
+    expect((await program.account.market.fetch(feeMarket)).totalLiquidity.toNumber()).toEqual(2000);
+
+    await program.methods
+      .removeCollateralMint(feeMint.publicKey)
+      .accountsStrict({ config: configPda, admin: provider.wallet.publicKey })
+      .rpc();
+    const config = await program.account.config.fetch(configPda);
+    expect(config.allowedCollateralMints.map((mint) => mint.toBase58())).not.toContain(feeMint.publicKey.toBase58());
+  });
+});
@@ -2,27 +2,37 @@
 
 import { getPredictionMarketProgram, getPredictionMarketProgramId } from '@project/anchor'
 import { useConnection, useWallet } from '@solana/wallet-adapter-react'
-import { Cluster, ComputeBudgetProgram, Keypair, PublicKey, sendAndConfirmRawTransaction, sendAndConfirmTransaction, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction } from '@solana/web3.js'
+import { Cluster, ComputeBudgetProgram, PublicKey, sendAndConfirmRawTransaction, sendAndConfirmTransaction, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction } from '@solana/web3.js'
 import { useMutation, useQuery } from '@tanstack/react-query'
 import { useMemo } from 'react'
 import { useCluster } from '../cluster/cluster-data-access'
 import { useAnchorProvider } from '../solana/solana-provider'
 import { useTransactionToast } from '../use-transaction-toast'
 import { toast } from 'sonner'
-import { AccountLayout, createAssociatedTokenAccountInstruction, getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from '@solana/spl-token'
+import { AccountLayout, ASSOCIATED_TOKEN_PROGRAM_ID, createAssociatedTokenAccountInstruction, getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from '@solana/spl-token'
 import { BN } from 'bn.js'
 
 export type MarketType = { manual: {} } | { oracle: {} };
+export type OracleComparison = { above: {} } | { below: {} } | { equalOrAbove: {} };
 export type ShareOutcome = { yes: {} } | { no: {} };
 export type WinningOutcome = { undecided: {} } | { yes: {} } | { no: {} } | { canceled: {} };
 
 interface CreateMarketArgs {
+  // Defaults to the current time in ms, which is unique enough per creator
+  marketId?: number;
   question: string;
   endTimestamp: number;
+  tradingCloseTimestamp?: number;
   marketType: MarketType;
   resolutionSource: PublicKey;
   initialLiquidity: number;
+  liquidityParameter?: number;
+  feeBps?: number;
   oracleThreshold?: number;
+  oracleThresholdExpo?: number;
+  oracleComparison?: OracleComparison;
+  oracleMaxAge?: number;
+  oracleMaxConfBps?: number;
   creatorPubkey: PublicKey
 }
 
@@ -67,6 +77,7 @@ export function usePredictionMarketProgram() {
   const provider = useAnchorProvider()
   const programId = useMemo(() => getPredictionMarketProgramId(cluster.network as Cluster), [cluster])
   const program = useMemo(() => getPredictionMarketProgram(provider, programId), [provider, programId])
+  const configPda = useMemo(() => PublicKey.findProgramAddressSync([Buffer.from("config")], programId)[0], [programId])
   const { wallet, sendTransaction, signAllTransactions } = useWallet();
 
   const marketAccounts = useQuery({
@@ -82,14 +93,41 @@ export function usePredictionMarketProgram() {
   // below function just only be used by the creator only
   const createMarketFn = useMutation<string, Error, CreateMarketArgs>({
     mutationKey: ['market', 'create', { cluster }],
-    mutationFn: async ({ question, endTimestamp, marketType, resolutionSource, initialLiquidity, oracleThreshold, creatorPubkey }) => {
-      const marketKeypair = Keypair.generate();
-      const yesTokenMint = Keypair.generate();
-      const noTokenMint = Keypair.generate();
-      const collateralVault = Keypair.generate();
-      
+    mutationFn: async ({ marketId, question, endTimestamp, tradingCloseTimestamp, marketType, resolutionSource, initialLiquidity, liquidityParameter, feeBps, oracleThreshold, oracleThresholdExpo, oracleComparison, oracleMaxAge, oracleMaxConfBps, creatorPubkey }) => {
+      const id = new BN(marketId ?? Date.now());
+      const [market] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), creatorPubkey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const [yesTokenMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from("yes_mint"), market.toBuffer()],
+        program.programId
+      );
+      const [noTokenMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from("no_mint"), market.toBuffer()],
+        program.programId
+      );
+      const [collateralVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_vault"), market.toBuffer()],
+        program.programId
+      );
+
       const [marketAuthority] = PublicKey.findProgramAddressSync(
-        [Buffer.from("authority"), marketKeypair.publicKey.toBuffer()],
+        [Buffer.from("authority"), market.toBuffer()],
+        program.programId
+      );
+
+      const [creatorFeeVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("creator_fees"), market.toBuffer()],
+        program.programId
+      );
+      const [protocolFeeVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("protocol_fees"), market.toBuffer()],
+        program.programId
+      );
+
+      const [lpTokenMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_mint"), market.toBuffer()],
         program.programId
       );
 
@@ -102,27 +140,42 @@ export function usePredictionMarketProgram() {
 
       return await program.methods
         .createMarket(
+          id,
           question,
           new BN(endTimestamp),
+          tradingCloseTimestamp ? new BN(tradingCloseTimestamp) : null,
           marketType,
           resolutionSource,
+          null, // Creator resolves
           new BN(initialLiquidity * 2), // Total initial liquidity
-          oracleThreshold ? new BN(oracleThreshold) : null
+          // Deepest LMSR market the deposit covers: b * ln(2) <= 4 * initialLiquidity
+          new BN(liquidityParameter ?? Math.floor((initialLiquidity * 4) / Math.LN2) - 1),
+          feeBps ?? null, // Protocol default fee
+          oracleThreshold ? new BN(oracleThreshold) : null,
+          oracleThresholdExpo ?? 0,
+          oracleComparison ?? { above: {} },
+          new BN(oracleMaxAge ?? 60),
+          oracleMaxConfBps ?? 100
         )
         .accountsStrict({ 
+          config: configPda,
           creator: creatorPubkey,
-          market: marketKeypair.publicKey,
+          market: market,
           collateralMint: collateralMint,
-          yesTokenMint: yesTokenMint.publicKey,
-          noTokenMint: noTokenMint.publicKey,
+          yesTokenMint: yesTokenMint,
+          noTokenMint: noTokenMint,
           marketAuthority: marketAuthority,
-          collateralVault: collateralVault.publicKey,
+          collateralVault: collateralVault,
+          creatorFeeVault: creatorFeeVault,
+          protocolFeeVault: protocolFeeVault,
+          lpTokenMint: lpTokenMint,
+          creatorLpTokenAccount: getAssociatedTokenAddressSync(lpTokenMint, creatorPubkey),
           creatorCollateralAccount: creatorCollateralAccount,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY,
         })
-        .signers([marketKeypair, yesTokenMint, noTokenMint, collateralVault])
         .rpc()
       },
     onSuccess: async (signature) => {
@@ -138,6 +191,7 @@ export function usePredictionMarketProgram() {
   return {
     program,
     programId,
+    configPda,
     marketAccounts,
     getProgramAccount,
     createMarketFn
@@ -148,7 +202,7 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
   const { cluster } = useCluster()
   const { connection } = useConnection();
   const transactionToast = useTransactionToast()
-  const { program, marketAccounts } = usePredictionMarketProgram();
+  const { program, configPda, marketAccounts } = usePredictionMarketProgram();
   const { wallet } = useWallet();
 
   const buySharesFn = useMutation<string, Error, BuySharesArgs>({
@@ -212,15 +266,22 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
           new BN(sharesDesired)
         )
         .accountsStrict({
+          config: configPda,
           market: marketPubkey,
           user: userPubkey,
           userCollateralAccount: userCollateralAccount,
           userYesTokenAccount: userYesTokenAccount,
           userNoTokenAccount: userNoTokenAccount,
           collateralVault: marketAccount.collateralVault,
+          creatorFeeVault: marketAccount.creatorFeeVault,
+          protocolFeeVault: marketAccount.protocolFeeVault,
           yesTokenMint: marketAccount.yesTokenMint,
           noTokenMint: marketAccount.noTokenMint,
+          orderBook: null,
+          bookCollateralVault: null,
+          bookShareVault: null,
           marketAuthority: marketAccount.marketAuthority,
+          collateralMint: marketAccount.collateralMint,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .instruction();
@@ -356,15 +417,22 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
             new BN(minPayout)
           )
           .accountsStrict({
+            config: configPda,
             market: marketPubkey,
             user: userPubkey,
             userCollateralAccount: userCollateralAccount,
             userYesTokenAccount: userYesTokenAccount,
             userNoTokenAccount: userNoTokenAccount,
             collateralVault: marketAccount.collateralVault,
+            creatorFeeVault: marketAccount.creatorFeeVault,
+            protocolFeeVault: marketAccount.protocolFeeVault,
             yesTokenMint: marketAccount.yesTokenMint,
             noTokenMint: marketAccount.noTokenMint,
+            orderBook: null,
+            bookCollateralVault: null,
+            bookShareVault: null,
             marketAuthority: marketAccount.marketAuthority,
+            collateralMint: marketAccount.collateralMint,
             tokenProgram: TOKEN_PROGRAM_ID,
           })
           .instruction();
@@ -479,6 +547,7 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
       return await program.methods
         .resolveMarket(manualOutcome || null)
         .accountsStrict({
+          config: configPda,
           market: marketPubkey,
           resolutionSource: marketAccount.resolutionSource,
           resolver: resolverPubkey,
@@ -536,6 +605,7 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
       return await program.methods
         .redeemWinnings(new BN(amount))
         .accountsStrict({
+          config: configPda,
           market: marketPubkey,
           user: userPubkey,
           userCollateralAccount: userCollateralAccount,
@@ -543,6 +613,7 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
           collateralVault: marketAccount.collateralVault,
           winningTokenMint: winningTokenMint,
           marketAuthority: marketAccount.marketAuthority,
+          collateralMint: marketAccount.collateralMint,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .rpc();
@@ -564,6 +635,7 @@ export function usePredictionMarketProgramAccount({ account }: { account: Public
       return await program.methods
         .emergencyResolveMarket(outcome)
         .accountsStrict({
+          config: configPda,
           market: marketPubkey,
           resolver: resolverPubkey
         })